@@ -0,0 +1,119 @@
+use anyhow::{bail, Context};
+use aws_sdk_cloudformation::operation::describe_change_set::DescribeChangeSetOutput;
+use serde::Deserialize;
+use serde_json::json;
+use std::{fmt, process::Stdio, time::Duration};
+use tokio::io::AsyncWriteExt;
+use tracing::info;
+
+use crate::notifications::redact_url;
+use crate::proxy;
+use crate::views::ChangeSetView;
+
+// Approvals may wait for a human, on a ticket or in a chat
+const APPROVAL_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Clone, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum ApprovalHook {
+    Command {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    Webhook {
+        url: String,
+    },
+}
+
+// The arguments and the webhook path may hold secrets
+impl fmt::Display for ApprovalHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApprovalHook::Command { command, .. } => write!(f, "command {command}"),
+            ApprovalHook::Webhook { url } => write!(f, "webhook {}", redact_url(url)),
+        }
+    }
+}
+
+impl fmt::Debug for ApprovalHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalConfig {
+    #[serde(flatten)]
+    pub hook: ApprovalHook,
+    // Skip the interactive confirmation, the hook alone approves the change set
+    #[serde(default)]
+    pub replace_confirm: bool,
+}
+
+impl ApprovalConfig {
+    // The hook receives the change set as JSON, a command on its stdin and a webhook as the body
+    pub async fn request(
+        &self,
+        stack: &str,
+        change_set: &DescribeChangeSetOutput,
+    ) -> anyhow::Result<()> {
+        let payload = json!({
+            "stack": stack,
+            "changeSet": ChangeSetView::from(change_set),
+        });
+        info!(
+            "Waiting for the approval of the change set by {}",
+            self.hook
+        );
+        match &self.hook {
+            ApprovalHook::Command { command, args } => {
+                let mut child = tokio::process::Command::new(command)
+                    .args(args)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::inherit())
+                    .stderr(Stdio::inherit())
+                    .spawn()
+                    .with_context(|| format!("Unable to run the approval command {command}"))?;
+                let mut stdin = child.stdin.take().context("No stdin for the approval")?;
+                // A hook may decide without reading the change set, its exit status is the answer
+                match stdin.write_all(payload.to_string().as_bytes()).await {
+                    Err(err) if err.kind() != std::io::ErrorKind::BrokenPipe => {
+                        return Err(err.into())
+                    }
+                    _ => {}
+                }
+                drop(stdin);
+                let status = tokio::time::timeout(APPROVAL_TIMEOUT, child.wait())
+                    .await
+                    .context("The approval command timed out")??;
+                if !status.success() {
+                    bail!("Change set rejected by the approval command {command} ({status})");
+                }
+            }
+            ApprovalHook::Webhook { url } => {
+                let response = proxy::reqwest_client_builder()
+                    .timeout(APPROVAL_TIMEOUT)
+                    .build()?
+                    .post(url)
+                    .json(&payload)
+                    .send()
+                    .await
+                    .map_err(reqwest::Error::without_url)?;
+                if !response.status().is_success() {
+                    bail!(
+                        "Change set rejected by the approval webhook ({})",
+                        response.status()
+                    );
+                }
+            }
+        }
+        info!("Change set approved");
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+use chrono::{SecondsFormat, Utc};
+use serde::Serialize;
+use serde_json::json;
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+    sync::{Mutex, OnceLock},
+};
+use tracing::error;
+
+use crate::{aws_client, cloudformation_api::CloudFormationApi};
+
+// Set once from the command line, every command that changes a stack appends to it
+static AUDIT_LOG: OnceLock<Mutex<File>> = OnceLock::new();
+
+pub fn open(path: &Path) -> anyhow::Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let _ = AUDIT_LOG.set(Mutex::new(file));
+    Ok(())
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Confirmation {
+    Accepted,
+    Declined,
+    // Not asked, because of --yes or of an empty change set
+    Skipped,
+}
+
+impl Confirmation {
+    pub fn from_answer(answer: bool) -> Self {
+        if answer {
+            Self::Accepted
+        } else {
+            Self::Declined
+        }
+    }
+}
+
+pub struct AuditEvent<'a> {
+    pub operation: &'a str,
+    pub stack: &'a str,
+    pub change_set_arn: Option<&'a str>,
+    pub confirmation: Option<Confirmation>,
+    pub status: String,
+}
+
+pub async fn record(client: &impl CloudFormationApi, event: AuditEvent<'_>) {
+    let Some(file) = AUDIT_LOG.get() else {
+        return;
+    };
+    let identity = client.caller_identity().await.ok();
+    let record = json!({
+        "timestamp": Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        "operation": event.operation,
+        "user": aws_client::local_user(),
+        "caller": identity.as_ref().map(|identity| &identity.arn),
+        "account": identity.as_ref().map(|identity| &identity.account),
+        "region": client.region(),
+        "stack": event.stack,
+        "changeSetArn": event.change_set_arn,
+        "confirmation": event.confirmation,
+        "status": event.status,
+    });
+    let mut file = file.lock().unwrap();
+    if let Err(err) = writeln!(file, "{record}") {
+        error!(
+            "Unable to write the audit record of the {}: {err}",
+            event.operation
+        );
+    }
+}
@@ -1,53 +1,803 @@
-use std::{thread, time::Duration};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
-use anyhow::Context;
-use aws_config::BehaviorVersion;
+use anyhow::{bail, Context};
+use aws_config::{
+    retry::RetryConfig, sts::AssumeRoleProvider, timeout::TimeoutConfig, BehaviorVersion,
+    ConfigLoader, Region,
+};
+use aws_credential_types::{
+    provider::{ProvideCredentials, SharedCredentialsProvider},
+    Credentials,
+};
 use aws_sdk_cloudformation::{
+    error::{DisplayErrorContext, ProvideErrorMetadata},
     operation::{
         create_change_set::CreateChangeSetOutput, describe_change_set::DescribeChangeSetOutput,
+        describe_stack_drift_detection_status::DescribeStackDriftDetectionStatusOutput,
+        describe_stack_refactor::DescribeStackRefactorOutput,
         list_stack_resources::ListStackResourcesOutput,
     },
     types::{
-        ChangeSetStatus, ChangeSetSummary, ChangeSetType, ExecutionStatus, Stack, StackEvent,
-        StackStatus, StackSummary,
+        ChangeSetStatus, ChangeSetSummary, ChangeSetType, DeletionMode, ExecutionStatus, Export,
+        OnStackFailure, Parameter, RegistryType, ResourceMapping, RollbackConfiguration,
+        RollbackTrigger, Stack, StackDefinition, StackDriftDetectionStatus, StackEvent,
+        StackRefactorAction, StackResource, StackResourceDetail, StackResourceDrift,
+        StackResourceDriftStatus, StackResourceSummary, StackStatus, StackSummary, Tag,
+        TemplateStage,
     },
     Client,
 };
+use aws_sigv4::{
+    http_request::{sign, SignableBody, SignableRequest, SigningSettings},
+    sign::v4,
+};
 use chrono::Utc;
-use spinners::{Spinner, Spinners};
-use tracing::{debug, info};
+use clap::Args;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tracing::{debug, info, warn};
+
+use crate::{
+    commands::gc::parse_age,
+    durations::DurationHistory,
+    error::{PklformationError, Result},
+    events_log::EventsLog,
+    nested_stacks,
+    notifications::NotificationTarget,
+    partition,
+    progress::{self, Progress, ProgressStream, ResourceCounters},
+    proxy, sso,
+};
+
+const ACCESS_DENIED_CODES: &[&str] = &[
+    "AccessDenied",
+    "AccessDeniedException",
+    "UnauthorizedOperation",
+];
+
+// Credentials STS doesn't accept anymore, renewing them is the fix
+const INVALID_CREDENTIALS_CODES: &[&str] = &[
+    "ExpiredToken",
+    "ExpiredTokenException",
+    "InvalidClientTokenId",
+    "SignatureDoesNotMatch",
+];
+
+const CREDENTIALS_RETRY_INTERVAL: Duration = Duration::from_secs(15);
+const CREDENTIALS_RENEWAL_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+// Long enough to coalesce the describes of a command, shorter than the usual polling interval
+const DESCRIBE_CACHE_TTL: Duration = Duration::from_secs(2);
+
+fn credentials_expired(err: &PklformationError) -> bool {
+    matches!(
+        err,
+        PklformationError::Aws { code: Some(code), .. }
+            if INVALID_CREDENTIALS_CODES.contains(&code.as_str())
+    )
+}
+
+#[derive(Debug)]
+pub struct AccessDenied {
+    pub action: &'static str,
+    pub feature: &'static str,
+    pub message: String,
+}
+
+impl std::fmt::Display for AccessDenied {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Access denied: {} requires {} ({})",
+            self.feature, self.action, self.message
+        )
+    }
+}
+
+impl std::error::Error for AccessDenied {}
+
+pub trait RequirePermission<T> {
+    fn required_permission(self, action: &'static str, feature: &'static str) -> Result<T>;
+}
+
+impl<T, E> RequirePermission<T> for Result<T, E>
+where
+    E: ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
+{
+    fn required_permission(self, action: &'static str, feature: &'static str) -> Result<T> {
+        self.map_err(|err| match err.code() {
+            Some(code) if ACCESS_DENIED_CODES.contains(&code) => AccessDenied {
+                action,
+                feature,
+                message: err.message().unwrap_or("no details").to_owned(),
+            }
+            .into(),
+            code => PklformationError::Aws {
+                action,
+                code: code.map(str::to_string),
+                message: err
+                    .message()
+                    .map_or_else(|| DisplayErrorContext(&err).to_string(), str::to_string),
+                source: None,
+            },
+        })
+    }
+}
+
+const MAX_CHANGE_SET_DESCRIPTION: usize = 1024;
+
+fn parse_change_set_name(arg: &str) -> Result<String, String> {
+    let valid = arg.len() <= 128
+        && arg.starts_with(|c: char| c.is_ascii_alphabetic())
+        && arg.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    if valid {
+        Ok(arg.to_string())
+    } else {
+        Err("Change set names start with a letter, contain only letters, digits and dashes, and are at most 128 characters long".to_string())
+    }
+}
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct ChangeSetNaming {
+    /// Name of the change set, generated from the stack name and the time by default
+    #[arg(long, value_parser = parse_change_set_name)]
+    pub change_set_name: Option<String>,
+    /// Description of the change set, the user and the git commit by default
+    #[arg(long)]
+    pub description: Option<String>,
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let output = String::from_utf8(output.stdout).ok()?;
+    Some(output.trim().to_string()).filter(|output| !output.is_empty())
+}
+
+pub(crate) fn local_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+// Who created a change set and from which commit, for the audit trail of the stack
+fn default_description() -> String {
+    let user = local_user();
+    match command_output("git", &["rev-parse", "--short", "HEAD"]) {
+        Some(commit) => format!("Created by {user} from commit {commit}"),
+        None => format!("Created by {user}"),
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ChangeSetOptions {
+    pub parameters: BTreeMap<String, String>,
+    pub tags: BTreeMap<String, String>,
+    pub rollback_alarms: Vec<String>,
+    pub monitoring_minutes: Option<i32>,
+    pub on_stack_failure: Option<OnStackFailure>,
+    pub notification_arns: Vec<String>,
+    pub naming: ChangeSetNaming,
+}
+
+impl ChangeSetOptions {
+    // Appended to the change set description to find change sets created from the same inputs.
+    // The parameters are their identities: a rotated secret makes another change set, but its
+    // value never reaches the description, which anyone allowed to describe it can read.
+    pub fn fingerprint(
+        &self,
+        template: &str,
+        change_set_type: &ChangeSetType,
+        parameters: &BTreeMap<String, String>,
+    ) -> String {
+        // Every field is tagged with its section and prefixed with its length, so no other
+        // inputs can be concatenated into the same bytes
+        let mut hasher = Sha256::new();
+        let mut field = |section: &str, value: &str| {
+            for bytes in [section.as_bytes(), value.as_bytes()] {
+                hasher.update((bytes.len() as u64).to_be_bytes());
+                hasher.update(bytes);
+            }
+        };
+        field("type", change_set_type.as_str());
+        field("template", template);
+        for (key, value) in parameters {
+            field("parameter", key);
+            field("parameter-identity", value);
+        }
+        for (key, value) in &self.tags {
+            field("tag", key);
+            field("tag-value", value);
+        }
+        for arn in &self.rollback_alarms {
+            field("rollback-alarm", arn);
+        }
+        for arn in &self.notification_arns {
+            field("notification-arn", arn);
+        }
+        if let Some(monitoring_minutes) = self.monitoring_minutes {
+            field("monitoring-minutes", &monitoring_minutes.to_string());
+        }
+        if let Some(on_stack_failure) = &self.on_stack_failure {
+            field("on-stack-failure", on_stack_failure.as_str());
+        }
+        let fingerprint: String = hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        format!("[pklformation fingerprint {fingerprint}]")
+    }
+
+    pub fn description(
+        &self,
+        template: &str,
+        change_set_type: &ChangeSetType,
+        parameters: &BTreeMap<String, String>,
+    ) -> String {
+        let fingerprint = self.fingerprint(template, change_set_type, parameters);
+        let description = self
+            .naming
+            .description
+            .clone()
+            .unwrap_or_else(default_description);
+        let description: String = description
+            .chars()
+            .take(MAX_CHANGE_SET_DESCRIPTION - fingerprint.len() - 1)
+            .collect();
+        format!("{description} {fingerprint}")
+    }
+}
+
+// The value of a parameter and what stands for it in the fingerprint: the value itself, or the
+// reference and version of a resolved one
+struct ResolvedParameter {
+    value: String,
+    identity: String,
+}
 
 pub struct AwsClient {
     inner: Client,
+    quotas: aws_sdk_servicequotas::Client,
+    region: Option<String>,
+    // Set for LocalStack and the like, the services without an SDK client go there too
+    endpoint_url: Option<String>,
+    events_log: Option<Mutex<EventsLog>>,
+    sns: aws_sdk_sns::Client,
+    sts: aws_sdk_sts::Client,
+    ssm: aws_sdk_ssm::Client,
+    secrets: aws_sdk_secretsmanager::Client,
+    credentials: Option<SharedCredentialsProvider>,
+    notifications: Vec<NotificationTarget>,
+    progress_stream: Option<ProgressStream>,
+    // By the name or id they were described with, for the lifetime of the client
+    described_stacks: Mutex<HashMap<String, (Instant, Stack)>>,
+    // Most recent event logged of each stack, its older events are not read again
+    logged_events: Mutex<HashMap<String, String>>,
+}
+
+pub struct CallerIdentity {
+    pub account: String,
+    pub arn: String,
+}
+
+const ENDPOINT_URL_ENV: &str = "AWS_ENDPOINT_URL";
+
+const MAX_SESSION_NAME: usize = 64;
+
+static SDK_OPTIONS: OnceLock<SdkOptions> = OnceLock::new();
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct SdkOptions {
+    /// Attempts of each AWS request, the first one included, 3 by default
+    #[arg(long, global = true)]
+    pub max_attempts: Option<u32>,
+    /// Time to connect to the AWS endpoints, like 5s
+    #[arg(long, global = true, value_parser = parse_age)]
+    pub connect_timeout: Option<Duration>,
+    /// Time to wait for the first byte of each AWS response, like 30s
+    #[arg(long, global = true, value_parser = parse_age)]
+    pub read_timeout: Option<Duration>,
+    /// Time for each AWS request to complete, retries included, like 2m
+    #[arg(long, global = true, value_parser = parse_age)]
+    pub operation_timeout: Option<Duration>,
+    /// Assume this role for every AWS call, with the credentials of the environment
+    #[arg(long, global = true)]
+    pub role_arn: Option<String>,
+}
+
+// Durations are written like on the command line, 30s or 2m
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SdkConfig {
+    pub max_attempts: Option<u32>,
+    pub connect_timeout: Option<String>,
+    pub read_timeout: Option<String>,
+    pub operation_timeout: Option<String>,
+    pub role_arn: Option<String>,
+}
+
+impl SdkOptions {
+    // Command line values take precedence over the project file ones
+    pub fn with_config(&self, config: &SdkConfig) -> Result<Self> {
+        let duration = |value: &Option<String>, name: &str| {
+            value
+                .as_deref()
+                .map(parse_age)
+                .transpose()
+                .map_err(|err| anyhow::anyhow!("Invalid sdk.{name} in the project file: {err}"))
+        };
+        Ok(Self {
+            max_attempts: self.max_attempts.or(config.max_attempts),
+            connect_timeout: self
+                .connect_timeout
+                .or(duration(&config.connect_timeout, "connectTimeout")?),
+            read_timeout: self
+                .read_timeout
+                .or(duration(&config.read_timeout, "readTimeout")?),
+            operation_timeout: self
+                .operation_timeout
+                .or(duration(&config.operation_timeout, "operationTimeout")?),
+            role_arn: self.role_arn.clone().or_else(|| config.role_arn.clone()),
+        })
+    }
+
+    // Applied to every client created afterwards
+    pub fn apply(self) {
+        let _ = SDK_OPTIONS.set(self);
+    }
+}
+
+fn config_loader(region: Option<&str>) -> ConfigLoader {
+    let mut loader = aws_config::defaults(BehaviorVersion::latest());
+    if let Some(options) = SDK_OPTIONS.get() {
+        if let Some(max_attempts) = options.max_attempts {
+            loader = loader.retry_config(RetryConfig::standard().with_max_attempts(max_attempts));
+        }
+        // The timeouts left unset keep their default
+        let mut timeouts = TimeoutConfig::builder();
+        timeouts
+            .set_connect_timeout(options.connect_timeout)
+            .set_read_timeout(options.read_timeout)
+            .set_operation_timeout(options.operation_timeout);
+        loader = loader.timeout_config(timeouts.build());
+    }
+    // Newer SDKs read it themselves, it points every service at LocalStack or a proxy
+    if let Ok(endpoint_url) = std::env::var(ENDPOINT_URL_ENV) {
+        loader = loader.endpoint_url(endpoint_url);
+    }
+    if let Some(http_client) = proxy::http_client() {
+        loader = loader.http_client(http_client);
+    }
+    match region {
+        Some(region) => loader.region(Region::new(region.to_owned())),
+        None => loader,
+    }
+}
+
+// Session names show who assumed the role in CloudTrail, with the characters STS accepts
+fn session_name() -> String {
+    format!("pklformation-{}", local_user())
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || "+=,.@_-".contains(*c))
+        .take(MAX_SESSION_NAME)
+        .collect()
+}
+
+// The assumed role credentials are refreshed by the provider before they expire
+async fn assume(config: aws_config::SdkConfig, role_arn: &str) -> aws_config::SdkConfig {
+    let provider = AssumeRoleProvider::builder(role_arn)
+        .session_name(session_name())
+        .configure(&config)
+        .build()
+        .await;
+    config
+        .into_builder()
+        .credentials_provider(SharedCredentialsProvider::new(provider))
+        .build()
+}
+
+async fn load_config(loader: ConfigLoader) -> aws_config::SdkConfig {
+    let config = loader.load().await;
+    match SDK_OPTIONS
+        .get()
+        .and_then(|options| options.role_arn.as_deref())
+    {
+        Some(role_arn) => assume(config, role_arn).await,
+        None => config,
+    }
+}
+
+// Clients configured in code instead of by the environment, like the ones of an emulator
+#[derive(Default)]
+pub struct AwsClientBuilder {
+    region: Option<String>,
+    endpoint_url: Option<String>,
+    credentials: Option<Credentials>,
+}
+
+impl AwsClientBuilder {
+    pub fn region(mut self, region: impl Into<String>) -> Self {
+        self.region = Some(region.into());
+        self
+    }
+
+    pub fn endpoint_url(mut self, endpoint_url: impl Into<String>) -> Self {
+        self.endpoint_url = Some(endpoint_url.into());
+        self
+    }
+
+    pub fn credentials(
+        mut self,
+        access_key_id: impl Into<String>,
+        secret_access_key: impl Into<String>,
+    ) -> Self {
+        self.credentials = Some(Credentials::new(
+            access_key_id,
+            secret_access_key,
+            None,
+            None,
+            "pklformation",
+        ));
+        self
+    }
+
+    pub async fn build(self) -> AwsClient {
+        let mut loader = config_loader(self.region.as_deref());
+        if let Some(endpoint_url) = self.endpoint_url {
+            loader = loader.endpoint_url(endpoint_url);
+        }
+        if let Some(credentials) = self.credentials {
+            loader = loader.credentials_provider(credentials);
+        }
+        AwsClient::from_config(&load_config(loader).await)
+    }
 }
 
 impl AwsClient {
-    pub async fn new() -> Self {
-        let config = aws_config::load_defaults(BehaviorVersion::latest()).await;
+    pub async fn new(region: Option<&str>) -> Self {
+        Self::from_config(&load_config(config_loader(region)).await)
+    }
+
+    pub fn builder() -> AwsClientBuilder {
+        AwsClientBuilder::default()
+    }
+
+    // Assumed from the credentials of --role-arn when given
+    pub async fn assume_role(role_arn: &str, region: Option<&str>) -> Self {
+        let config = assume(load_config(config_loader(region)).await, role_arn).await;
+        Self::from_config(&config)
+    }
+
+    fn from_config(config: &aws_config::SdkConfig) -> Self {
         Self {
-            inner: aws_sdk_cloudformation::Client::new(&config),
+            inner: aws_sdk_cloudformation::Client::new(config),
+            quotas: aws_sdk_servicequotas::Client::new(config),
+            region: config.region().map(ToString::to_string),
+            endpoint_url: config.endpoint_url().map(str::to_string),
+            events_log: None,
+            sns: aws_sdk_sns::Client::new(config),
+            sts: aws_sdk_sts::Client::new(config),
+            ssm: aws_sdk_ssm::Client::new(config),
+            secrets: aws_sdk_secretsmanager::Client::new(config),
+            credentials: config.credentials_provider(),
+            notifications: Vec::new(),
+            progress_stream: None,
+            described_stacks: Mutex::new(HashMap::new()),
+            logged_events: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+
+    pub(crate) fn endpoint_url(&self) -> Option<&str> {
+        self.endpoint_url.as_deref()
+    }
+
+    // For the services without an SDK client, which sign their requests themselves
+    async fn credentials(&self) -> anyhow::Result<Credentials> {
+        let provider = self
+            .credentials
+            .as_ref()
+            .context("No AWS credentials configured")?;
+        Ok(provider.provide_credentials().await?)
+    }
+
+    // JSON protocol call signed with the credentials of the client, returns the status and the
+    // body, errors included
+    pub(crate) async fn signed_json_post(
+        &self,
+        service: &str,
+        region: &str,
+        url: &str,
+        target: &str,
+        body: Vec<u8>,
+    ) -> anyhow::Result<(reqwest::StatusCode, String)> {
+        let headers = [
+            ("content-type", "application/x-amz-json-1.1"),
+            ("x-amz-target", target),
+        ];
+        let identity = self.credentials().await?.into();
+        let params = v4::SigningParams::builder()
+            .identity(&identity)
+            .region(region)
+            .name(service)
+            .time(SystemTime::now())
+            .settings(SigningSettings::default())
+            .build()?
+            .into();
+        let signable = SignableRequest::new(
+            "POST",
+            url,
+            headers.iter().copied(),
+            SignableBody::Bytes(&body),
+        )?;
+        let (instructions, _) = sign(signable, &params)?.into_parts();
+
+        let mut builder = proxy::reqwest_client_builder()
+            .build()?
+            .post(url)
+            .body(body.clone());
+        for (name, value) in headers.iter().copied().chain(instructions.headers()) {
+            builder = builder.header(name, value);
+        }
+        let response = builder.send().await?;
+        let status = response.status();
+        Ok((status, response.text().await?))
+    }
+
+    // KMS JSON protocol call with the credentials of the client. Keys given by ARN are in their
+    // own region, the others in the region of the client.
+    pub(crate) async fn kms(
+        &self,
+        key_id: &str,
+        target: &str,
+        request: serde_json::Value,
+    ) -> anyhow::Result<serde_json::Value> {
+        let region = match key_id.strip_prefix("arn:") {
+            Some(arn) => arn.split(':').nth(2),
+            None => self.region(),
+        }
+        .context("No region to call KMS in")?
+        .to_string();
+        let partition =
+            partition::Partition::from_region(&region).unwrap_or(partition::Partition::Aws);
+        let url = format!("https://kms.{region}.{}/", partition.dns_suffix());
+        let (status, text) = self
+            .signed_json_post(
+                "kms",
+                &region,
+                &url,
+                &format!("TrentService.{target}"),
+                serde_json::to_vec(&request)?,
+            )
+            .await?;
+        let response: serde_json::Value =
+            serde_json::from_str(&text).context("Invalid KMS response")?;
+        if !status.is_success() {
+            bail!(
+                "KMS {target} with key {key_id} failed with {status}: {} {}",
+                response["__type"].as_str().unwrap_or_default(),
+                response["message"]
+                    .as_str()
+                    .or(response["Message"].as_str())
+                    .unwrap_or_default()
+            );
+        }
+        Ok(response)
+    }
+
+    pub async fn caller_identity(&self) -> Result<CallerIdentity> {
+        let identity = self
+            .sts
+            .get_caller_identity()
+            .send()
+            .await
+            .required_permission("sts:GetCallerIdentity", "Checking the credentials")?;
+        Ok(CallerIdentity {
+            account: identity.account().unwrap_or_default().to_string(),
+            arn: identity.arn().unwrap_or_default().to_string(),
+        })
+    }
+
+    // Fails fast with the fix rather than with the SDK error of the first call. An expired SSO
+    // session can be renewed on the way, the check is then done again.
+    pub async fn check_credentials(&self) -> Result<CallerIdentity> {
+        let mut credentials = self.credentials().await;
+        if credentials.is_err() && sso::offer_login() {
+            credentials = self.credentials().await;
+        }
+        if let Err(err) = credentials {
+            let message = match SDK_OPTIONS.get().and_then(|options| options.role_arn.as_deref()) {
+                Some(role_arn) => format!("unable to assume the role {role_arn}"),
+                None => "no AWS credentials found, configure them with `aws configure`, `aws sso login` or AWS_PROFILE".to_string(),
+            };
+            return Err(PklformationError::Aws {
+                action: "sts:GetCallerIdentity",
+                code: None,
+                message,
+                source: Some(err.into()),
+            });
+        }
+        let mut identity = self.sts.get_caller_identity().send().await;
+        let invalid =
+            |code: Option<&str>| code.is_some_and(|code| INVALID_CREDENTIALS_CODES.contains(&code));
+        if matches!(&identity, Err(err) if invalid(err.code())) && sso::offer_login() {
+            identity = self.sts.get_caller_identity().send().await;
+        }
+        let identity = match identity {
+            Ok(identity) => identity,
+            Err(err) => match err.code() {
+                Some(code) if invalid(Some(code)) => {
+                    return Err(PklformationError::Aws {
+                        action: "sts:GetCallerIdentity",
+                        code: Some(code.to_string()),
+                        message:
+                            "the AWS credentials are expired or invalid, renew them and try again"
+                                .to_string(),
+                        source: Some(Box::new(err)),
+                    });
+                }
+                _ => {
+                    return Err(err)
+                        .required_permission("sts:GetCallerIdentity", "Checking the credentials")
+                }
+            },
+        };
+        Ok(CallerIdentity {
+            account: identity.account().unwrap_or_default().to_string(),
+            arn: identity.arn().unwrap_or_default().to_string(),
+        })
+    }
+
+    // The operation goes on in CloudFormation whatever the credentials, the wait resumes once they
+    // are renewed: the SDK resolves them again as soon as the cached ones are expired
+    async fn wait_for_renewed_credentials(&self, target: &str) -> anyhow::Result<()> {
+        warn!("The AWS credentials expired while waiting for {target}, the operation goes on in CloudFormation");
+        if !sso::offer_login() {
+            info!("Renew the credentials, with `aws sso login` for instance, the wait resumes once they are valid");
+        }
+        let started = Instant::now();
+        loop {
+            match self.sts.get_caller_identity().send().await {
+                Ok(_) => {
+                    info!("Credentials renewed, resuming the wait for {target}");
+                    return Ok(());
+                }
+                Err(err) if started.elapsed() >= CREDENTIALS_RENEWAL_TIMEOUT => {
+                    return Err(PklformationError::Aws {
+                        action: "sts:GetCallerIdentity",
+                        code: err.code().map(str::to_string),
+                        message: format!(
+                            "the credentials were not renewed within {} minutes, {target} may still be in progress",
+                            CREDENTIALS_RENEWAL_TIMEOUT.as_secs() / 60
+                        ),
+                        source: Some(Box::new(err)),
+                    }
+                    .into());
+                }
+                Err(err) => debug!("Credentials still invalid: {err}"),
+            }
+            tokio::time::sleep(CREDENTIALS_RETRY_INTERVAL).await;
         }
     }
 
+    pub fn with_events_log(mut self, events_log: EventsLog) -> Self {
+        self.events_log = Some(Mutex::new(events_log));
+        self
+    }
+
+    pub fn with_progress_stream(mut self, progress_stream: Option<ProgressStream>) -> Self {
+        self.progress_stream = progress_stream;
+        self
+    }
+
+    pub fn with_notifications(mut self, notifications: Vec<NotificationTarget>) -> Self {
+        self.notifications = notifications;
+        self
+    }
+
+    pub fn notification_targets(&self) -> &[NotificationTarget] {
+        &self.notifications
+    }
+
+    pub async fn publish_notification(
+        &self,
+        topic_arn: &str,
+        subject: &str,
+        message: &str,
+    ) -> Result<()> {
+        let publish_result = self
+            .sns
+            .publish()
+            .topic_arn(topic_arn)
+            .subject(subject)
+            .message(message)
+            .send()
+            .await
+            .required_permission("sns:Publish", "Sending notifications to SNS")?;
+        debug!("Publish result: {publish_result:?}");
+        Ok(())
+    }
+
+    // With every change, the pages are merged so the validations and the display see them all
     pub async fn describe_change_set(
         &self,
         change_set_id: &str,
+    ) -> Result<DescribeChangeSetOutput> {
+        Ok(self
+            .describe_change_set_pages(None, change_set_id, true)
+            .await?)
+    }
+
+    // Change sets can be named relatively to their stack, or by their ARN
+    pub async fn describe_stack_change_set(
+        &self,
+        stack_name: &str,
+        change_set: &str,
+    ) -> Result<DescribeChangeSetOutput> {
+        Ok(self
+            .describe_change_set_pages(Some(stack_name), change_set, true)
+            .await?)
+    }
+
+    async fn describe_change_set_pages(
+        &self,
+        stack_name: Option<&str>,
+        change_set: &str,
+        all_pages: bool,
     ) -> anyhow::Result<DescribeChangeSetOutput> {
-        let describe_change_set = self
-            .inner
-            .describe_change_set()
-            .change_set_name(change_set_id)
-            .send()
+        let mut describe_change_set = self
+            .describe_change_set_page(stack_name, change_set, None)
             .await?;
+        while let Some(next_token) = describe_change_set.next_token.take().filter(|_| all_pages) {
+            debug!("Fetching the next page of changes of {change_set}");
+            let page = self
+                .describe_change_set_page(stack_name, change_set, Some(next_token))
+                .await?;
+            describe_change_set
+                .changes
+                .get_or_insert_with(Vec::new)
+                .extend(page.changes.unwrap_or_default());
+            describe_change_set.next_token = page.next_token;
+        }
         debug!("Change set desription: {:?}", &describe_change_set);
         Ok(describe_change_set)
     }
 
+    async fn describe_change_set_page(
+        &self,
+        stack_name: Option<&str>,
+        change_set: &str,
+        next_token: Option<String>,
+    ) -> anyhow::Result<DescribeChangeSetOutput> {
+        Ok(self
+            .inner
+            .describe_change_set()
+            .set_stack_name(stack_name.map(str::to_string))
+            .change_set_name(change_set)
+            .set_next_token(next_token)
+            .send()
+            .await
+            .required_permission("cloudformation:DescribeChangeSet", "Describing change sets")?)
+    }
+
+    // Only the status is needed, the first page has it
     pub async fn change_set_status(
         &self,
         change_set_id: &str,
-    ) -> anyhow::Result<(ChangeSetStatus, String)> {
-        let describe_change_set_output = self.describe_change_set(change_set_id).await?;
+    ) -> Result<(ChangeSetStatus, String)> {
+        let describe_change_set_output = self
+            .describe_change_set_pages(None, change_set_id, false)
+            .await?;
         Ok((
             describe_change_set_output
                 .status
@@ -61,45 +811,235 @@ impl AwsClient {
         ))
     }
 
-    pub async fn delete_change_set(&self, change_set_id: &str) -> anyhow::Result<()> {
+    pub async fn delete_change_set(&self, change_set_id: &str) -> Result<()> {
+        self.forget_stacks();
         let delete_change_set_result = self
             .inner
             .delete_change_set()
             .change_set_name(change_set_id)
             .send()
-            .await?;
+            .await
+            .required_permission("cloudformation:DeleteChangeSet", "Deleting change sets")?;
         debug!("Delete Change set resul: {:?}", &delete_change_set_result);
         Ok(())
     }
 
-    pub async fn describe_stack(&self, stack_name: &str) -> anyhow::Result<Stack> {
+    pub async fn describe_stack(&self, stack_name: &str) -> Result<Stack> {
+        if let Some((described, stack)) = self.described_stacks.lock().unwrap().get(stack_name) {
+            if described.elapsed() < DESCRIBE_CACHE_TTL {
+                return Ok(stack.clone());
+            }
+        }
+        let stack = self.fetch_stack(stack_name).await?;
+        self.described_stacks
+            .lock()
+            .unwrap()
+            .insert(stack_name.to_string(), (Instant::now(), stack.clone()));
+        Ok(stack)
+    }
+
+    async fn fetch_stack(&self, stack_name: &str) -> anyhow::Result<Stack> {
         let describe_stacks_output = self
             .inner
             .describe_stacks()
             .stack_name(stack_name)
             .send()
-            .await?;
+            .await
+            .required_permission("cloudformation:DescribeStacks", "Describing stacks")?;
         let stacks = describe_stacks_output.stacks.context("No stacks list")?;
         stacks.first().cloned().context("Empty stacks list")
     }
 
-    pub async fn list_stack_resources(
-        &self,
-        stack_name: &str,
-    ) -> anyhow::Result<ListStackResourcesOutput> {
+    // Called before every change to a stack, a stack can be cached under its name and its id
+    fn forget_stacks(&self) {
+        self.described_stacks.lock().unwrap().clear();
+    }
+
+    // Best effort, only used to point at the console when an operation fails
+    pub async fn stack_console_url(&self, stack_name: &str) -> Option<String> {
+        let stack = self.describe_stack(stack_name).await.ok()?;
+        partition::stack_console_url(stack.stack_id()?)
+    }
+
+    // DescribeStacks without a name returns every stack of the region, a page at a time
+    pub async fn describe_all_stacks(&self) -> Result<Vec<Stack>> {
+        self.inner
+            .describe_stacks()
+            .into_paginator()
+            .items()
+            .send()
+            .collect::<Result<Vec<_>, _>>()
+            .await
+            .required_permission("cloudformation:DescribeStacks", "Describing stacks")
+    }
+
+    pub async fn list_stack_resources(&self, stack_name: &str) -> Result<ListStackResourcesOutput> {
         let list_stack_resources_output = self
             .inner
             .list_stack_resources()
             .stack_name(stack_name)
             .send()
-            .await?;
+            .await
+            .required_permission(
+                "cloudformation:ListStackResources",
+                "Listing stack resources",
+            )?;
         Ok(list_stack_resources_output)
     }
 
-    pub async fn list_stacks(
+    pub async fn list_all_stack_resources(
+        &self,
+        stack_name: &str,
+    ) -> Result<Vec<StackResourceSummary>> {
+        let stack_resources = self
+            .inner
+            .list_stack_resources()
+            .stack_name(stack_name)
+            .into_paginator()
+            .items()
+            .send()
+            .collect::<Result<Vec<_>, _>>()
+            .await
+            .required_permission(
+                "cloudformation:ListStackResources",
+                "Listing stack resources",
+            )?;
+        Ok(stack_resources)
+    }
+
+    pub async fn describe_stack_resource(
+        &self,
+        stack_name: &str,
+        logical_resource_id: &str,
+    ) -> Result<StackResourceDetail> {
+        let describe_stack_resource_output = self
+            .inner
+            .describe_stack_resource()
+            .stack_name(stack_name)
+            .logical_resource_id(logical_resource_id)
+            .send()
+            .await
+            .required_permission(
+                "cloudformation:DescribeStackResource",
+                "Describing stack resources",
+            )?;
+        debug!("Describe stack resource result: {describe_stack_resource_output:?}");
+        Ok(describe_stack_resource_output
+            .stack_resource_detail
+            .context("Empty stack resource detail")?)
+    }
+
+    pub async fn describe_stack_resources_by_physical_id(
+        &self,
+        physical_resource_id: &str,
+    ) -> Result<Vec<StackResource>> {
+        let describe_stack_resources_result = self
+            .inner
+            .describe_stack_resources()
+            .physical_resource_id(physical_resource_id)
+            .send()
+            .await;
+        debug!("Describe stack resources result: {describe_stack_resources_result:?}");
+        match describe_stack_resources_result {
+            // CloudFormation answers with a ValidationError when no stack owns the resource
+            Err(err) if err.code() == Some("ValidationError") => Ok(vec![]),
+            result => Ok(result
+                .required_permission(
+                    "cloudformation:DescribeStackResources",
+                    "Searching resources by physical ID",
+                )?
+                .stack_resources()
+                .to_vec()),
+        }
+    }
+
+    pub async fn list_exports(&self) -> Result<Vec<Export>> {
+        let exports = self
+            .inner
+            .list_exports()
+            .into_paginator()
+            .items()
+            .send()
+            .collect::<Result<Vec<_>, _>>()
+            .await
+            .required_permission("cloudformation:ListExports", "Listing exports")?;
+        debug!("List exports result: {exports:?}");
+        Ok(exports)
+    }
+
+    pub async fn list_imports(&self, export_name: &str) -> Result<Vec<String>> {
+        let list_imports_result = self
+            .inner
+            .list_imports()
+            .export_name(export_name)
+            .into_paginator()
+            .items()
+            .send()
+            .collect::<Result<Vec<_>, _>>()
+            .await;
+        debug!("List imports result: {list_imports_result:?}");
+        match list_imports_result {
+            // CloudFormation answers with a ValidationError when the export is not imported
+            Err(err) if err.code() == Some("ValidationError") => Ok(vec![]),
+            result => {
+                result.required_permission("cloudformation:ListImports", "Checking export usage")
+            }
+        }
+    }
+
+    pub async fn resource_schema(&self, type_name: &str) -> Result<Option<String>> {
+        let describe_type_result = self
+            .inner
+            .describe_type()
+            .r#type(RegistryType::Resource)
+            .type_name(type_name)
+            .send()
+            .await;
+        match describe_type_result {
+            Ok(describe_type_output) => Ok(describe_type_output.schema),
+            Err(err) if err.code() == Some("TypeNotFoundException") => Ok(None),
+            Err(err) => Err(err).required_permission(
+                "cloudformation:DescribeType",
+                "Checking resource attributes",
+            ),
+        }
+    }
+
+    pub async fn get_template(
+        &self,
+        stack_name: &str,
+        template_stage: TemplateStage,
+    ) -> Result<String> {
+        let get_template_output = self
+            .inner
+            .get_template()
+            .stack_name(stack_name)
+            .template_stage(template_stage)
+            .send()
+            .await
+            .required_permission("cloudformation:GetTemplate", "Fetching deployed templates")?;
+        debug!("Get template result: {get_template_output:?}");
+        Ok(get_template_output
+            .template_body
+            .context("Empty template body")?)
+    }
+
+    pub async fn list_stacks(&self, status_filter: &[StackStatus]) -> Result<Vec<StackSummary>> {
+        let mut stacks = Vec::new();
+        self.list_stacks_by_page(status_filter, |page| {
+            stacks.extend(page);
+            true
+        })
+        .await?;
+        Ok(stacks)
+    }
+
+    // Each page is handed over as soon as it arrives, until on_page returns false
+    pub async fn list_stacks_by_page(
         &self,
         status_filter: &[StackStatus],
-    ) -> anyhow::Result<Vec<StackSummary>> {
+        mut on_page: impl FnMut(Vec<StackSummary>) -> bool,
+    ) -> Result<()> {
         let mut list_stacks_request_builder = self.inner.list_stacks();
 
         for status in status_filter {
@@ -107,11 +1047,75 @@ impl AwsClient {
                 list_stacks_request_builder.stack_status_filter(status.clone());
         }
 
-        let list_stacks_output = list_stacks_request_builder.send().await?;
-        Ok(list_stacks_output.stack_summaries().to_vec())
+        let mut pages = list_stacks_request_builder.into_paginator().send();
+        while let Some(page) = pages.next().await {
+            let page = page.required_permission("cloudformation:ListStacks", "Listing stacks")?;
+            if !on_page(page.stack_summaries().to_vec()) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // Every stack of the account, except the deleted ones
+    pub async fn list_existing_stacks(&self) -> Result<Vec<StackSummary>> {
+        let stacks = self
+            .inner
+            .list_stacks()
+            .into_paginator()
+            .items()
+            .send()
+            .collect::<Result<Vec<_>, _>>()
+            .await
+            .required_permission("cloudformation:ListStacks", "Listing stacks")?;
+        Ok(stacks
+            .into_iter()
+            .filter(|stack| stack.stack_status() != Some(&StackStatus::DeleteComplete))
+            .collect())
+    }
+
+    pub async fn count_active_stacks(&self) -> Result<usize> {
+        Ok(self.list_existing_stacks().await?.len())
     }
 
-    pub async fn stack_status(&self, stack_name: &str) -> anyhow::Result<(StackStatus, String)> {
+    pub async fn service_quota(&self, service_code: &str, quota_name: &str) -> Result<Option<f64>> {
+        let applied_quotas = self
+            .quotas
+            .list_service_quotas()
+            .service_code(service_code)
+            .into_paginator()
+            .items()
+            .send()
+            .collect::<Result<Vec<_>, _>>()
+            .await
+            .required_permission("servicequotas:ListServiceQuotas", "Checking quotas")?;
+        if let Some(quota) = applied_quotas
+            .iter()
+            .find(|quota| quota.quota_name() == Some(quota_name))
+        {
+            return Ok(quota.value());
+        }
+
+        let default_quotas = self
+            .quotas
+            .list_aws_default_service_quotas()
+            .service_code(service_code)
+            .into_paginator()
+            .items()
+            .send()
+            .collect::<Result<Vec<_>, _>>()
+            .await
+            .required_permission(
+                "servicequotas:ListAWSDefaultServiceQuotas",
+                "Checking quotas",
+            )?;
+        Ok(default_quotas
+            .iter()
+            .find(|quota| quota.quota_name() == Some(quota_name))
+            .and_then(|quota| quota.value()))
+    }
+
+    pub async fn stack_status(&self, stack_name: &str) -> Result<(StackStatus, String)> {
         let stack = self.describe_stack(stack_name).await?;
         Ok((
             stack
@@ -126,44 +1130,243 @@ impl AwsClient {
         ))
     }
 
+    async fn resolve_parameters(
+        &self,
+        options: &ChangeSetOptions,
+    ) -> anyhow::Result<BTreeMap<String, ResolvedParameter>> {
+        let mut parameters = BTreeMap::new();
+        for (key, value) in &options.parameters {
+            let value = self
+                .parameter_value(value)
+                .await
+                .with_context(|| format!("Unable to resolve the parameter {key}"))?;
+            parameters.insert(key.clone(), value);
+        }
+        Ok(parameters)
+    }
+
+    fn parameter_identities(
+        resolved: &BTreeMap<String, ResolvedParameter>,
+    ) -> BTreeMap<String, String> {
+        resolved
+            .iter()
+            .map(|(key, parameter)| (key.clone(), parameter.identity.clone()))
+            .collect()
+    }
+
     pub async fn create_or_update_change_set(
         &self,
         stack_name: &str,
         template: &str,
         change_set_type: ChangeSetType,
-    ) -> anyhow::Result<CreateChangeSetOutput> {
+        options: &ChangeSetOptions,
+    ) -> Result<CreateChangeSetOutput> {
+        self.forget_stacks();
         info!("{change_set_type:?} stack {stack_name}...");
-        let change_set_name = format!("{}-{}", stack_name, Utc::now().format("%Y%m%d-%H%M%S-%f"));
+        let change_set_name =
+            options.naming.change_set_name.clone().unwrap_or_else(|| {
+                format!("{}-{}", stack_name, Utc::now().format("%Y%m%d-%H%M%S-%f"))
+            });
         info!("Create change set {change_set_name}...");
+        let resolved = self.resolve_parameters(options).await?;
+        let parameters: Vec<_> = resolved
+            .iter()
+            .map(|(key, parameter)| {
+                Parameter::builder()
+                    .parameter_key(key)
+                    .parameter_value(&parameter.value)
+                    .build()
+            })
+            .collect();
+        let tags: Vec<_> = options
+            .tags
+            .iter()
+            .map(|(key, value)| Tag::builder().key(key).value(value).build())
+            .collect();
+        let rollback_configuration = (!options.rollback_alarms.is_empty()
+            || options.monitoring_minutes.is_some())
+        .then(|| {
+            RollbackConfiguration::builder()
+                .set_rollback_triggers(Some(
+                    options
+                        .rollback_alarms
+                        .iter()
+                        .map(|arn| {
+                            RollbackTrigger::builder()
+                                .arn(arn)
+                                .r#type("AWS::CloudWatch::Alarm")
+                                .build()
+                        })
+                        .collect(),
+                ))
+                .set_monitoring_time_in_minutes(options.monitoring_minutes)
+                .build()
+        });
         let changeset = self
             .inner
             .create_change_set()
             .stack_name(stack_name)
             .change_set_name(change_set_name.clone())
             .change_set_type(change_set_type.clone())
+            .description(options.description(
+                template,
+                &change_set_type,
+                &Self::parameter_identities(&resolved),
+            ))
             .template_body(template)
+            .set_parameters((!parameters.is_empty()).then_some(parameters))
+            .set_tags((!tags.is_empty()).then_some(tags))
+            .set_rollback_configuration(rollback_configuration)
+            .set_notification_arns(
+                (!options.notification_arns.is_empty()).then(|| options.notification_arns.clone()),
+            )
+            // CloudFormation only accepts a failure behavior when the stack is created
+            .set_on_stack_failure(
+                options
+                    .on_stack_failure
+                    .clone()
+                    .filter(|_| change_set_type == ChangeSetType::Create),
+            )
             .send()
-            .await?;
+            .await
+            .required_permission("cloudformation:CreateChangeSet", "Creating change sets")?;
+        if let Some(progress_stream) = self.progress_stream.as_ref() {
+            progress_stream.change_set_created(stack_name, changeset.id());
+        }
 
         info!("{change_set_type:?} change set {stack_name} done!");
         Ok(changeset)
     }
 
-    pub async fn execute_change_set(&self, change_set_id: &str) -> anyhow::Result<()> {
+    // Values like ssm:/path/to/param or secretsmanager:name:key are resolved here, so the
+    // secrets never need to be written in the project or parameter files
+    async fn parameter_value(&self, value: &str) -> anyhow::Result<ResolvedParameter> {
+        if let Some(name) = value.strip_prefix("ssm:") {
+            let parameter = self
+                .ssm
+                .get_parameter()
+                .name(name)
+                .with_decryption(true)
+                .send()
+                .await
+                .required_permission("ssm:GetParameter", "Resolving ssm: parameters")?;
+            let parameter = parameter
+                .parameter()
+                .with_context(|| format!("SSM parameter {name} has no value"))?;
+            return Ok(ResolvedParameter {
+                value: parameter
+                    .value()
+                    .with_context(|| format!("SSM parameter {name} has no value"))?
+                    .to_string(),
+                identity: format!("{value}@{}", parameter.version()),
+            });
+        }
+        let Some(secret) = value.strip_prefix("secretsmanager:") else {
+            return Ok(ResolvedParameter {
+                value: value.to_string(),
+                identity: value.to_string(),
+            });
+        };
+        // Secret ARNs contain colons themselves, the key is what follows their 7 parts
+        let (secret_id, key) = if secret.starts_with("arn:") {
+            let mut parts = secret.splitn(8, ':');
+            let secret_id: Vec<_> = parts.by_ref().take(7).collect();
+            (secret_id.join(":"), parts.next())
+        } else {
+            match secret.split_once(':') {
+                Some((secret_id, key)) => (secret_id.to_string(), Some(key)),
+                None => (secret.to_string(), None),
+            }
+        };
+        let secret_value = self
+            .secrets
+            .get_secret_value()
+            .secret_id(&secret_id)
+            .send()
+            .await
+            .required_permission(
+                "secretsmanager:GetSecretValue",
+                "Resolving secretsmanager: parameters",
+            )?;
+        let secret_string = secret_value
+            .secret_string()
+            .with_context(|| format!("Secret {secret_id} has no string value"))?;
+        let identity = format!("{value}@{}", secret_value.version_id().unwrap_or_default());
+        let Some(key) = key else {
+            return Ok(ResolvedParameter {
+                value: secret_string.to_string(),
+                identity,
+            });
+        };
+        let secret: serde_json::Value = serde_json::from_str(secret_string)
+            .with_context(|| format!("Secret {secret_id} is not a JSON object"))?;
+        let value = match secret.get(key) {
+            Some(serde_json::Value::String(value)) => value.clone(),
+            Some(value) => value.to_string(),
+            None => bail!("Secret {secret_id} has no key {key}"),
+        };
+        Ok(ResolvedParameter { value, identity })
+    }
+
+    // PutParameter refuses tags when it overwrites, they are added separately
+    pub async fn put_ssm_parameter(
+        &self,
+        name: &str,
+        value: &str,
+        tags: &[(&str, &str)],
+    ) -> Result<()> {
+        self.ssm
+            .put_parameter()
+            .name(name)
+            .value(value)
+            .r#type(aws_sdk_ssm::types::ParameterType::String)
+            .overwrite(true)
+            .send()
+            .await
+            .required_permission("ssm:PutParameter", "Publishing outputs to SSM")?;
+        let tags: Vec<_> = tags
+            .iter()
+            .map(|(key, value)| {
+                aws_sdk_ssm::types::Tag::builder()
+                    .key(*key)
+                    .value(*value)
+                    .build()
+            })
+            .collect::<Result<_, _>>()
+            .map_err(anyhow::Error::from)?;
+        self.ssm
+            .add_tags_to_resource()
+            .resource_type(aws_sdk_ssm::types::ResourceTypeForTagging::Parameter)
+            .resource_id(name)
+            .set_tags(Some(tags))
+            .send()
+            .await
+            .required_permission("ssm:AddTagsToResource", "Publishing outputs to SSM")?;
+        Ok(())
+    }
+
+    pub async fn execute_change_set(
+        &self,
+        change_set_id: &str,
+        disable_rollback: bool,
+    ) -> Result<()> {
+        self.forget_stacks();
         info!("Apply change set {change_set_id}!",);
         let execution_result = self
             .inner
             .execute_change_set()
             .change_set_name(change_set_id)
+            .set_disable_rollback(disable_rollback.then_some(true))
             .send()
-            .await?;
+            .await
+            .required_permission("cloudformation:ExecuteChangeSet", "Executing change sets")?;
 
         debug!("Execution result: {execution_result:?}");
         info!("Change Set {change_set_id} applied!");
         Ok(())
     }
 
-    pub async fn describe_stack_events(&self, stack: &str) -> anyhow::Result<Vec<StackEvent>> {
+    pub async fn describe_stack_events(&self, stack: &str) -> Result<Vec<StackEvent>> {
         info!("Describe stack events {stack}!",);
         let stack_events: Vec<_> = self
             .inner
@@ -173,27 +1376,174 @@ impl AwsClient {
             .items()
             .send()
             .collect::<Result<Vec<_>, _>>()
-            .await?;
+            .await
+            .required_permission(
+                "cloudformation:DescribeStackEvents",
+                "Describing stack events",
+            )?;
 
         debug!("Describe stack events result: {stack_events:?}");
         Ok(stack_events)
     }
 
-    pub async fn delete_stack(&self, stack_name: &str) -> anyhow::Result<()> {
+    // Events come most recent first, stopping early saves the older pages
+    pub async fn stack_events_by_page(
+        &self,
+        stack: &str,
+        mut on_page: impl FnMut(Vec<StackEvent>) -> bool,
+    ) -> Result<()> {
+        let mut pages = self
+            .inner
+            .describe_stack_events()
+            .stack_name(stack)
+            .into_paginator()
+            .send();
+        while let Some(page) = pages.next().await {
+            let page = page.required_permission(
+                "cloudformation:DescribeStackEvents",
+                "Describing stack events",
+            )?;
+            if !on_page(page.stack_events().to_vec()) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn recent_stack_events(&self, stack: &str) -> Result<Vec<StackEvent>> {
+        let describe_stack_events_output = self
+            .inner
+            .describe_stack_events()
+            .stack_name(stack)
+            .send()
+            .await
+            .required_permission(
+                "cloudformation:DescribeStackEvents",
+                "Describing stack events",
+            )?;
+        Ok(describe_stack_events_output.stack_events().to_vec())
+    }
+
+    // Pages back to the last event logged of the stack, or to the start of its operation the
+    // first time, an operation can add more events than a page between two polls
+    async fn unlogged_stack_events(&self, stack: &str) -> Result<Vec<StackEvent>> {
+        let logged = self.logged_events.lock().unwrap().get(stack).cloned();
+        let mut events = Vec::new();
+        self.stack_events_by_page(stack, |page| {
+            for event in page {
+                if logged.is_some() && event.event_id() == logged.as_deref() {
+                    return false;
+                }
+                let start = logged.is_none() && progress::starts_operation(stack, &event);
+                events.push(event);
+                if start {
+                    return false;
+                }
+            }
+            true
+        })
+        .await?;
+        if let Some(event_id) = events.first().and_then(StackEvent::event_id) {
+            self.logged_events
+                .lock()
+                .unwrap()
+                .insert(stack.to_string(), event_id.to_string());
+        }
+        Ok(events)
+    }
+
+    // The events of the stack itself are already fetched, the nested stacks are only looked for
+    // while one is in progress
+    async fn log_stack_events(&self, stack: &str, events: Vec<StackEvent>, nested: bool) {
+        if self.events_log.is_none() && self.progress_stream.is_none() {
+            return;
+        }
+        let mut streams = vec![(None, stack.to_string(), Some(events))];
+        if nested {
+            streams.extend(
+                nested_stacks::in_progress(self, stack)
+                    .await
+                    .into_iter()
+                    .map(|(path, stack_id)| (Some(path), stack_id, None)),
+            );
+        }
+        for (path, stack_id, events) in streams {
+            let events = match events {
+                Some(events) => Ok(events),
+                None => self.unlogged_stack_events(&stack_id).await,
+            };
+            let result = events.and_then(|events| {
+                if let Some(progress_stream) = self.progress_stream.as_ref() {
+                    progress_stream.stack_events(&events, path.as_deref());
+                }
+                match self.events_log.as_ref() {
+                    Some(events_log) => Ok(events_log
+                        .lock()
+                        .unwrap()
+                        .append(&events, path.as_deref())?),
+                    None => Ok(()),
+                }
+            });
+            if let Err(err) = result {
+                warn!("Unable to log the events of stack {stack_id}: {err}");
+            }
+        }
+    }
+
+    pub async fn delete_stack(&self, stack_name: &str) -> Result<()> {
+        self.forget_stacks();
         info!("Delete stack {stack_name}...");
         let deletation_result = self
             .inner
             .delete_stack()
             .stack_name(stack_name)
             .send()
-            .await?;
+            .await
+            .required_permission("cloudformation:DeleteStack", "Deleting stacks")?;
         debug!("Deletation result: {deletation_result:?}");
 
         info!("Stack {stack_name} deleted!");
         Ok(())
     }
 
-    fn stack_op_in_progres(status: &StackStatus) -> bool {
+    // Stacks stuck in DELETE_FAILED are deleted without the resources that failed to delete
+    pub async fn force_delete_stack(&self, stack_name: &str) -> Result<()> {
+        self.forget_stacks();
+        info!("Force delete stack {stack_name}...");
+        let deletation_result = self
+            .inner
+            .delete_stack()
+            .stack_name(stack_name)
+            .deletion_mode(DeletionMode::ForceDeleteStack)
+            .send()
+            .await
+            .required_permission("cloudformation:DeleteStack", "Deleting stacks")?;
+        debug!("Deletation result: {deletation_result:?}");
+        Ok(())
+    }
+
+    pub async fn update_termination_protection(
+        &self,
+        stack_name: &str,
+        enabled: bool,
+    ) -> Result<()> {
+        self.forget_stacks();
+        let update_result = self
+            .inner
+            .update_termination_protection()
+            .stack_name(stack_name)
+            .enable_termination_protection(enabled)
+            .send()
+            .await
+            .required_permission(
+                "cloudformation:UpdateTerminationProtection",
+                "Changing termination protection",
+            )?;
+        debug!("Update termination protection result: {update_result:?}");
+        Ok(())
+    }
+
+    pub fn stack_op_in_progres(status: &StackStatus) -> bool {
         matches!(
             status,
             StackStatus::CreateInProgress
@@ -208,6 +1558,13 @@ impl AwsClient {
         )
     }
 
+    // CloudFormation fails change sets without changes instead of creating them empty
+    pub fn is_empty_change_set(status: &ChangeSetStatus, reason: &str) -> bool {
+        *status == ChangeSetStatus::Failed
+            && (reason.contains("didn't contain changes")
+                || reason.contains("No updates are to be performed"))
+    }
+
     fn change_set_op_in_progres(status: &ChangeSetStatus) -> bool {
         matches!(
             status,
@@ -218,46 +1575,269 @@ impl AwsClient {
         )
     }
 
+    // Rollback triggers are monitored once every resource of the operation is complete
+    async fn rollback_monitoring(
+        &self,
+        stack_name: &str,
+        status: &StackStatus,
+        counters: &ResourceCounters,
+    ) -> Option<i32> {
+        if !matches!(
+            status,
+            StackStatus::CreateInProgress | StackStatus::UpdateInProgress
+        ) || !counters.all_complete()
+        {
+            return None;
+        }
+        self.describe_stack(stack_name)
+            .await
+            .ok()?
+            .rollback_configuration()?
+            .monitoring_time_in_minutes()
+            .filter(|minutes| *minutes > 0)
+    }
+
     pub async fn wait_until_stack_op_in_progress(
         &self,
         stack_name: &str,
         pool_interval: Duration,
-    ) -> anyhow::Result<(StackStatus, String)> {
+    ) -> Result<(StackStatus, String)> {
+        self.wait_until_stack_op_in_progress_or_deadline(stack_name, pool_interval, None)
+            .await
+    }
+
+    fn estimate(
+        stack_name: &str,
+        history: &DurationHistory,
+        counters: &ResourceCounters,
+    ) -> Option<String> {
+        if !history.has_stack(stack_name) {
+            return None;
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()?
+            .as_secs_f64();
+        history.estimate(stack_name, &counters.in_progress(now))
+    }
+
+    // Only the events since the last refresh are fetched, they are returned for the logs
+    async fn refresh_resource_counters(
+        &self,
+        stack_name: &str,
+        counters: &mut ResourceCounters,
+    ) -> Vec<StackEvent> {
+        let mut events = Vec::new();
+        let result = self
+            .stack_events_by_page(stack_name, |page| {
+                let more = counters.add_page(stack_name, &page);
+                events.extend(page);
+                more
+            })
+            .await;
+        match result {
+            Ok(()) => counters.refresh(),
+            Err(err) => {
+                debug!("Unable to count the resources of {stack_name}: {err}");
+                counters.discard();
+            }
+        }
+        events
+    }
+
+    fn waiting_message(
+        status: &StackStatus,
+        monitoring: Option<i32>,
+        estimate: Option<&str>,
+        counters: Option<&str>,
+        deadline: Option<Instant>,
+    ) -> String {
+        let waiting = counters
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("Waiting for {status:?}"));
+        let message = match (monitoring, estimate) {
+            (Some(minutes), _) => {
+                format!("Monitoring rollback triggers for up to {minutes} minute(s)")
+            }
+            (None, Some(estimate)) => format!("{waiting} ({estimate})"),
+            (None, None) => waiting,
+        };
+        match deadline {
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                format!("{message} ({} min left)", remaining.as_secs().div_ceil(60))
+            }
+            None => message,
+        }
+    }
+
+    // Returns the in progress status when the deadline is reached before the end of the operation
+    pub async fn wait_until_stack_op_in_progress_or_deadline(
+        &self,
+        stack_name: &str,
+        pool_interval: Duration,
+        deadline: Option<Instant>,
+    ) -> Result<(StackStatus, String)> {
+        let (status, reason) = self
+            .wait_for_stack_op(stack_name, pool_interval, deadline)
+            .await?;
+        if let Some(progress_stream) = self.progress_stream.as_ref() {
+            progress_stream.stack_status(stack_name, &status, &reason);
+        }
+        Ok((status, reason))
+    }
+
+    async fn wait_for_stack_op(
+        &self,
+        stack_name: &str,
+        pool_interval: Duration,
+        deadline: Option<Instant>,
+    ) -> Result<(StackStatus, String)> {
         let (mut status, mut reason) = self.stack_status(stack_name).await?;
 
         if Self::stack_op_in_progres(&status) {
-            let mut sp = Spinner::new(Spinners::Dots9, format!("Waiting for {status:?}"));
+            let history = DurationHistory::load();
+            let started = Instant::now();
+            let mut counters = ResourceCounters::new();
+            let mut monitoring = None;
+            let mut message = Self::waiting_message(&status, monitoring, None, None, deadline);
+            let mut sp = Progress::start(message.clone());
             loop {
+                // One pass over the new events a poll, for the counters, the estimate and the logs
+                let events = self
+                    .refresh_resource_counters(stack_name, &mut counters)
+                    .await;
+                let nested = counters.type_in_progress(nested_stacks::NESTED_STACK_TYPE);
+                self.log_stack_events(stack_name, events, nested).await;
                 if !Self::stack_op_in_progres(&status) {
                     sp.stop();
+                    if monitoring.is_some() && status.as_str().contains("ROLLBACK") {
+                        warn!(
+                            "Stack {stack_name} rolled back during the monitoring phase: {reason}"
+                        );
+                    }
                     return Ok((status, reason));
                 }
-                thread::sleep(pool_interval);
-                if let Ok((new_status, new_reason)) = self.stack_status(stack_name).await {
-                    status = new_status;
-                    reason = new_reason
-                } else {
-                    // return last know status
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    sp.stop();
                     return Ok((status, reason));
-                };
+                }
+                if monitoring.is_none() {
+                    monitoring = self
+                        .rollback_monitoring(stack_name, &status, &counters)
+                        .await;
+                    if let Some(minutes) = monitoring {
+                        info!("Resources of {stack_name} complete, monitoring the rollback triggers for {minutes} minute(s)");
+                    }
+                }
+                let estimate = Self::estimate(stack_name, &history, &counters);
+                let new_message = Self::waiting_message(
+                    &status,
+                    monitoring,
+                    estimate.as_deref(),
+                    counters.summary(started.elapsed()).as_deref(),
+                    deadline,
+                );
+                if new_message != message {
+                    sp.restart(new_message.clone());
+                    message = new_message;
+                }
+                tokio::time::sleep(pool_interval).await;
+                self.forget_stacks();
+                match self.stack_status(stack_name).await {
+                    Ok((new_status, new_reason)) => {
+                        status = new_status;
+                        reason = new_reason
+                    }
+                    Err(err) if credentials_expired(&err) => {
+                        sp.stop();
+                        self.wait_for_renewed_credentials(stack_name).await?;
+                        sp = Progress::start(message.clone());
+                    }
+                    // return last know status
+                    Err(_) => return Ok((status, reason)),
+                }
             }
         }
 
         Ok((status, reason))
     }
 
+    pub async fn continue_update_rollback(
+        &self,
+        stack_name: &str,
+        resources_to_skip: Vec<String>,
+    ) -> Result<()> {
+        self.forget_stacks();
+        info!("Continue update rollback of stack {stack_name}...");
+        let continue_result = self
+            .inner
+            .continue_update_rollback()
+            .stack_name(stack_name)
+            .set_resources_to_skip((!resources_to_skip.is_empty()).then_some(resources_to_skip))
+            .send()
+            .await
+            .required_permission(
+                "cloudformation:ContinueUpdateRollback",
+                "Recovering failed rollbacks",
+            )?;
+        debug!("Continue update rollback result: {continue_result:?}");
+        Ok(())
+    }
+
+    pub async fn cancel_update_stack(&self, stack_name: &str) -> Result<()> {
+        self.forget_stacks();
+        info!("Cancel update of stack {stack_name}...");
+        let cancel_result = self
+            .inner
+            .cancel_update_stack()
+            .stack_name(stack_name)
+            .send()
+            .await
+            .required_permission(
+                "cloudformation:CancelUpdateStack",
+                "Cancelling stack updates",
+            )?;
+        debug!("Cancel update result: {cancel_result:?}");
+        Ok(())
+    }
+
     pub async fn wait_until_change_set_op_in_progress(
         &self,
         change_set_id: &str,
         pool_interval: Duration,
-    ) -> anyhow::Result<(ChangeSetStatus, String)> {
+    ) -> Result<(ChangeSetStatus, String)> {
+        let (status, reason) = self
+            .wait_for_change_set_op(change_set_id, pool_interval)
+            .await?;
+        if let Some(progress_stream) = self.progress_stream.as_ref() {
+            progress_stream.change_set_status(change_set_id, &status, &reason);
+        }
+        Ok((status, reason))
+    }
+
+    async fn wait_for_change_set_op(
+        &self,
+        change_set_id: &str,
+        pool_interval: Duration,
+    ) -> Result<(ChangeSetStatus, String)> {
         let (status, reason) = self.change_set_status(change_set_id).await?;
 
         if Self::change_set_op_in_progres(&status) {
-            let mut sp = Spinner::new(Spinners::Dots9, format!("Waiting for {status:?}"));
+            let message = format!("Waiting for {status:?}");
+            let mut sp = Progress::start(message.clone());
             loop {
-                let (status, reason) = self.change_set_status(change_set_id).await?;
-                thread::sleep(pool_interval);
+                let (status, reason) = match self.change_set_status(change_set_id).await {
+                    Ok(status) => status,
+                    Err(err) if credentials_expired(&err) => {
+                        sp.stop();
+                        self.wait_for_renewed_credentials(change_set_id).await?;
+                        sp = Progress::start(message.clone());
+                        continue;
+                    }
+                    Err(err) => return Err(err),
+                };
+                tokio::time::sleep(pool_interval).await;
                 if !Self::change_set_op_in_progres(&status) {
                     sp.stop();
                     return Ok((status, reason));
@@ -268,20 +1848,204 @@ impl AwsClient {
         Ok((status, reason))
     }
 
-    pub async fn pending_change_set(
+    pub async fn detect_stack_drift(&self, stack_name: &str) -> Result<String> {
+        self.forget_stacks();
+        let detect_stack_drift_output = self
+            .inner
+            .detect_stack_drift()
+            .stack_name(stack_name)
+            .send()
+            .await
+            .required_permission("cloudformation:DetectStackDrift", "Detecting drift")?;
+        debug!("Detect stack drift result: {detect_stack_drift_output:?}");
+        Ok(detect_stack_drift_output
+            .stack_drift_detection_id
+            .context("Empty drift detection id")?)
+    }
+
+    pub async fn wait_for_drift_detection(
         &self,
-        stack_name: &str,
-    ) -> anyhow::Result<Option<ChangeSetSummary>> {
-        let list_change_set = self
+        stack_drift_detection_id: &str,
+        pool_interval: Duration,
+    ) -> Result<DescribeStackDriftDetectionStatusOutput> {
+        loop {
+            let detection = self
+                .inner
+                .describe_stack_drift_detection_status()
+                .stack_drift_detection_id(stack_drift_detection_id)
+                .send()
+                .await
+                .required_permission(
+                    "cloudformation:DescribeStackDriftDetectionStatus",
+                    "Detecting drift",
+                )?;
+            debug!("Describe stack drift detection status result: {detection:?}");
+            if detection.detection_status() != Some(&StackDriftDetectionStatus::DetectionInProgress)
+            {
+                return Ok(detection);
+            }
+            tokio::time::sleep(pool_interval).await;
+        }
+    }
+
+    pub async fn drifted_resources(&self, stack_name: &str) -> Result<Vec<StackResourceDrift>> {
+        let mut pages = self
+            .inner
+            .describe_stack_resource_drifts()
+            .stack_name(stack_name)
+            .stack_resource_drift_status_filters(StackResourceDriftStatus::Modified)
+            .stack_resource_drift_status_filters(StackResourceDriftStatus::Deleted)
+            .into_paginator()
+            .send();
+        let mut drifts = Vec::new();
+        while let Some(page) = pages.next().await {
+            let page = page.required_permission(
+                "cloudformation:DescribeStackResourceDrifts",
+                "Detecting drift",
+            )?;
+            drifts.extend(page.stack_resource_drifts().iter().cloned());
+        }
+        Ok(drifts)
+    }
+
+    pub async fn create_stack_refactor(
+        &self,
+        description: Option<&str>,
+        enable_stack_creation: bool,
+        resource_mappings: Vec<ResourceMapping>,
+        stack_definitions: Vec<StackDefinition>,
+    ) -> Result<String> {
+        let create_stack_refactor_output = self
+            .inner
+            .create_stack_refactor()
+            .set_description(description.map(str::to_string))
+            .set_enable_stack_creation(enable_stack_creation.then_some(true))
+            .set_resource_mappings(Some(resource_mappings))
+            .set_stack_definitions(Some(stack_definitions))
+            .send()
+            .await
+            .required_permission("cloudformation:CreateStackRefactor", "Refactoring stacks")?;
+        debug!("Create stack refactor result: {create_stack_refactor_output:?}");
+        Ok(create_stack_refactor_output
+            .stack_refactor_id
+            .context("Empty stack refactor id")?)
+    }
+
+    pub async fn describe_stack_refactor(
+        &self,
+        stack_refactor_id: &str,
+    ) -> Result<DescribeStackRefactorOutput> {
+        let describe_stack_refactor_output = self
+            .inner
+            .describe_stack_refactor()
+            .stack_refactor_id(stack_refactor_id)
+            .send()
+            .await
+            .required_permission("cloudformation:DescribeStackRefactor", "Refactoring stacks")?;
+        debug!("Describe stack refactor result: {describe_stack_refactor_output:?}");
+        Ok(describe_stack_refactor_output)
+    }
+
+    // Creation and execution of a refactor have their own status, the caller tells which one
+    pub async fn wait_for_stack_refactor(
+        &self,
+        stack_refactor_id: &str,
+        pool_interval: Duration,
+        in_progress: impl Fn(&DescribeStackRefactorOutput) -> bool,
+    ) -> Result<DescribeStackRefactorOutput> {
+        let mut stack_refactor = self.describe_stack_refactor(stack_refactor_id).await?;
+        if in_progress(&stack_refactor) {
+            let mut sp = Progress::start(format!("Waiting for stack refactor {stack_refactor_id}"));
+            while in_progress(&stack_refactor) {
+                tokio::time::sleep(pool_interval).await;
+                stack_refactor = self.describe_stack_refactor(stack_refactor_id).await?;
+            }
+            sp.stop();
+        }
+        Ok(stack_refactor)
+    }
+
+    pub async fn list_stack_refactor_actions(
+        &self,
+        stack_refactor_id: &str,
+    ) -> Result<Vec<StackRefactorAction>> {
+        let actions = self
+            .inner
+            .list_stack_refactor_actions()
+            .stack_refactor_id(stack_refactor_id)
+            .into_paginator()
+            .items()
+            .send()
+            .collect::<Result<Vec<_>, _>>()
+            .await
+            .required_permission(
+                "cloudformation:ListStackRefactorActions",
+                "Refactoring stacks",
+            )?;
+        debug!("List stack refactor actions result: {actions:?}");
+        Ok(actions)
+    }
+
+    pub async fn execute_stack_refactor(&self, stack_refactor_id: &str) -> Result<()> {
+        self.forget_stacks();
+        info!("Execute stack refactor {stack_refactor_id}");
+        let execute_stack_refactor_output = self
+            .inner
+            .execute_stack_refactor()
+            .stack_refactor_id(stack_refactor_id)
+            .send()
+            .await
+            .required_permission("cloudformation:ExecuteStackRefactor", "Refactoring stacks")?;
+        debug!("Execute stack refactor result: {execute_stack_refactor_output:?}");
+        Ok(())
+    }
+
+    pub async fn list_change_sets(&self, stack_name: &str) -> Result<Vec<ChangeSetSummary>> {
+        let change_sets = self
             .inner
             .list_change_sets()
             .stack_name(stack_name)
+            .into_paginator()
+            .items()
             .send()
-            .await?;
-        Ok(list_change_set
-            .summaries()
-            .iter()
-            .find(|cs| matches!(cs.execution_status, Some(ExecutionStatus::Available)))
-            .cloned())
+            .collect::<Result<Vec<_>, _>>()
+            .await
+            .required_permission("cloudformation:ListChangeSets", "Listing change sets")?;
+        debug!("List change sets result: {change_sets:?}");
+        Ok(change_sets)
+    }
+
+    pub async fn pending_change_set(&self, stack_name: &str) -> Result<Option<ChangeSetSummary>> {
+        Ok(self
+            .list_change_sets(stack_name)
+            .await?
+            .into_iter()
+            .find(|cs| matches!(cs.execution_status, Some(ExecutionStatus::Available))))
+    }
+
+    pub async fn reusable_change_set(
+        &self,
+        stack_name: &str,
+        template: &str,
+        change_set_type: &ChangeSetType,
+        options: &ChangeSetOptions,
+    ) -> Result<Option<ChangeSetSummary>> {
+        let resolved = self.resolve_parameters(options).await?;
+        let fingerprint = options.fingerprint(
+            template,
+            change_set_type,
+            &Self::parameter_identities(&resolved),
+        );
+        Ok(self
+            .list_change_sets(stack_name)
+            .await?
+            .into_iter()
+            .find(|cs| {
+                matches!(cs.execution_status, Some(ExecutionStatus::Available))
+                    && matches!(cs.status, Some(ChangeSetStatus::CreateComplete))
+                    && cs
+                        .description()
+                        .is_some_and(|description| description.ends_with(&fingerprint))
+            }))
     }
 }
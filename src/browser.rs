@@ -0,0 +1,44 @@
+use anyhow::{bail, Context};
+use std::process::{Command, Stdio};
+use tracing::{info, warn};
+
+// BROWSER is the usual override, the platform opener is used otherwise
+fn opener() -> (String, Vec<String>) {
+    if let Ok(browser) = std::env::var("BROWSER") {
+        return (browser, vec![]);
+    }
+    if cfg!(target_os = "macos") {
+        ("open".to_string(), vec![])
+    } else if cfg!(target_os = "windows") {
+        (
+            "cmd".to_string(),
+            vec!["/C".to_string(), "start".to_string(), String::new()],
+        )
+    } else {
+        ("xdg-open".to_string(), vec![])
+    }
+}
+
+fn try_open(url: &str) -> anyhow::Result<()> {
+    let (command, args) = opener();
+    let status = Command::new(&command)
+        .args(args)
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .with_context(|| format!("Unable to run {command}"))?;
+    if !status.success() {
+        bail!("{command} exited with {status}");
+    }
+    Ok(())
+}
+
+// Not opening the browser never fails the command, the url is printed instead
+pub fn open(url: &str) {
+    match try_open(url) {
+        Ok(()) => info!("Opened {url}"),
+        Err(err) => warn!("Unable to open the browser: {err:#}, open {url}"),
+    }
+}
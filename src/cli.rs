@@ -0,0 +1,1248 @@
+use std::path::PathBuf;
+
+use crate::audit_log;
+use crate::aws_client::{AwsClient, SdkOptions};
+use crate::commands::cancel::CancelCommand;
+use crate::commands::changesets::ChangesetsCommand;
+use crate::commands::compare::CompareCommand;
+use crate::commands::cost::CostCommand;
+use crate::commands::describe::DescribeCommand;
+use crate::commands::destroy_all::DestroyAllCommand;
+use crate::commands::destroy_select::{DestroySelectCommand, StackFilter};
+use crate::commands::diff::DiffCommand;
+use crate::commands::drift::DriftCommand;
+use crate::commands::eval::EvalCommand;
+use crate::commands::events::{EventFilter, EventsCommand};
+use crate::commands::execute::ExecuteCommand;
+use crate::commands::exports::ExportsCommand;
+use crate::commands::find_resource::FindResourceCommand;
+use crate::commands::gc::{parse_age, GcCommand};
+use crate::commands::graph::{GraphCommand, GraphFormat};
+use crate::commands::history::HistoryCommand;
+use crate::commands::list::{ListCommand, ListFilter, ListSort};
+use crate::commands::outputs::OutputsCommand;
+use crate::commands::preview::{PreviewCommand, PreviewOptions};
+use crate::commands::protect::ProtectCommand;
+use crate::commands::refactor::RefactorCommand;
+use crate::commands::resource::ResourceCommand;
+use crate::commands::self_update::SelfUpdateCommand;
+use crate::commands::stats::StatsCommand;
+use crate::commands::template::TemplateCommand;
+use crate::commands::tui::TuiCommand;
+use crate::commands::up_all::UpAllCommand;
+use crate::commands::validate::ValidateCommand;
+use crate::commands::version::VersionCommand;
+use crate::commands::watch::WatchCommand;
+use crate::display::{ChangeSetFormat, ColorChoice, Display, OutputFormat, StackTableLayout};
+use crate::error::{self, PklformationError};
+use crate::events_log::EventsLog;
+use crate::pager::Pager;
+use crate::partition;
+use crate::pkl::parse_key_value;
+use crate::progress::{ProgressFormat, ProgressStream, SpinnerStyle};
+use crate::project::Project;
+use crate::proxy;
+use crate::reports::ReportFormat;
+use crate::stack_outputs::{self, OutputsFormat};
+use crate::state_key;
+use crate::validation::Validator;
+
+use anyhow::bail;
+use aws_sdk_cloudformation::types::StackStatus;
+use clap::{ArgGroup, Parser, Subcommand};
+use std::{io::IsTerminal, process::ExitCode, time::Duration};
+use tracing::{info, span, Level};
+
+// The commands the LocalStack tests drive with a client of their own
+pub use crate::approval::{ApprovalConfig, ApprovalHook};
+pub use crate::aws_client::ChangeSetOptions;
+pub use crate::commands::destroy::{DestroyCommand, DestroyOptions};
+pub use crate::commands::up::{UpCommand, UpOptions};
+pub use crate::pkl::PklOptions;
+
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    #[arg(short, long, default_value = "5", value_parser = parse_duration)]
+    pool_interval: Duration,
+    #[arg(long, default_value = "PklFormation.pkl")]
+    project: PathBuf,
+    /// pkl executable used to evaluate the project file and the templates, defaults to $PKL_EXEC or pkl
+    #[arg(long)]
+    pkl_bin: Option<PathBuf>,
+    /// Apply the overlay of this environment from the project file
+    #[arg(long)]
+    env: Option<String>,
+    /// Keep read-only commands running, skipping what the credentials are not allowed to read
+    #[arg(long)]
+    allow_degraded: bool,
+    /// Format of the output of list, describe, preview and outputs
+    #[arg(short, long, global = true, value_enum, default_value_t)]
+    output: OutputFormat,
+    /// Color the output, auto disables colors when NO_COLOR is set or stdout is not a terminal
+    #[arg(long, global = true, value_enum, default_value_t)]
+    color: ColorChoice,
+    /// Report the progress with a spinner, or as one JSON object per line for automation
+    #[arg(long, global = true, value_enum, default_value_t)]
+    progress: ProgressFormat,
+    /// Style of the spinner, for the terminals that can't draw the default one, defaults to $PKLFORMATION_SPINNER or dots
+    #[arg(long, global = true, value_enum)]
+    spinner: Option<SpinnerStyle>,
+    /// Write the ndjson progress to this file or named pipe instead of stdout
+    #[arg(long, global = true)]
+    progress_out: Option<PathBuf>,
+    /// Append a JSON record of every operation changing a stack to this file
+    #[arg(long, global = true)]
+    audit_log: Option<PathBuf>,
+    /// HTTP proxy for the AWS and web requests, like http://proxy:3128, defaults to $HTTPS_PROXY or $HTTP_PROXY
+    #[arg(long, global = true)]
+    proxy: Option<String>,
+    /// Encrypt the parameter values and outputs recorded in the history, kms:<key id> or a file of 32 base64 bytes, defaults to $PKLFORMATION_STATE_KEY
+    #[arg(long, global = true)]
+    state_key: Option<String>,
+    /// Print long outputs directly instead of through $PAGER
+    #[arg(long, global = true)]
+    no_pager: bool,
+    #[command(flatten)]
+    sdk: SdkOptions,
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    #[command(group(ArgGroup::new("target").required(true).args(["stack", "all"])))]
+    Up {
+        #[arg(short, long)]
+        stack: Option<String>,
+        /// Deploy every stack of the project file, following their dependencies
+        #[arg(long, conflicts_with_all = ["template", "account_roles"])]
+        all: bool,
+        #[arg(short, long)]
+        template: Option<PathBuf>,
+        /// Deploy the stack in the account of each assumed role, one after the other
+        #[arg(long = "account-role")]
+        account_roles: Vec<String>,
+        /// Append every stack event observed during the deployment to this file, as JSON lines
+        #[arg(long)]
+        events_log: Option<PathBuf>,
+        #[command(flatten)]
+        options: Box<UpOptions>,
+    },
+
+    Preview {
+        #[arg(short, long)]
+        stack: String,
+        #[arg(short, long)]
+        template: Option<PathBuf>,
+        #[command(flatten)]
+        options: PreviewOptions,
+    },
+
+    #[command(group(ArgGroup::new("target").args(["stack", "all", "select"])))]
+    Destroy {
+        /// Picked from the stacks of the account when omitted
+        #[arg(short, long)]
+        stack: Option<String>,
+        /// Destroy every stack of the project file, dependents first
+        #[arg(long)]
+        all: bool,
+        /// Pick the stacks to destroy among the stacks of the account
+        #[arg(long)]
+        select: bool,
+        /// Only offer the stacks whose name starts with this prefix
+        #[arg(long, requires = "select")]
+        prefix: Option<String>,
+        /// Only offer the stacks with this tag, as KEY=VALUE
+        #[arg(long = "tag", requires = "select", value_parser = parse_key_value)]
+        tags: Vec<(String, String)>,
+        #[command(flatten)]
+        options: DestroyOptions,
+        /// Destroy stacks marked as protected in the project file
+        #[arg(long)]
+        allow_protected: bool,
+        /// Append every stack event observed during the deletion to this file, as JSON lines
+        #[arg(long)]
+        events_log: Option<PathBuf>,
+    },
+
+    /// Cancel the update in progress of a stack, or delete its pending change sets
+    Cancel {
+        #[arg(short, long)]
+        stack: String,
+    },
+
+    /// Delete the failed change sets of a stack, and the ones not executed for a while
+    Gc {
+        #[arg(short, long)]
+        stack: String,
+        /// Age of the change sets to delete, like 30m, 12h or 7d
+        #[arg(long, value_parser = parse_age, default_value = "7d")]
+        older_than: Duration,
+    },
+
+    /// Enable or disable the termination protection of a stack
+    Protect {
+        #[arg(short, long)]
+        stack: String,
+        #[arg(long)]
+        off: bool,
+    },
+
+    List {
+        #[arg(short, long)]
+        status_filter: Option<Vec<StackStatus>>,
+        /// Stop after this many stacks
+        #[arg(long)]
+        limit: Option<usize>,
+        #[command(flatten)]
+        filter: ListFilter,
+        #[command(flatten)]
+        sort: ListSort,
+        #[command(flatten)]
+        layout: StackTableLayout,
+    },
+
+    Describe {
+        /// Picked from the stacks of the account when omitted
+        #[arg(short, long)]
+        stack: Option<String>,
+        /// Open the console page of the stack in the browser
+        #[arg(long)]
+        open: bool,
+    },
+
+    /// Outputs of a stack, for the build steps that need them
+    Outputs {
+        #[arg(short, long)]
+        stack: String,
+        /// Detected from the extension of --out by default, json with --output json, dotenv otherwise
+        #[arg(long, value_enum)]
+        format: Option<OutputsFormat>,
+        /// Write the outputs to this file instead of stdout
+        #[arg(short = 'O', long)]
+        out: Option<PathBuf>,
+    },
+
+    /// Events of a stack, most recent first
+    Events {
+        /// Picked from the stacks of the account when omitted
+        #[arg(short, long)]
+        stack: Option<String>,
+        #[command(flatten)]
+        filter: EventFilter,
+        /// Maximum number of events to show
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+
+    /// Details of a single resource of a stack
+    Resource {
+        #[arg(short, long)]
+        stack: String,
+        #[arg(long)]
+        logical_id: String,
+        /// Also print the properties of the resource in the deployed template
+        #[arg(long)]
+        properties: bool,
+    },
+
+    /// Cost of the resources of a stack by service, from Cost Explorer
+    Cost {
+        #[arg(short, long)]
+        stack: String,
+        /// How far back to report, in days (e.g. 30d)
+        #[arg(long, value_parser = parse_age, default_value = "30d")]
+        period: Duration,
+    },
+
+    /// Exports of the account and region, with the stack owning them
+    Exports {
+        /// Only the exports of this stack
+        #[arg(short, long)]
+        stack: Option<String>,
+        /// Also list the stacks importing each export
+        #[arg(long)]
+        show_importers: bool,
+    },
+
+    /// Past up, preview and destroy runs recorded on this machine, most recent first
+    History {
+        #[arg(short, long)]
+        stack: Option<String>,
+        #[arg(long, value_parser = ["up", "preview", "destroy"])]
+        operation: Option<String>,
+        /// Maximum number of runs to show
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+        /// Show the parameter values and outputs encrypted with --state-key
+        #[arg(long)]
+        reveal: bool,
+        /// Compare the parameter values and outputs of each run with the previous run of its stack, offline, with --state-key
+        #[arg(long, conflicts_with = "reveal")]
+        diff: bool,
+    },
+
+    /// Follow the status, resources and events of a stack until it is stable
+    Watch {
+        #[arg(short, long)]
+        stack: String,
+        /// Seconds between two refreshes
+        #[arg(long, default_value = "10", value_parser = parse_duration)]
+        interval: Duration,
+    },
+
+    /// Execute an existing change set, created by `preview` or by someone else
+    Execute {
+        #[arg(short, long)]
+        stack: String,
+        /// Name or ARN of the change set
+        #[arg(short, long)]
+        change_set: String,
+    },
+
+    /// List the change sets of a stack
+    Changesets {
+        #[arg(short, long)]
+        stack: String,
+    },
+
+    Template {
+        #[arg(short, long)]
+        stack: String,
+        #[arg(short, long)]
+        processed: bool,
+        #[arg(short = 'O', long)]
+        out: Option<PathBuf>,
+    },
+
+    #[command(group(ArgGroup::new("source").required(true).multiple(true).args(["stack", "template"])))]
+    Validate {
+        #[arg(short, long)]
+        stack: Option<String>,
+        #[arg(short, long)]
+        template: Option<PathBuf>,
+        /// Check Fn::GetAtt attribute names against the resource schemas of the CloudFormation registry
+        #[arg(long)]
+        schemas: bool,
+        /// Report the findings as SARIF or JUnit XML instead of text
+        #[arg(long, value_enum)]
+        report_format: Option<ReportFormat>,
+        /// Write the report to this file instead of stdout
+        #[arg(short = 'O', long, requires = "report_format")]
+        out: Option<PathBuf>,
+        #[command(flatten)]
+        pkl: PklOptions,
+    },
+
+    /// Evaluate a template with pkl and print the resulting JSON
+    #[command(group(ArgGroup::new("source").required(true).multiple(true).args(["stack", "template"])))]
+    Eval {
+        #[arg(short, long)]
+        stack: Option<String>,
+        #[arg(short, long)]
+        template: Option<PathBuf>,
+        #[arg(short = 'O', long)]
+        out: Option<PathBuf>,
+        #[command(flatten)]
+        pkl: PklOptions,
+    },
+
+    /// Diff the templates, parameters and tags of two deployed stacks, e.g. staging and prod
+    Compare {
+        /// Deployed name of a stack, given twice
+        #[arg(short, long, required = true, num_args = 1)]
+        stack: Vec<String>,
+    },
+
+    /// Resource counts, sections and size of a template, to review large generated ones
+    #[command(group(ArgGroup::new("source").required(true).multiple(true).args(["stack", "template"])))]
+    Stats {
+        #[arg(short, long)]
+        stack: Option<String>,
+        #[arg(short, long)]
+        template: Option<PathBuf>,
+        #[command(flatten)]
+        pkl: PklOptions,
+    },
+
+    Diff {
+        #[arg(short, long)]
+        stack: String,
+        #[arg(short, long)]
+        template: Option<PathBuf>,
+        #[command(flatten)]
+        pkl: PklOptions,
+    },
+
+    #[command(group(
+        ArgGroup::new("filter")
+            .required(true)
+            .multiple(true)
+            .args(["physical_id", "resource_type", "name_contains"])
+    ))]
+    FindResource {
+        #[arg(short, long)]
+        physical_id: Option<String>,
+        #[arg(short = 't', long = "type")]
+        resource_type: Option<String>,
+        #[arg(short, long)]
+        name_contains: Option<String>,
+    },
+
+    Version {
+        #[arg(long)]
+        check: bool,
+    },
+
+    SelfUpdate,
+
+    Project,
+
+    /// Dashboard of the project stacks, with their events and resources
+    Tui,
+
+    /// Detect the drift of project stacks, once or periodically
+    Drift {
+        #[arg(short, long, required_unless_present = "all", conflicts_with = "all")]
+        stack: Option<String>,
+        /// Every stack of the project
+        #[arg(long)]
+        all: bool,
+        /// Keep running and detect the drift again after each interval
+        #[arg(long)]
+        watch: bool,
+        /// Time between two detections, like 30m or 1h
+        #[arg(long, value_parser = parse_age, default_value = "1h")]
+        interval: Duration,
+        /// Send the notifications of the project when drift appears
+        #[arg(long)]
+        notify: bool,
+    },
+
+    /// Move or rename resources between the project stacks without replacing them
+    Refactor {
+        /// Pkl, JSON or YAML file with the moves, from a stack and logical id to another
+        #[arg(short, long)]
+        mapping: PathBuf,
+        /// Create the destination stacks that don't exist yet
+        #[arg(long)]
+        enable_stack_creation: bool,
+        /// Execute the refactor without confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+
+    /// Export the dependency graph between the project stacks
+    Graph {
+        #[arg(short, long, value_enum, default_value_t)]
+        format: GraphFormat,
+        #[arg(short = 'O', long)]
+        out: Option<PathBuf>,
+    },
+}
+
+impl Commands {
+    fn stack(&self) -> Option<&str> {
+        match self {
+            Commands::Preview { stack, .. }
+            | Commands::Changesets { stack }
+            | Commands::Resource { stack, .. }
+            | Commands::Cost { stack, .. }
+            | Commands::Watch { stack, .. }
+            | Commands::Outputs { stack, .. }
+            | Commands::Execute { stack, .. }
+            | Commands::Cancel { stack, .. }
+            | Commands::Gc { stack, .. }
+            | Commands::Protect { stack, .. }
+            | Commands::Template { stack, .. }
+            | Commands::Diff { stack, .. } => Some(stack),
+            Commands::Up { stack, .. }
+            | Commands::Destroy { stack, .. }
+            | Commands::Describe { stack, .. }
+            | Commands::Events { stack, .. }
+            | Commands::Validate { stack, .. }
+            | Commands::Exports { stack, .. }
+            | Commands::Eval { stack, .. }
+            | Commands::Stats { stack, .. } => stack.as_deref(),
+            _ => None,
+        }
+    }
+
+    // Commands changing stacks, checked to run with the expected account before anything else
+    fn deploys(&self) -> bool {
+        matches!(
+            self,
+            Commands::Up { .. }
+                | Commands::Destroy { .. }
+                | Commands::Execute { .. }
+                | Commands::Cancel { .. }
+                | Commands::Gc { .. }
+                | Commands::Protect { .. }
+                | Commands::Refactor { .. }
+        )
+    }
+
+    // Read-only commands whose output can be longer than the terminal
+    fn pageable(&self) -> bool {
+        match self {
+            Commands::Describe { open, .. } => !open,
+            Commands::List { .. }
+            | Commands::Events { .. }
+            | Commands::Resource { .. }
+            | Commands::Changesets { .. }
+            | Commands::History { .. }
+            | Commands::Exports { .. }
+            | Commands::FindResource { .. }
+            | Commands::Diff { .. }
+            | Commands::Compare { .. } => true,
+            Commands::Preview { options, .. } => options.format == ChangeSetFormat::Text,
+            _ => false,
+        }
+    }
+
+    fn missing_stack(&mut self) -> Option<&mut Option<String>> {
+        match self {
+            Commands::Describe {
+                stack: stack @ None,
+                ..
+            }
+            | Commands::Events {
+                stack: stack @ None,
+                ..
+            }
+            | Commands::Destroy {
+                stack: stack @ None,
+                all: false,
+                select: false,
+                ..
+            } => Some(stack),
+            _ => None,
+        }
+    }
+}
+
+// A picked stack is a stack of the project, or a stack no project key maps to, known by its
+// deployed name only
+enum PickedStack {
+    Project(String),
+    Deployed(String),
+}
+
+impl PickedStack {
+    fn stack(&self) -> &str {
+        match self {
+            PickedStack::Project(stack) | PickedStack::Deployed(stack) => stack,
+        }
+    }
+
+    // The environment suffix is already in deployed names
+    fn stack_name(&self, project: &Project) -> String {
+        match self {
+            PickedStack::Project(stack) => project.stack_name(stack),
+            PickedStack::Deployed(name) => name.clone(),
+        }
+    }
+}
+
+async fn pick_stack(project: &Project) -> anyhow::Result<PickedStack> {
+    if !std::io::stdin().is_terminal() {
+        bail!("--stack is required when not running in a terminal");
+    }
+    let mut names: Vec<_> = AwsClient::new(project.default_region())
+        .await
+        .list_existing_stacks()
+        .await?
+        .iter()
+        .filter_map(|stack| stack.stack_name().map(str::to_string))
+        .collect();
+    names.sort();
+    if names.is_empty() {
+        bail!("No stacks found in the account");
+    }
+    let Some(index) = Display::new().ask_fuzzy_select("Stack", &names) else {
+        bail!(PklformationError::Aborted("No stack selected".to_string()));
+    };
+    let name = names.swap_remove(index);
+    Ok(project
+        .stacks
+        .keys()
+        .find(|stack| project.stack_name(stack) == name)
+        .cloned()
+        .map_or(PickedStack::Deployed(name), PickedStack::Project))
+}
+
+fn parse_duration(arg: &str) -> Result<std::time::Duration, std::num::ParseIntError> {
+    let seconds = arg.parse()?;
+    Ok(std::time::Duration::from_secs(seconds))
+}
+
+// The kind of the error is told by the exit code, for scripts and CI
+pub async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            ExitCode::from(error::exit_code(&err))
+        }
+    }
+}
+
+async fn run(mut cli: Cli) -> anyhow::Result<()> {
+    // Logs can't be mixed with the machine readable outputs
+    let machine_stdout = cli.output == OutputFormat::Json
+        || (cli.progress == ProgressFormat::Ndjson && cli.progress_out.is_none())
+        || matches!(&cli.command, Commands::Preview { options, .. } if options.format != ChangeSetFormat::Text)
+        || matches!(
+            &cli.command,
+            Commands::Validate {
+                report_format: Some(_),
+                out: None,
+                ..
+            }
+        );
+    let ansi = cli.color.apply();
+    // The pager owns stdout until it is closed, the logs would land in the middle of the page
+    let pager = if !cli.no_pager && cli.command.pageable() {
+        Pager::start()
+    } else {
+        None
+    };
+    if machine_stdout || pager.is_some() {
+        tracing_subscriber::fmt()
+            .with_ansi(ansi)
+            .with_writer(std::io::stderr)
+            .init();
+    } else {
+        tracing_subscriber::fmt().with_ansi(ansi).init();
+    }
+
+    SpinnerStyle::apply(cli.spinner)?;
+    proxy::configure(cli.proxy.as_deref())?;
+    state_key::configure(cli.state_key.as_deref())?;
+    if let Some(path) = cli.audit_log.as_deref() {
+        audit_log::open(path)?;
+    }
+    let mut project = Project::load(&cli.project, cli.pkl_bin.clone())?;
+    if let Some(env) = cli.env.as_deref() {
+        project = project.with_environment(env)?;
+    }
+    project.sdk_options(&cli.sdk)?.apply();
+    let mut picked = None;
+    if let Some(missing) = cli.command.missing_stack() {
+        let stack = pick_stack(&project).await?;
+        *missing = Some(stack.stack().to_string());
+        picked = Some(stack);
+    }
+    let stack_name = |stack: &str| match &picked {
+        Some(picked) => picked.stack_name(&project),
+        None => project.stack_name(stack),
+    };
+    let mut validator = Validator::new(&project.naming)?
+        .with_protected_resource_types(project.protected_resource_types.clone());
+    if let Some(stack) = cli.command.stack() {
+        validator = validator.with_immutable_resources(project.immutable_resources(stack));
+    }
+    let region = cli.command.stack().and_then(|stack| project.region(stack));
+    let progress_stream = match cli.progress {
+        ProgressFormat::Ndjson => Some(ProgressStream::open(cli.progress_out.as_deref())?),
+        ProgressFormat::Spinner => None,
+    };
+    let client = AwsClient::new(region)
+        .await
+        .with_notifications(project.notifications.clone())
+        .with_progress_stream(progress_stream.clone());
+    if let Some(stack) = cli.command.stack() {
+        partition::validate_region(client.region(), project.partition(stack))?;
+    }
+    if cli.command.deploys() {
+        let identity = client.check_credentials().await?;
+        info!(
+            "Deploying as {} to account {} ({})",
+            identity.arn,
+            identity.account,
+            client.region().unwrap_or("default region")
+        );
+    }
+
+    match &cli.command {
+        Commands::Up {
+            all: true,
+            options,
+            events_log,
+            ..
+        } => {
+            let span = span!(Level::INFO, "up-all");
+            let _enter = span.enter();
+            UpAllCommand::new(
+                &project,
+                cli.pool_interval.to_owned(),
+                validator,
+                UpOptions {
+                    pkl: project.pkl_options(&options.pkl),
+                    ..options.as_ref().clone()
+                },
+                events_log.clone(),
+                progress_stream.clone(),
+            )
+            .run()
+            .await?;
+        }
+        Commands::Up {
+            stack: Some(stack),
+            template,
+            account_roles,
+            options,
+            events_log,
+            ..
+        } if account_roles.is_empty() => {
+            let template = project.template(stack, template.as_deref())?;
+            let client = match events_log {
+                Some(path) => client.with_events_log(EventsLog::open(path)?),
+                None => client,
+            };
+            let span = span!(
+                Level::INFO,
+                "up",
+                stack = stack,
+                template = template.to_str()
+            );
+            let _enter = span.enter();
+            UpCommand::new(
+                client,
+                project.stack_name(stack),
+                template,
+                cli.pool_interval.to_owned(),
+                validator,
+                project.change_set_options(stack),
+                UpOptions {
+                    pkl: stack_outputs::pkl_options(&project, stack, &options.pkl).await?,
+                    protected: project.is_protected(stack),
+                    approval: project.approval(),
+                    required_tags: project.required_tags.clone(),
+                    ..options.as_ref().clone()
+                },
+            )
+            .run()
+            .await?;
+        }
+        Commands::Up {
+            stack: Some(stack),
+            template,
+            account_roles,
+            options,
+            events_log,
+            ..
+        } => {
+            let template = project.template(stack, template.as_deref())?;
+            let mut results = Vec::new();
+            for role_arn in account_roles {
+                let span = span!(
+                    Level::INFO,
+                    "up",
+                    stack = stack,
+                    template = template.to_str(),
+                    account_role = role_arn
+                );
+                let _enter = span.enter();
+                let mut client = AwsClient::assume_role(role_arn, region)
+                    .await
+                    .with_notifications(project.notifications.clone())
+                    .with_progress_stream(progress_stream.clone());
+                if let Some(path) = events_log {
+                    client = client.with_events_log(EventsLog::open(path)?);
+                }
+                let result = UpCommand::new(
+                    client,
+                    project.stack_name(stack),
+                    template.to_path_buf(),
+                    cli.pool_interval.to_owned(),
+                    validator.clone(),
+                    project.change_set_options(stack),
+                    UpOptions {
+                        pkl: stack_outputs::pkl_options(&project, stack, &options.pkl).await?,
+                        protected: project.is_protected(stack),
+                        approval: project.approval(),
+                        required_tags: project.required_tags.clone(),
+                        ..options.as_ref().clone()
+                    },
+                )
+                .run()
+                .await
+                .map_err(anyhow::Error::from);
+                results.push((role_arn.to_string(), result));
+            }
+
+            Display::new().print_operation_results("Accounts:", &results);
+            let failures = results
+                .iter()
+                .filter(|(_, result)| {
+                    !matches!(
+                        result,
+                        Ok(StackStatus::CreateComplete | StackStatus::UpdateComplete)
+                    )
+                })
+                .count();
+            if failures > 0 {
+                bail!("Up failed in {failures} account(s)");
+            }
+        }
+        Commands::Up { stack: None, .. } => unreachable!("clap requires --stack or --all"),
+        Commands::Preview {
+            stack,
+            template,
+            options,
+        } => {
+            let template = project.template(stack, template.as_deref())?;
+            let span = span!(Level::DEBUG, "preview", stack = stack);
+            let _enter = span.enter();
+            PreviewCommand::new(
+                client,
+                project.stack_name(stack),
+                template,
+                cli.pool_interval.to_owned(),
+                validator,
+                project.change_set_options(stack),
+                PreviewOptions {
+                    pkl: stack_outputs::pkl_options(&project, stack, &options.pkl).await?,
+                    output: cli.output,
+                    ..options.clone()
+                },
+            )
+            .run()
+            .await?;
+        }
+        Commands::Destroy {
+            all: true,
+            allow_protected,
+            options,
+            events_log,
+            ..
+        } => {
+            let span = span!(Level::DEBUG, "destroy-all");
+            let _enter = span.enter();
+            DestroyAllCommand::new(
+                &project,
+                cli.pool_interval.to_owned(),
+                events_log.clone(),
+                progress_stream.clone(),
+                *allow_protected,
+                options.yes,
+            )
+            .run()
+            .await?;
+        }
+        Commands::Destroy {
+            select: true,
+            prefix,
+            tags,
+            allow_protected,
+            options,
+            events_log,
+            ..
+        } => {
+            let span = span!(Level::DEBUG, "destroy-select");
+            let _enter = span.enter();
+            DestroySelectCommand::new(
+                &project,
+                cli.pool_interval.to_owned(),
+                StackFilter {
+                    prefix: prefix.clone(),
+                    tags: tags.clone(),
+                },
+                events_log.clone(),
+                progress_stream.clone(),
+                *allow_protected,
+                options.yes,
+            )
+            .run()
+            .await?;
+        }
+        Commands::Destroy {
+            stack: Some(stack),
+            options,
+            allow_protected,
+            events_log,
+            ..
+        } => {
+            if project.is_protected(stack) && !options.impact && !allow_protected {
+                bail!("Stack {stack} is protected in the project file, pass --allow-protected to destroy it");
+            }
+            let span = span!(Level::DEBUG, "destroy", stack = stack);
+            let _enter = span.enter();
+            let client = match events_log {
+                Some(path) => client.with_events_log(EventsLog::open(path)?),
+                None => client,
+            };
+            DestroyCommand::new(
+                client,
+                stack_name(stack),
+                cli.pool_interval.to_owned(),
+                options.clone(),
+            )
+            .run()
+            .await?;
+        }
+        Commands::Destroy { stack: None, .. } => unreachable!("the stack is picked when omitted"),
+        Commands::Cancel { stack } => {
+            let span = span!(Level::DEBUG, "cancel", stack = stack);
+            let _enter = span.enter();
+            CancelCommand::new(
+                client,
+                project.stack_name(stack),
+                cli.pool_interval.to_owned(),
+            )
+            .run()
+            .await?;
+        }
+        Commands::Gc { stack, older_than } => {
+            let span = span!(Level::DEBUG, "gc", stack = stack);
+            let _enter = span.enter();
+            GcCommand::new(client, project.stack_name(stack), *older_than)
+                .run()
+                .await?;
+        }
+        Commands::Protect { stack, off } => {
+            let span = span!(Level::DEBUG, "protect", stack = stack);
+            let _enter = span.enter();
+            ProtectCommand::new(client, project.stack_name(stack), !off)
+                .run()
+                .await?;
+        }
+        Commands::List {
+            status_filter,
+            limit,
+            filter,
+            sort,
+            layout,
+        } => {
+            let span = span!(Level::DEBUG, "list");
+            let _entr = span.enter();
+            ListCommand::new(
+                client,
+                status_filter.clone(),
+                ListFilter {
+                    required_tags: project.required_tags.clone(),
+                    ..filter.clone()
+                },
+                sort.clone(),
+                *limit,
+                layout.clone(),
+                cli.output,
+            )
+            .run()
+            .await?;
+        }
+        Commands::Execute { stack, change_set } => {
+            let span = span!(Level::DEBUG, "execute", stack = stack);
+            let _enter = span.enter();
+            ExecuteCommand::new(
+                client,
+                project.stack_name(stack),
+                change_set.clone(),
+                cli.pool_interval.to_owned(),
+                validator,
+                project.is_protected(stack),
+                project.approval(),
+            )
+            .run()
+            .await?;
+        }
+        Commands::Watch { stack, interval } => {
+            let span = span!(Level::DEBUG, "watch", stack = stack);
+            let _enter = span.enter();
+            WatchCommand::new(client, project.stack_name(stack), *interval)
+                .run()
+                .await?;
+        }
+        Commands::Changesets { stack } => {
+            let span = span!(Level::DEBUG, "changesets", stack = stack);
+            let _enter = span.enter();
+            ChangesetsCommand::new(client, project.stack_name(stack))
+                .run()
+                .await?;
+        }
+        Commands::Describe { stack: None, .. } => {
+            unreachable!("the stack is picked when omitted")
+        }
+        Commands::Describe {
+            stack: Some(stack),
+            open,
+        } => {
+            let span = span!(Level::DEBUG, "describe", stack = stack);
+            let _enter = span.enter();
+            DescribeCommand::new(
+                client,
+                stack_name(stack),
+                cli.pool_interval.to_owned(),
+                cli.allow_degraded,
+                *open,
+                cli.output,
+            )
+            .run()
+            .await?;
+        }
+        Commands::Outputs { stack, format, out } => {
+            let span = span!(Level::DEBUG, "outputs", stack = stack);
+            let _enter = span.enter();
+            OutputsCommand::new(
+                client,
+                project.stack_name(stack),
+                *format,
+                out.clone(),
+                cli.output,
+            )
+            .run()
+            .await?;
+        }
+        Commands::Events { stack: None, .. } => {
+            unreachable!("the stack is picked when omitted")
+        }
+        Commands::Events {
+            stack: Some(stack),
+            filter,
+            limit,
+        } => {
+            let span = span!(Level::DEBUG, "events", stack = stack);
+            let _enter = span.enter();
+            EventsCommand::new(
+                client,
+                stack_name(stack),
+                filter.clone(),
+                *limit,
+                cli.output,
+            )
+            .run()
+            .await?;
+        }
+        Commands::Resource {
+            stack,
+            logical_id,
+            properties,
+        } => {
+            let span = span!(Level::DEBUG, "resource", stack = stack);
+            let _enter = span.enter();
+            ResourceCommand::new(
+                client,
+                project.stack_name(stack),
+                logical_id.clone(),
+                *properties,
+                cli.output,
+            )
+            .run()
+            .await?;
+        }
+        Commands::Cost { stack, period } => {
+            let span = span!(Level::DEBUG, "cost", stack = stack);
+            let _enter = span.enter();
+            CostCommand::new(client, project.stack_name(stack), *period, cli.output)
+                .run()
+                .await?;
+        }
+        Commands::Exports {
+            stack,
+            show_importers,
+        } => {
+            let span = span!(Level::DEBUG, "exports");
+            let _enter = span.enter();
+            ExportsCommand::new(
+                client,
+                stack.as_deref().map(|stack| project.stack_name(stack)),
+                *show_importers,
+                cli.output,
+            )
+            .run()
+            .await?;
+        }
+        Commands::History {
+            stack,
+            operation,
+            limit,
+            reveal,
+            diff,
+        } => {
+            HistoryCommand::new(
+                client,
+                stack.as_deref().map(|stack| project.stack_name(stack)),
+                operation.clone(),
+                *limit,
+                *reveal,
+                *diff,
+                cli.output,
+            )
+            .run()
+            .await?;
+        }
+        Commands::Template {
+            stack,
+            processed,
+            out,
+        } => {
+            let span = span!(Level::DEBUG, "template", stack = stack);
+            let _enter = span.enter();
+            TemplateCommand::new(client, project.stack_name(stack), *processed, out.clone())
+                .run()
+                .await?;
+        }
+        Commands::Validate {
+            stack,
+            template,
+            schemas,
+            report_format,
+            out,
+            pkl,
+        } => {
+            let template = match stack {
+                Some(stack) => project.template(stack, template.as_deref())?,
+                None => template.clone().unwrap(),
+            };
+            let span = span!(Level::DEBUG, "validate", template = template.to_str());
+            let _enter = span.enter();
+            ValidateCommand::new(
+                template,
+                validator,
+                project.pkl_options(pkl),
+                schemas.then_some(client),
+                report_format.map(|format| (format, out.clone())),
+            )
+            .run()
+            .await?;
+        }
+        Commands::Eval {
+            stack,
+            template,
+            out,
+            pkl,
+        } => {
+            let template = match stack {
+                Some(stack) => project.template(stack, template.as_deref())?,
+                None => template.clone().unwrap(),
+            };
+            let span = span!(Level::DEBUG, "eval", template = template.to_str());
+            let _enter = span.enter();
+            EvalCommand::new(template, project.pkl_options(pkl), out.clone())
+                .run()
+                .await?;
+        }
+        Commands::Compare { stack } => {
+            let span = span!(Level::DEBUG, "compare");
+            let _enter = span.enter();
+            CompareCommand::new(&project, stack.clone()).run().await?;
+        }
+        Commands::Stats {
+            stack,
+            template,
+            pkl,
+        } => {
+            let template = match stack {
+                Some(stack) => project.template(stack, template.as_deref())?,
+                None => template.clone().unwrap(),
+            };
+            let span = span!(Level::DEBUG, "stats", template = template.to_str());
+            let _enter = span.enter();
+            StatsCommand::new(template, project.pkl_options(pkl), cli.output)
+                .run()
+                .await?;
+        }
+        Commands::Diff {
+            stack,
+            template,
+            pkl,
+        } => {
+            let template = project.template(stack, template.as_deref())?;
+            let span = span!(Level::DEBUG, "diff", stack = stack);
+            let _enter = span.enter();
+            DiffCommand::new(
+                client,
+                project.stack_name(stack),
+                template,
+                stack_outputs::pkl_options(&project, stack, pkl).await?,
+            )
+            .run()
+            .await?;
+        }
+        Commands::FindResource {
+            physical_id,
+            resource_type,
+            name_contains,
+        } => {
+            let span = span!(Level::DEBUG, "find-resource");
+            let _enter = span.enter();
+            FindResourceCommand::new(
+                client,
+                physical_id.clone(),
+                resource_type.clone(),
+                name_contains.clone(),
+                cli.allow_degraded,
+            )
+            .run()
+            .await?;
+        }
+        Commands::Version { check } => {
+            VersionCommand::new(*check).run().await?;
+        }
+        Commands::SelfUpdate => {
+            let span = span!(Level::DEBUG, "self-update");
+            let _enter = span.enter();
+            SelfUpdateCommand::new().run().await?;
+            return Ok(());
+        }
+        Commands::Project => {
+            Display::new().print_project_stacks(&project.stacks);
+        }
+        Commands::Tui => {
+            TuiCommand::new(&project, cli.pool_interval.to_owned(), validator)
+                .run()
+                .await?;
+        }
+        Commands::Drift {
+            stack,
+            watch,
+            interval,
+            notify,
+            ..
+        } => {
+            let stacks = match stack {
+                Some(stack) => vec![stack.clone()],
+                None => project.stacks.keys().cloned().collect(),
+            };
+            DriftCommand::new(
+                &project,
+                stacks,
+                cli.pool_interval.to_owned(),
+                watch.then_some(*interval),
+                *notify,
+                cli.output,
+            )
+            .run()
+            .await?;
+        }
+        Commands::Refactor {
+            mapping,
+            enable_stack_creation,
+            yes,
+        } => {
+            let span = span!(Level::INFO, "refactor");
+            let _enter = span.enter();
+            RefactorCommand::new(
+                &project,
+                mapping.clone(),
+                cli.pool_interval.to_owned(),
+                *enable_stack_creation,
+                *yes,
+            )
+            .run()
+            .await?;
+        }
+        Commands::Graph { format, out } => {
+            let span = span!(Level::DEBUG, "graph");
+            let _enter = span.enter();
+            GraphCommand::new(&project, *format, out.clone())
+                .run()
+                .await?;
+        }
+    }
+
+    crate::update::notify_new_version().await;
+    Ok(())
+}
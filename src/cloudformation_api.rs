@@ -0,0 +1,427 @@
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use aws_sdk_cloudformation::{
+    operation::{
+        create_change_set::CreateChangeSetOutput, describe_change_set::DescribeChangeSetOutput,
+        list_stack_resources::ListStackResourcesOutput,
+    },
+    types::{
+        ChangeSetStatus, ChangeSetSummary, ChangeSetType, Stack, StackEvent, StackResourceSummary,
+        StackStatus, TemplateStage,
+    },
+};
+
+use crate::{
+    aws_client::{AwsClient, CallerIdentity, ChangeSetOptions},
+    notifications::NotificationTarget,
+};
+
+#[cfg(test)]
+pub(crate) mod mock;
+
+// What the commands need from AWS, so their state machines can run against a mock. The futures
+// are Send for the commands to stay spawnable whatever the implementation.
+pub trait CloudFormationApi: Send + Sync {
+    fn region(&self) -> Option<&str>;
+
+    fn notification_targets(&self) -> &[NotificationTarget];
+
+    fn caller_identity(&self) -> impl Future<Output = anyhow::Result<CallerIdentity>> + Send;
+
+    // The data keys of the state key, with the credentials the stacks are deployed with
+    fn kms(
+        &self,
+        key_id: &str,
+        target: &str,
+        request: serde_json::Value,
+    ) -> impl Future<Output = anyhow::Result<serde_json::Value>> + Send;
+
+    fn describe_stack(
+        &self,
+        stack_name: &str,
+    ) -> impl Future<Output = anyhow::Result<Stack>> + Send;
+
+    fn stack_status(
+        &self,
+        stack_name: &str,
+    ) -> impl Future<Output = anyhow::Result<(StackStatus, String)>> + Send;
+
+    fn stack_console_url(&self, stack_name: &str) -> impl Future<Output = Option<String>> + Send;
+
+    fn count_active_stacks(&self) -> impl Future<Output = anyhow::Result<usize>> + Send;
+
+    fn describe_stack_events(
+        &self,
+        stack: &str,
+    ) -> impl Future<Output = anyhow::Result<Vec<StackEvent>>> + Send;
+
+    fn list_all_stack_resources(
+        &self,
+        stack_name: &str,
+    ) -> impl Future<Output = anyhow::Result<Vec<StackResourceSummary>>> + Send;
+
+    fn wait_until_stack_op_in_progress(
+        &self,
+        stack_name: &str,
+        pool_interval: Duration,
+    ) -> impl Future<Output = anyhow::Result<(StackStatus, String)>> + Send;
+
+    fn wait_until_stack_op_in_progress_or_deadline(
+        &self,
+        stack_name: &str,
+        pool_interval: Duration,
+        deadline: Option<Instant>,
+    ) -> impl Future<Output = anyhow::Result<(StackStatus, String)>> + Send;
+
+    fn list_stack_resources(
+        &self,
+        stack_name: &str,
+    ) -> impl Future<Output = anyhow::Result<ListStackResourcesOutput>> + Send;
+
+    fn list_imports(
+        &self,
+        export_name: &str,
+    ) -> impl Future<Output = anyhow::Result<Vec<String>>> + Send;
+
+    fn get_template(
+        &self,
+        stack_name: &str,
+        template_stage: TemplateStage,
+    ) -> impl Future<Output = anyhow::Result<String>> + Send;
+
+    fn delete_stack(&self, stack_name: &str) -> impl Future<Output = anyhow::Result<()>> + Send;
+
+    fn force_delete_stack(
+        &self,
+        stack_name: &str,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send;
+
+    fn update_termination_protection(
+        &self,
+        stack_name: &str,
+        enabled: bool,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send;
+
+    fn cancel_update_stack(
+        &self,
+        stack_name: &str,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send;
+
+    fn continue_update_rollback(
+        &self,
+        stack_name: &str,
+        resources_to_skip: Vec<String>,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send;
+
+    fn create_or_update_change_set(
+        &self,
+        stack_name: &str,
+        template: &str,
+        change_set_type: ChangeSetType,
+        options: &ChangeSetOptions,
+    ) -> impl Future<Output = anyhow::Result<CreateChangeSetOutput>> + Send;
+
+    fn wait_until_change_set_op_in_progress(
+        &self,
+        change_set_id: &str,
+        pool_interval: Duration,
+    ) -> impl Future<Output = anyhow::Result<(ChangeSetStatus, String)>> + Send;
+
+    fn describe_change_set(
+        &self,
+        change_set_id: &str,
+    ) -> impl Future<Output = anyhow::Result<DescribeChangeSetOutput>> + Send;
+
+    fn describe_stack_change_set(
+        &self,
+        stack_name: &str,
+        change_set: &str,
+    ) -> impl Future<Output = anyhow::Result<DescribeChangeSetOutput>> + Send;
+
+    fn execute_change_set(
+        &self,
+        change_set_id: &str,
+        disable_rollback: bool,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send;
+
+    fn delete_change_set(
+        &self,
+        change_set_id: &str,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send;
+
+    fn list_change_sets(
+        &self,
+        stack_name: &str,
+    ) -> impl Future<Output = anyhow::Result<Vec<ChangeSetSummary>>> + Send;
+
+    fn pending_change_set(
+        &self,
+        stack_name: &str,
+    ) -> impl Future<Output = anyhow::Result<Option<ChangeSetSummary>>> + Send;
+
+    fn reusable_change_set(
+        &self,
+        stack_name: &str,
+        template: &str,
+        change_set_type: &ChangeSetType,
+        options: &ChangeSetOptions,
+    ) -> impl Future<Output = anyhow::Result<Option<ChangeSetSummary>>> + Send;
+
+    fn resource_schema(
+        &self,
+        type_name: &str,
+    ) -> impl Future<Output = anyhow::Result<Option<String>>> + Send;
+
+    fn service_quota(
+        &self,
+        service_code: &str,
+        quota_name: &str,
+    ) -> impl Future<Output = anyhow::Result<Option<f64>>> + Send;
+
+    fn publish_notification(
+        &self,
+        topic_arn: &str,
+        subject: &str,
+        message: &str,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send;
+
+    fn put_ssm_parameter(
+        &self,
+        name: &str,
+        value: &str,
+        tags: &[(&str, &str)],
+    ) -> impl Future<Output = anyhow::Result<()>> + Send;
+}
+
+// The inherent methods take precedence, each one is forwarded as is
+impl CloudFormationApi for AwsClient {
+    fn region(&self) -> Option<&str> {
+        AwsClient::region(self)
+    }
+
+    fn notification_targets(&self) -> &[NotificationTarget] {
+        AwsClient::notification_targets(self)
+    }
+
+    async fn caller_identity(&self) -> anyhow::Result<CallerIdentity> {
+        Ok(AwsClient::caller_identity(self).await?)
+    }
+
+    async fn kms(
+        &self,
+        key_id: &str,
+        target: &str,
+        request: serde_json::Value,
+    ) -> anyhow::Result<serde_json::Value> {
+        AwsClient::kms(self, key_id, target, request).await
+    }
+
+    async fn describe_stack(&self, stack_name: &str) -> anyhow::Result<Stack> {
+        Ok(AwsClient::describe_stack(self, stack_name).await?)
+    }
+
+    async fn stack_status(&self, stack_name: &str) -> anyhow::Result<(StackStatus, String)> {
+        Ok(AwsClient::stack_status(self, stack_name).await?)
+    }
+
+    async fn stack_console_url(&self, stack_name: &str) -> Option<String> {
+        AwsClient::stack_console_url(self, stack_name).await
+    }
+
+    async fn count_active_stacks(&self) -> anyhow::Result<usize> {
+        Ok(AwsClient::count_active_stacks(self).await?)
+    }
+
+    async fn describe_stack_events(&self, stack: &str) -> anyhow::Result<Vec<StackEvent>> {
+        Ok(AwsClient::describe_stack_events(self, stack).await?)
+    }
+
+    async fn list_all_stack_resources(
+        &self,
+        stack_name: &str,
+    ) -> anyhow::Result<Vec<StackResourceSummary>> {
+        Ok(AwsClient::list_all_stack_resources(self, stack_name).await?)
+    }
+
+    async fn wait_until_stack_op_in_progress(
+        &self,
+        stack_name: &str,
+        pool_interval: Duration,
+    ) -> anyhow::Result<(StackStatus, String)> {
+        Ok(AwsClient::wait_until_stack_op_in_progress(self, stack_name, pool_interval).await?)
+    }
+
+    async fn wait_until_stack_op_in_progress_or_deadline(
+        &self,
+        stack_name: &str,
+        pool_interval: Duration,
+        deadline: Option<Instant>,
+    ) -> anyhow::Result<(StackStatus, String)> {
+        Ok(AwsClient::wait_until_stack_op_in_progress_or_deadline(
+            self,
+            stack_name,
+            pool_interval,
+            deadline,
+        )
+        .await?)
+    }
+
+    async fn list_stack_resources(
+        &self,
+        stack_name: &str,
+    ) -> anyhow::Result<ListStackResourcesOutput> {
+        Ok(AwsClient::list_stack_resources(self, stack_name).await?)
+    }
+
+    async fn list_imports(&self, export_name: &str) -> anyhow::Result<Vec<String>> {
+        Ok(AwsClient::list_imports(self, export_name).await?)
+    }
+
+    async fn get_template(
+        &self,
+        stack_name: &str,
+        template_stage: TemplateStage,
+    ) -> anyhow::Result<String> {
+        Ok(AwsClient::get_template(self, stack_name, template_stage).await?)
+    }
+
+    async fn delete_stack(&self, stack_name: &str) -> anyhow::Result<()> {
+        Ok(AwsClient::delete_stack(self, stack_name).await?)
+    }
+
+    async fn force_delete_stack(&self, stack_name: &str) -> anyhow::Result<()> {
+        Ok(AwsClient::force_delete_stack(self, stack_name).await?)
+    }
+
+    async fn update_termination_protection(
+        &self,
+        stack_name: &str,
+        enabled: bool,
+    ) -> anyhow::Result<()> {
+        Ok(AwsClient::update_termination_protection(self, stack_name, enabled).await?)
+    }
+
+    async fn cancel_update_stack(&self, stack_name: &str) -> anyhow::Result<()> {
+        Ok(AwsClient::cancel_update_stack(self, stack_name).await?)
+    }
+
+    async fn continue_update_rollback(
+        &self,
+        stack_name: &str,
+        resources_to_skip: Vec<String>,
+    ) -> anyhow::Result<()> {
+        Ok(AwsClient::continue_update_rollback(self, stack_name, resources_to_skip).await?)
+    }
+
+    async fn create_or_update_change_set(
+        &self,
+        stack_name: &str,
+        template: &str,
+        change_set_type: ChangeSetType,
+        options: &ChangeSetOptions,
+    ) -> anyhow::Result<CreateChangeSetOutput> {
+        Ok(AwsClient::create_or_update_change_set(
+            self,
+            stack_name,
+            template,
+            change_set_type,
+            options,
+        )
+        .await?)
+    }
+
+    async fn wait_until_change_set_op_in_progress(
+        &self,
+        change_set_id: &str,
+        pool_interval: Duration,
+    ) -> anyhow::Result<(ChangeSetStatus, String)> {
+        Ok(
+            AwsClient::wait_until_change_set_op_in_progress(self, change_set_id, pool_interval)
+                .await?,
+        )
+    }
+
+    async fn describe_change_set(
+        &self,
+        change_set_id: &str,
+    ) -> anyhow::Result<DescribeChangeSetOutput> {
+        Ok(AwsClient::describe_change_set(self, change_set_id).await?)
+    }
+
+    async fn describe_stack_change_set(
+        &self,
+        stack_name: &str,
+        change_set: &str,
+    ) -> anyhow::Result<DescribeChangeSetOutput> {
+        Ok(AwsClient::describe_stack_change_set(self, stack_name, change_set).await?)
+    }
+
+    async fn execute_change_set(
+        &self,
+        change_set_id: &str,
+        disable_rollback: bool,
+    ) -> anyhow::Result<()> {
+        Ok(AwsClient::execute_change_set(self, change_set_id, disable_rollback).await?)
+    }
+
+    async fn delete_change_set(&self, change_set_id: &str) -> anyhow::Result<()> {
+        Ok(AwsClient::delete_change_set(self, change_set_id).await?)
+    }
+
+    async fn list_change_sets(&self, stack_name: &str) -> anyhow::Result<Vec<ChangeSetSummary>> {
+        Ok(AwsClient::list_change_sets(self, stack_name).await?)
+    }
+
+    async fn pending_change_set(
+        &self,
+        stack_name: &str,
+    ) -> anyhow::Result<Option<ChangeSetSummary>> {
+        Ok(AwsClient::pending_change_set(self, stack_name).await?)
+    }
+
+    async fn reusable_change_set(
+        &self,
+        stack_name: &str,
+        template: &str,
+        change_set_type: &ChangeSetType,
+        options: &ChangeSetOptions,
+    ) -> anyhow::Result<Option<ChangeSetSummary>> {
+        Ok(
+            AwsClient::reusable_change_set(self, stack_name, template, change_set_type, options)
+                .await?,
+        )
+    }
+
+    async fn resource_schema(&self, type_name: &str) -> anyhow::Result<Option<String>> {
+        Ok(AwsClient::resource_schema(self, type_name).await?)
+    }
+
+    async fn service_quota(
+        &self,
+        service_code: &str,
+        quota_name: &str,
+    ) -> anyhow::Result<Option<f64>> {
+        Ok(AwsClient::service_quota(self, service_code, quota_name).await?)
+    }
+
+    async fn publish_notification(
+        &self,
+        topic_arn: &str,
+        subject: &str,
+        message: &str,
+    ) -> anyhow::Result<()> {
+        Ok(AwsClient::publish_notification(self, topic_arn, subject, message).await?)
+    }
+
+    async fn put_ssm_parameter(
+        &self,
+        name: &str,
+        value: &str,
+        tags: &[(&str, &str)],
+    ) -> anyhow::Result<()> {
+        Ok(AwsClient::put_ssm_parameter(self, name, value, tags).await?)
+    }
+}
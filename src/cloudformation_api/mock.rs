@@ -0,0 +1,392 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, Once},
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context};
+use aws_sdk_cloudformation::{
+    operation::{
+        create_change_set::CreateChangeSetOutput, describe_change_set::DescribeChangeSetOutput,
+        list_stack_resources::ListStackResourcesOutput,
+    },
+    types::{
+        ChangeSetStatus, ChangeSetSummary, ChangeSetType, Stack, StackEvent, StackResourceSummary,
+        StackStatus, TemplateStage,
+    },
+};
+
+use crate::{
+    aws_client::{CallerIdentity, ChangeSetOptions},
+    cloudformation_api::CloudFormationApi,
+    notifications::NotificationTarget,
+};
+
+pub const MOCK_STACK_ID: &str =
+    "arn:aws:cloudformation:us-east-1:123456789012:stack/mock/00000000-0000-0000-0000-000000000000";
+pub const MOCK_CHANGE_SET_ID: &str =
+    "arn:aws:cloudformation:us-east-1:123456789012:changeSet/mock/00000000-0000-0000-0000-000000000000";
+
+// The history and the durations go to a scratch directory instead of the user's
+pub fn isolate_user_data() {
+    static ONCE: Once = Once::new();
+    ONCE.call_once(|| {
+        let dir = std::env::temp_dir().join(format!("pklformation-test-{}", std::process::id()));
+        std::env::set_var("XDG_DATA_HOME", dir.join("data"));
+        std::env::set_var("XDG_CACHE_HOME", dir.join("cache"));
+    });
+}
+
+#[derive(Default)]
+struct State {
+    stack_statuses: VecDeque<StackStatus>,
+    current_status: Option<StackStatus>,
+    change_set_statuses: VecDeque<(ChangeSetStatus, String)>,
+    change_set: Option<DescribeChangeSetOutput>,
+    pending_change_set: Option<ChangeSetSummary>,
+    events: Vec<StackEvent>,
+    resources: Vec<StackResourceSummary>,
+    calls: Vec<String>,
+}
+
+// Scripted stand-in for CloudFormation: every wait on the stack returns the next status of the
+// script, every wait on a change set the next change set status, and every call is recorded.
+// Clones share the script and the calls, so a test keeps one to inspect what a command did
+#[derive(Clone, Default)]
+pub struct MockCloudFormation {
+    state: Arc<Mutex<State>>,
+}
+
+impl MockCloudFormation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Statuses returned by the successive waits, the stack doesn't exist before the first one
+    pub fn with_stack_statuses(self, statuses: impl IntoIterator<Item = StackStatus>) -> Self {
+        self.state.lock().unwrap().stack_statuses = statuses.into_iter().collect();
+        self
+    }
+
+    // Statuses returned by the successive waits on change sets, CREATE_COMPLETE once exhausted
+    pub fn with_change_set_statuses(
+        self,
+        statuses: impl IntoIterator<Item = (ChangeSetStatus, String)>,
+    ) -> Self {
+        self.state.lock().unwrap().change_set_statuses = statuses.into_iter().collect();
+        self
+    }
+
+    pub fn with_pending_change_set(self, change_set: ChangeSetSummary) -> Self {
+        self.state.lock().unwrap().pending_change_set = Some(change_set);
+        self
+    }
+
+    // Calls made so far, as `operation target`
+    pub fn calls(&self) -> Vec<String> {
+        self.state.lock().unwrap().calls.clone()
+    }
+
+    fn record(&self, operation: &str, target: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .calls
+            .push(format!("{operation} {target}"));
+    }
+
+    fn next_stack_status(&self, stack_name: &str) -> anyhow::Result<(StackStatus, String)> {
+        self.record("WaitStack", stack_name);
+        let mut state = self.state.lock().unwrap();
+        if let Some(status) = state.stack_statuses.pop_front() {
+            state.current_status = Some(status);
+        }
+        match &state.current_status {
+            Some(status) => Ok((status.clone(), String::new())),
+            None => bail!("Stack with id {stack_name} does not exist"),
+        }
+    }
+}
+
+impl CloudFormationApi for MockCloudFormation {
+    fn region(&self) -> Option<&str> {
+        Some("us-east-1")
+    }
+
+    fn notification_targets(&self) -> &[NotificationTarget] {
+        &[]
+    }
+
+    async fn caller_identity(&self) -> anyhow::Result<CallerIdentity> {
+        Ok(CallerIdentity {
+            account: "123456789012".to_string(),
+            arn: "arn:aws:iam::123456789012:user/mock".to_string(),
+        })
+    }
+
+    async fn kms(
+        &self,
+        key_id: &str,
+        target: &str,
+        _request: serde_json::Value,
+    ) -> anyhow::Result<serde_json::Value> {
+        self.record(&format!("Kms{target}"), key_id);
+        bail!("KMS is not mocked")
+    }
+
+    async fn describe_stack(&self, stack_name: &str) -> anyhow::Result<Stack> {
+        let status = self
+            .state
+            .lock()
+            .unwrap()
+            .current_status
+            .clone()
+            .with_context(|| format!("Stack with id {stack_name} does not exist"))?;
+        Ok(Stack::builder()
+            .stack_name(stack_name)
+            .stack_id(MOCK_STACK_ID)
+            .stack_status(status)
+            .build())
+    }
+
+    async fn stack_status(&self, stack_name: &str) -> anyhow::Result<(StackStatus, String)> {
+        let stack = self.describe_stack(stack_name).await?;
+        Ok((
+            stack.stack_status.context("Stack without status")?,
+            String::new(),
+        ))
+    }
+
+    async fn stack_console_url(&self, _stack_name: &str) -> Option<String> {
+        None
+    }
+
+    async fn count_active_stacks(&self) -> anyhow::Result<usize> {
+        Ok(0)
+    }
+
+    async fn describe_stack_events(&self, _stack: &str) -> anyhow::Result<Vec<StackEvent>> {
+        Ok(self.state.lock().unwrap().events.clone())
+    }
+
+    async fn list_all_stack_resources(
+        &self,
+        _stack_name: &str,
+    ) -> anyhow::Result<Vec<StackResourceSummary>> {
+        Ok(self.state.lock().unwrap().resources.clone())
+    }
+
+    async fn wait_until_stack_op_in_progress(
+        &self,
+        stack_name: &str,
+        _pool_interval: Duration,
+    ) -> anyhow::Result<(StackStatus, String)> {
+        self.next_stack_status(stack_name)
+    }
+
+    async fn wait_until_stack_op_in_progress_or_deadline(
+        &self,
+        stack_name: &str,
+        _pool_interval: Duration,
+        _deadline: Option<Instant>,
+    ) -> anyhow::Result<(StackStatus, String)> {
+        self.next_stack_status(stack_name)
+    }
+
+    async fn list_stack_resources(
+        &self,
+        _stack_name: &str,
+    ) -> anyhow::Result<ListStackResourcesOutput> {
+        Ok(ListStackResourcesOutput::builder()
+            .set_stack_resource_summaries(Some(self.state.lock().unwrap().resources.clone()))
+            .build())
+    }
+
+    async fn list_imports(&self, _export_name: &str) -> anyhow::Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    async fn get_template(
+        &self,
+        _stack_name: &str,
+        _template_stage: TemplateStage,
+    ) -> anyhow::Result<String> {
+        Ok(r#"{"Resources": {}}"#.to_string())
+    }
+
+    async fn delete_stack(&self, stack_name: &str) -> anyhow::Result<()> {
+        self.record("DeleteStack", stack_name);
+        Ok(())
+    }
+
+    async fn force_delete_stack(&self, stack_name: &str) -> anyhow::Result<()> {
+        self.record("ForceDeleteStack", stack_name);
+        Ok(())
+    }
+
+    async fn update_termination_protection(
+        &self,
+        stack_name: &str,
+        enabled: bool,
+    ) -> anyhow::Result<()> {
+        self.record(
+            &format!("UpdateTerminationProtection({enabled})"),
+            stack_name,
+        );
+        Ok(())
+    }
+
+    async fn cancel_update_stack(&self, stack_name: &str) -> anyhow::Result<()> {
+        self.record("CancelUpdateStack", stack_name);
+        Ok(())
+    }
+
+    async fn continue_update_rollback(
+        &self,
+        stack_name: &str,
+        _resources_to_skip: Vec<String>,
+    ) -> anyhow::Result<()> {
+        self.record("ContinueUpdateRollback", stack_name);
+        Ok(())
+    }
+
+    async fn create_or_update_change_set(
+        &self,
+        stack_name: &str,
+        _template: &str,
+        change_set_type: ChangeSetType,
+        _options: &ChangeSetOptions,
+    ) -> anyhow::Result<CreateChangeSetOutput> {
+        self.record(
+            &format!("CreateChangeSet({})", change_set_type.as_str()),
+            stack_name,
+        );
+        Ok(CreateChangeSetOutput::builder()
+            .id(MOCK_CHANGE_SET_ID)
+            .stack_id(MOCK_STACK_ID)
+            .build())
+    }
+
+    async fn wait_until_change_set_op_in_progress(
+        &self,
+        change_set_id: &str,
+        _pool_interval: Duration,
+    ) -> anyhow::Result<(ChangeSetStatus, String)> {
+        self.record("WaitChangeSet", change_set_id);
+        Ok(self
+            .state
+            .lock()
+            .unwrap()
+            .change_set_statuses
+            .pop_front()
+            .unwrap_or((ChangeSetStatus::CreateComplete, String::new())))
+    }
+
+    async fn describe_change_set(
+        &self,
+        change_set_id: &str,
+    ) -> anyhow::Result<DescribeChangeSetOutput> {
+        Ok(self
+            .state
+            .lock()
+            .unwrap()
+            .change_set
+            .clone()
+            .unwrap_or_else(|| {
+                DescribeChangeSetOutput::builder()
+                    .change_set_id(change_set_id)
+                    .stack_id(MOCK_STACK_ID)
+                    .status(ChangeSetStatus::CreateComplete)
+                    .build()
+            }))
+    }
+
+    async fn describe_stack_change_set(
+        &self,
+        _stack_name: &str,
+        change_set: &str,
+    ) -> anyhow::Result<DescribeChangeSetOutput> {
+        self.describe_change_set(change_set).await
+    }
+
+    async fn execute_change_set(
+        &self,
+        change_set_id: &str,
+        _disable_rollback: bool,
+    ) -> anyhow::Result<()> {
+        self.record("ExecuteChangeSet", change_set_id);
+        Ok(())
+    }
+
+    async fn delete_change_set(&self, change_set_id: &str) -> anyhow::Result<()> {
+        self.record("DeleteChangeSet", change_set_id);
+        Ok(())
+    }
+
+    async fn list_change_sets(&self, _stack_name: &str) -> anyhow::Result<Vec<ChangeSetSummary>> {
+        Ok(self
+            .state
+            .lock()
+            .unwrap()
+            .pending_change_set
+            .iter()
+            .cloned()
+            .collect())
+    }
+
+    async fn pending_change_set(
+        &self,
+        _stack_name: &str,
+    ) -> anyhow::Result<Option<ChangeSetSummary>> {
+        Ok(self.state.lock().unwrap().pending_change_set.clone())
+    }
+
+    // Like CloudFormation, the change sets of a missing or deleted stack can't be listed
+    async fn reusable_change_set(
+        &self,
+        stack_name: &str,
+        _template: &str,
+        _change_set_type: &ChangeSetType,
+        _options: &ChangeSetOptions,
+    ) -> anyhow::Result<Option<ChangeSetSummary>> {
+        self.record("ListChangeSets", stack_name);
+        match self.state.lock().unwrap().current_status {
+            None | Some(StackStatus::DeleteComplete) => {
+                bail!("Stack with id {stack_name} does not exist")
+            }
+            Some(_) => Ok(None),
+        }
+    }
+
+    async fn resource_schema(&self, _type_name: &str) -> anyhow::Result<Option<String>> {
+        Ok(Some("{}".to_string()))
+    }
+
+    async fn service_quota(
+        &self,
+        _service_code: &str,
+        _quota_name: &str,
+    ) -> anyhow::Result<Option<f64>> {
+        Ok(None)
+    }
+
+    async fn publish_notification(
+        &self,
+        topic_arn: &str,
+        _subject: &str,
+        _message: &str,
+    ) -> anyhow::Result<()> {
+        self.record("Publish", topic_arn);
+        Ok(())
+    }
+
+    async fn put_ssm_parameter(
+        &self,
+        name: &str,
+        _value: &str,
+        _tags: &[(&str, &str)],
+    ) -> anyhow::Result<()> {
+        self.record("PutParameter", name);
+        Ok(())
+    }
+}
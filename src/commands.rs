@@ -1,5 +1,33 @@
+pub mod cancel;
+pub mod changesets;
+pub mod compare;
+pub mod cost;
 pub mod describe;
 pub mod destroy;
+pub mod destroy_all;
+pub mod destroy_select;
+pub mod diff;
+pub mod drift;
+pub mod eval;
+pub mod events;
+pub mod execute;
+pub mod exports;
+pub mod find_resource;
+pub mod gc;
+pub mod graph;
+pub mod history;
 pub mod list;
+pub mod outputs;
 pub mod preview;
+pub mod protect;
+pub mod refactor;
+pub mod resource;
+pub mod self_update;
+pub mod stats;
+pub mod template;
+pub mod tui;
 pub mod up;
+pub mod up_all;
+pub mod validate;
+pub mod version;
+pub mod watch;
@@ -0,0 +1,125 @@
+use anyhow::bail;
+use aws_sdk_cloudformation::types::{ChangeSetStatus, ExecutionStatus, StackStatus};
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+use crate::{
+    audit_log::{self, AuditEvent},
+    aws_client::AwsClient,
+    display::Display,
+    error::PklformationError,
+};
+
+pub struct CancelCommand {
+    client: AwsClient,
+    stack: String,
+    pool_interval: Duration,
+    display: Display,
+}
+
+impl CancelCommand {
+    pub fn new(client: AwsClient, stack: String, pool_interval: Duration) -> Self {
+        Self {
+            client,
+            stack,
+            pool_interval,
+            display: Display::new(),
+        }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        let (status, _reason) = self.client.stack_status(&self.stack).await?;
+        match status {
+            StackStatus::UpdateInProgress => {
+                let result = self.cancel_update().await;
+                audit_log::record(
+                    &self.client,
+                    AuditEvent {
+                        operation: "cancel",
+                        stack: &self.stack,
+                        change_set_arn: None,
+                        confirmation: None,
+                        status: match &result {
+                            Ok(()) => StackStatus::UpdateRollbackComplete.as_str().to_string(),
+                            Err(err) => format!("FAILED ({err})"),
+                        },
+                    },
+                )
+                .await;
+                Ok(result?)
+            }
+            _ => Ok(self.delete_pending_change_sets(&status).await?),
+        }
+    }
+
+    async fn cancel_update(&self) -> anyhow::Result<()> {
+        let start_time = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs_f64();
+        self.client.cancel_update_stack(&self.stack).await?;
+        let (status, reason) = self
+            .client
+            .wait_until_stack_op_in_progress(&self.stack, self.pool_interval)
+            .await?;
+        self.display
+            .print_stack(&self.client.describe_stack(&self.stack).await?);
+        let events = self
+            .client
+            .describe_stack_events(&self.stack)
+            .await?
+            .into_iter()
+            .filter(|p| p.timestamp().map(|t| t.as_secs_f64()).unwrap_or_default() > start_time);
+        self.display.print_resources_errors(events);
+        match status {
+            StackStatus::UpdateRollbackComplete => {
+                info!("Update of stack {} cancelled and rolled back", self.stack);
+                Ok(())
+            }
+            _ => bail!(
+                "Rollback of stack {} ended with status {status:?}: {reason}",
+                self.stack
+            ),
+        }
+    }
+
+    async fn delete_pending_change_sets(&self, status: &StackStatus) -> anyhow::Result<()> {
+        let pending: Vec<_> = self
+            .client
+            .list_change_sets(&self.stack)
+            .await?
+            .into_iter()
+            .filter(|change_set| {
+                matches!(
+                    change_set.status(),
+                    Some(ChangeSetStatus::CreatePending | ChangeSetStatus::CreateInProgress)
+                ) || change_set.execution_status() == Some(&ExecutionStatus::Available)
+            })
+            .collect();
+        if pending.is_empty() {
+            bail!(
+                "Stack {} is in status {status:?} without pending change set, there is nothing to cancel",
+                self.stack
+            );
+        }
+
+        let names: Vec<_> = pending
+            .iter()
+            .filter_map(|change_set| change_set.change_set_name().map(str::to_string))
+            .collect();
+        self.display
+            .print_stack_names("Pending change sets:", &names);
+        if !self
+            .display
+            .ask_confirm("Do you want to delete these change sets?")
+        {
+            return Ok(());
+        }
+        for change_set_id in pending
+            .iter()
+            .filter_map(|change_set| change_set.change_set_id())
+        {
+            self.client.delete_change_set(change_set_id).await?;
+            info!("Change set {change_set_id} deleted");
+        }
+        Ok(())
+    }
+}
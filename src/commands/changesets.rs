@@ -0,0 +1,23 @@
+use crate::{aws_client::AwsClient, display::Display, error::PklformationError};
+
+pub struct ChangesetsCommand {
+    client: AwsClient,
+    stack: String,
+    display: Display,
+}
+
+impl ChangesetsCommand {
+    pub fn new(client: AwsClient, stack: String) -> Self {
+        Self {
+            client,
+            stack,
+            display: Display::new(),
+        }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        let change_sets = self.client.list_change_sets(&self.stack).await?;
+        self.display.print_change_set_summaries(&change_sets);
+        Ok(())
+    }
+}
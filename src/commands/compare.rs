@@ -0,0 +1,97 @@
+use anyhow::{anyhow, Context};
+use aws_sdk_cloudformation::types::{Stack, TemplateStage};
+use serde_json::{Map, Value};
+use tracing::info;
+
+use crate::{
+    aws_client::AwsClient, diff, display::Display, error::PklformationError, project::Project,
+};
+
+struct DeployedStack {
+    template: Value,
+    parameters: Value,
+    tags: Value,
+}
+
+pub struct CompareCommand<'a> {
+    project: &'a Project,
+    stacks: Vec<String>,
+    display: Display,
+}
+
+impl<'a> CompareCommand<'a> {
+    pub fn new(project: &'a Project, stacks: Vec<String>) -> Self {
+        Self {
+            project,
+            stacks,
+            display: Display::new(),
+        }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        let [first, second] = self.stacks.as_slice() else {
+            return Err(anyhow!(
+                "Compare takes exactly two stacks, e.g. --stack app-staging --stack app-prod"
+            )
+            .into());
+        };
+        let (old, new) = (self.load(first).await?, self.load(second).await?);
+        let sections = [
+            ("Template", diff::diff(&old.template, &new.template)),
+            ("Parameters", diff::diff(&old.parameters, &new.parameters)),
+            ("Tags", diff::diff(&old.tags, &new.tags)),
+        ];
+        if sections
+            .iter()
+            .all(|(_, differences)| differences.is_empty())
+        {
+            info!("No divergence between {first} and {second}");
+            return Ok(());
+        }
+        self.display
+            .print_stack_comparison(first, second, &sections);
+        Ok(())
+    }
+
+    // Stacks are compared by their deployed names, environments usually only differ by suffix
+    async fn load(&self, stack: &str) -> anyhow::Result<DeployedStack> {
+        let client = AwsClient::new(self.project.region(stack)).await;
+        let description = client.describe_stack(stack).await?;
+        let template =
+            serde_json::from_str(&client.get_template(stack, TemplateStage::Original).await?)
+                .with_context(|| format!("Deployed template of {stack} is not JSON"))?;
+        Ok(DeployedStack {
+            template,
+            parameters: parameters(&description),
+            tags: tags(&description),
+        })
+    }
+}
+
+fn parameters(stack: &Stack) -> Value {
+    let parameters: Map<_, _> = stack
+        .parameters()
+        .iter()
+        .map(|parameter| {
+            (
+                parameter.parameter_key().unwrap_or_default().to_string(),
+                Value::from(parameter.parameter_value().unwrap_or_default()),
+            )
+        })
+        .collect();
+    Value::Object(parameters)
+}
+
+fn tags(stack: &Stack) -> Value {
+    let tags: Map<_, _> = stack
+        .tags()
+        .iter()
+        .map(|tag| {
+            (
+                tag.key().unwrap_or_default().to_string(),
+                Value::from(tag.value().unwrap_or_default()),
+            )
+        })
+        .collect();
+    Value::Object(tags)
+}
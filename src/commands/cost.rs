@@ -0,0 +1,45 @@
+use chrono::{Days, Utc};
+use std::time::Duration;
+use tracing::info;
+
+use crate::{
+    aws_client::AwsClient,
+    costs,
+    display::{Display, OutputFormat},
+    error::PklformationError,
+};
+
+pub struct CostCommand {
+    client: AwsClient,
+    stack: String,
+    period: Duration,
+    display: Display,
+}
+
+impl CostCommand {
+    pub fn new(client: AwsClient, stack: String, period: Duration, output: OutputFormat) -> Self {
+        Self {
+            client,
+            stack,
+            period,
+            display: Display::new().output(output),
+        }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        // Cost Explorer works on whole days, the end date is exclusive
+        let end = Utc::now().date_naive() + Days::new(1);
+        let days = (self.period.as_secs() / 86400).max(1);
+        let start = end - Days::new(days);
+        let costs = costs::by_service(&self.client, &self.stack, start, end).await?;
+        if costs.is_empty() {
+            info!(
+                "No cost found for stack {}, check that the {} cost allocation tag is activated",
+                self.stack,
+                costs::STACK_NAME_TAG
+            );
+        }
+        self.display.print_costs(&self.stack, start, end, &costs);
+        Ok(())
+    }
+}
@@ -1,34 +1,67 @@
-use crate::{aws_client::AwsClient, display::Display};
+use crate::{
+    aws_client::AwsClient,
+    browser,
+    display::{Display, OutputFormat},
+    error::PklformationError,
+    partition,
+};
 use std::time::Duration;
+use tracing::warn;
 
 pub struct DescribeCommand {
     client: AwsClient,
     stack: String,
     pool_interval: Duration,
+    allow_degraded: bool,
+    open: bool,
     display: Display,
 }
 
 impl DescribeCommand {
-    pub fn new(client: AwsClient, stack: String, pool_interval: Duration) -> Self {
+    pub fn new(
+        client: AwsClient,
+        stack: String,
+        pool_interval: Duration,
+        allow_degraded: bool,
+        open: bool,
+        output: OutputFormat,
+    ) -> Self {
         Self {
             client,
             stack,
             pool_interval,
-            display: Display::new(),
+            allow_degraded,
+            open,
+            display: Display::new().output(output),
         }
     }
 
-    pub async fn run(self) -> anyhow::Result<()> {
+    pub async fn run(self) -> Result<(), PklformationError> {
         let _wait_result = self
             .client
             .wait_until_stack_op_in_progress(&self.stack, self.pool_interval)
             .await;
 
         let stack = self.client.describe_stack(&self.stack).await?;
-        self.display.print_stack(&stack);
-        if let Some(stack_id) = stack.stack_id() {
-            let stack_resources = self.client.list_stack_resources(stack_id).await?;
-            self.display.print_stack_resources(&stack_resources);
+        let stack_resources = match stack.stack_id() {
+            Some(stack_id) => match self.client.list_stack_resources(stack_id).await {
+                Ok(stack_resources) => Some(stack_resources),
+                Err(err)
+                    if self.allow_degraded && matches!(err, PklformationError::AccessDenied(_)) =>
+                {
+                    warn!("{err}, skipping stack resources");
+                    None
+                }
+                Err(err) => return Err(err),
+            },
+            None => None,
+        };
+        self.display
+            .print_stack_description(&stack, stack_resources.as_ref());
+        if self.open {
+            if let Some(console_url) = stack.stack_id().and_then(partition::stack_console_url) {
+                browser::open(&console_url);
+            }
         }
         Ok(())
     }
@@ -1,68 +1,322 @@
-use aws_sdk_cloudformation::types::StackStatus;
+use anyhow::{anyhow, bail, Context};
+use aws_sdk_cloudformation::types::{ResourceStatus, Stack, StackStatus, TemplateStage};
+use clap::Args;
+use serde_json::Value;
 
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use tracing::{error, info};
+use std::{
+    collections::BTreeMap,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tracing::{error, info, warn};
 
-use crate::{aws_client::AwsClient, display::Display};
+use crate::{
+    audit_log::{self, AuditEvent, Confirmation},
+    aws_client::AwsClient,
+    cloudformation_api::CloudFormationApi,
+    display::Display,
+    error::PklformationError,
+    history::{self, Run},
+    notifications::{self, Completion},
+};
 
-pub struct DestroyCommand {
-    client: AwsClient,
+#[derive(Args, Clone, Default)]
+pub struct DestroyOptions {
+    /// Only report the resources that would be deleted or retained, and the exports that would disappear
+    #[arg(long, conflicts_with_all = ["all", "select"])]
+    pub impact: bool,
+    /// Delete a stack stuck in DELETE_FAILED, retaining the resources that failed to delete
+    #[arg(long, conflicts_with_all = ["all", "select", "impact"])]
+    pub force_delete: bool,
+    /// Skip the confirmations, stacks with termination protection are still refused
+    #[arg(long)]
+    pub yes: bool,
+}
+
+pub struct DestroyCommand<C = AwsClient> {
+    client: C,
     stack: String,
     pool_interval: Duration,
+    options: DestroyOptions,
     display: Display,
+    confirmation: Mutex<Option<Confirmation>>,
 }
 
-impl DestroyCommand {
-    pub fn new(client: AwsClient, stack: String, pool_interval: Duration) -> Self {
+impl<C: CloudFormationApi> DestroyCommand<C> {
+    pub fn new(client: C, stack: String, pool_interval: Duration, options: DestroyOptions) -> Self {
         Self {
             client,
             stack,
             pool_interval,
+            options,
             display: Display::new(),
+            confirmation: Mutex::new(None),
         }
     }
 
-    pub async fn run(self) -> anyhow::Result<()> {
+    pub async fn run(self) -> Result<(), PklformationError> {
         let _wait_result = self
             .client
             .wait_until_stack_op_in_progress(&self.stack, self.pool_interval)
             .await;
 
-        let start_time = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs_f64();
+        if self.options.impact {
+            return Ok(self.print_impact().await?);
+        }
+
         let stack = self.client.describe_stack(&self.stack).await?;
         self.display.print_stack(&stack);
         if let Some(stack_id) = stack.stack_id() {
             let stack_resources = self.client.list_stack_resources(stack_id).await?;
-            self.display.print_stack_resources(&stack_resources);
+            self.display
+                .print_stack_resources(stack_id, &stack_resources);
+        }
+
+        // CloudFormation only fails on exports in use once the deletion is under way
+        let imported = self.imported_exports(&stack).await?;
+        if !imported.is_empty() {
+            self.display
+                .print_stack_names("Exports imported by other stacks:", &imported);
+            return Err(anyhow!(
+                "Stack {} has exports still in use, destroy the importing stacks first",
+                self.stack
+            )
+            .into());
+        }
+
+        if self.options.force_delete && stack.stack_status() != Some(&StackStatus::DeleteFailed) {
+            return Err(anyhow!(
+                "Stack {} is not in status DeleteFailed, --force-delete only applies to failed deletions",
+                self.stack
+            ).into());
+        }
+
+        // Disabled only once nothing can refuse the deletion anymore, a refused destroy keeps
+        // the stack protected
+        let protected = stack.enable_termination_protection() == Some(true);
+        if protected
+            && (self.options.yes
+                || !self.display.ask_confirm(&format!(
+                    "Stack {} has termination protection enabled, do you want to disable it?",
+                    self.stack
+                )))
+        {
+            return Err(anyhow!(Self::protected_error(&self.stack)).into());
+        }
+
+        if self.confirm_destroy() && self.confirm_force_delete().await? {
+            *self.confirmation.lock().unwrap() = Some(if self.options.yes {
+                Confirmation::Skipped
+            } else {
+                Confirmation::Accepted
+            });
+            if protected {
+                self.client
+                    .update_termination_protection(&self.stack, false)
+                    .await?;
+                info!("Termination protection of stack {} disabled", self.stack);
+            }
+            let status = self.delete().await?;
+            if status != StackStatus::DeleteComplete {
+                return Err(PklformationError::StackFailed {
+                    stack: self.stack.clone(),
+                    status,
+                    reason: None,
+                });
+            }
+        } else {
+            audit_log::record(
+                &self.client,
+                AuditEvent {
+                    operation: "destroy",
+                    stack: &self.stack,
+                    change_set_arn: None,
+                    confirmation: Some(Confirmation::Declined),
+                    status: stack
+                        .stack_status()
+                        .map(|status| status.as_str().to_string())
+                        .unwrap_or_default(),
+                },
+            )
+            .await;
         }
 
-        if self.display.ask_confirm("Do you want to continue?") {
-            self.client.delete_stack(stack.stack_id().unwrap()).await?;
-
-            let (op_status, _reason) = self
-                .client
-                .wait_until_stack_op_in_progress(&self.stack, self.pool_interval)
-                .await?;
-
-            match op_status {
-                StackStatus::DeleteComplete => {
-                    info!("Destroy compleated successfully!")
-                }
-                _ => {
-                    error!("Up failed with status: {op_status:?}");
-                    let events = self
-                        .client
-                        .describe_stack_events(&self.stack)
-                        .await?
-                        .into_iter()
-                        .filter(|p| {
-                            p.timestamp().map(|t| t.as_secs_f64()).unwrap_or_default() > start_time
-                        });
-                    self.display.print_resources_errors(events);
-                }
+        Ok(())
+    }
+
+    async fn imported_exports(&self, stack: &Stack) -> anyhow::Result<Vec<String>> {
+        let mut imported = Vec::new();
+        for export_name in stack.outputs().iter().filter_map(|o| o.export_name()) {
+            let importers = self.client.list_imports(export_name).await?;
+            if !importers.is_empty() {
+                imported.push(format!("{export_name}: {}", importers.join(", ")));
             }
         }
+        Ok(imported)
+    }
+
+    fn confirm_destroy(&self) -> bool {
+        self.options.yes
+            || self.display.ask_typed_confirm(
+                &format!("Type the stack name to destroy {}", self.stack),
+                &self.stack,
+            )
+    }
+
+    async fn confirm_force_delete(&self) -> anyhow::Result<bool> {
+        if !self.options.force_delete || self.options.yes {
+            return Ok(true);
+        }
+        let failed: Vec<_> = self
+            .client
+            .list_all_stack_resources(&self.stack)
+            .await?
+            .into_iter()
+            .filter(|resource| resource.resource_status() == Some(&ResourceStatus::DeleteFailed))
+            .filter_map(|resource| resource.physical_resource_id().map(str::to_string))
+            .collect();
+        self.display
+            .print_stack_names("Resources that failed to delete:", &failed);
+        Ok(self.display.ask_confirm(
+            "Force delete retains these resources, they won't belong to any stack anymore. Do you want to continue?",
+        ))
+    }
+
+    fn protected_error(stack: &str) -> String {
+        format!("Stack {stack} has termination protection enabled, disable it with `pklformation protect --stack {stack} --off`")
+    }
+
+    async fn print_impact(&self) -> anyhow::Result<()> {
+        let stack = self.client.describe_stack(&self.stack).await?;
+        let resources = self.client.list_all_stack_resources(&self.stack).await?;
+        let deletion_policies = self.deletion_policies().await;
+        let resources: Vec<_> = resources
+            .into_iter()
+            .map(|resource| {
+                let deletion_policy = resource
+                    .logical_resource_id()
+                    .and_then(|logical_id| deletion_policies.get(logical_id))
+                    .cloned();
+                (resource, deletion_policy)
+            })
+            .collect();
+
+        let mut exports = Vec::new();
+        for export_name in stack.outputs().iter().filter_map(|o| o.export_name()) {
+            let importers = self.client.list_imports(export_name).await?;
+            exports.push((export_name.to_string(), importers));
+        }
 
+        self.display.print_destroy_impact(&resources, &exports);
         Ok(())
     }
+
+    async fn deletion_policies(&self) -> BTreeMap<String, String> {
+        let template = match self
+            .client
+            .get_template(&self.stack, TemplateStage::Original)
+            .await
+            .map(|template| serde_json::from_str::<Value>(&template))
+        {
+            Ok(Ok(template)) => template,
+            Ok(Err(err)) => {
+                warn!("Deployed template is not JSON, deletion policies are unknown: {err}");
+                return BTreeMap::new();
+            }
+            Err(err) => {
+                warn!("Unable to get the deployed template, deletion policies are unknown: {err}");
+                return BTreeMap::new();
+            }
+        };
+        template
+            .get("Resources")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+            .filter_map(|(logical_id, resource)| {
+                let deletion_policy = resource.get("DeletionPolicy")?.as_str()?;
+                Some((logical_id.clone(), deletion_policy.to_string()))
+            })
+            .collect()
+    }
+
+    pub async fn delete(&self) -> anyhow::Result<StackStatus> {
+        let started = Instant::now();
+        let stack = self.client.describe_stack(&self.stack).await.ok();
+        let result = self.delete_stack().await;
+        let completion = Completion {
+            operation: "destroy",
+            stack: &self.stack,
+            stack_id: stack.as_ref().and_then(|stack| stack.stack_id()),
+            duration: started.elapsed(),
+            result: &result,
+        };
+        history::record(
+            &self.client,
+            Run {
+                operation: "destroy",
+                stack: &self.stack,
+                duration: completion.duration,
+                template: None,
+                parameters: &BTreeMap::new(),
+                change_set_id: None,
+                outcome: completion.status(),
+            },
+        )
+        .await;
+        let confirmation = *self.confirmation.lock().unwrap();
+        audit_log::record(
+            &self.client,
+            AuditEvent {
+                operation: "destroy",
+                stack: &self.stack,
+                change_set_arn: None,
+                confirmation,
+                status: completion.status(),
+            },
+        )
+        .await;
+        notifications::notify(&self.client, completion).await;
+        result
+    }
+
+    async fn delete_stack(&self) -> anyhow::Result<StackStatus> {
+        let start_time = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs_f64();
+        let stack = self.client.describe_stack(&self.stack).await?;
+        if stack.enable_termination_protection() == Some(true) {
+            bail!(Self::protected_error(&self.stack));
+        }
+        // Deleted stacks can only be described by their id
+        let stack_id = stack.stack_id().context("Stack without id")?;
+        if self.options.force_delete {
+            self.client.force_delete_stack(stack_id).await?;
+        } else {
+            self.client.delete_stack(stack_id).await?;
+        }
+
+        let (op_status, _reason) = self
+            .client
+            .wait_until_stack_op_in_progress(stack_id, self.pool_interval)
+            .await?;
+
+        match op_status {
+            StackStatus::DeleteComplete => {
+                info!("Destroy compleated successfully!")
+            }
+            _ => {
+                error!("Destroy failed with status: {op_status:?}");
+                let events = self
+                    .client
+                    .describe_stack_events(stack_id)
+                    .await?
+                    .into_iter()
+                    .filter(|p| {
+                        p.timestamp().map(|t| t.as_secs_f64()).unwrap_or_default() > start_time
+                    });
+                self.display.print_resources_errors(events);
+            }
+        }
+
+        Ok(op_status)
+    }
 }
@@ -0,0 +1,173 @@
+use anyhow::anyhow;
+use aws_sdk_cloudformation::types::StackStatus;
+
+use std::{path::PathBuf, time::Duration};
+use tracing::{info, span, Level};
+
+use crate::{
+    aws_client::AwsClient,
+    commands::destroy::{DestroyCommand, DestroyOptions},
+    display::Display,
+    error::PklformationError,
+    events_log::EventsLog,
+    progress::ProgressStream,
+    project::Project,
+    stack_graph::StackGraph,
+};
+
+pub struct DestroyAllCommand<'a> {
+    project: &'a Project,
+    pool_interval: Duration,
+    events_log: Option<PathBuf>,
+    progress_stream: Option<ProgressStream>,
+    allow_protected: bool,
+    yes: bool,
+    display: Display,
+}
+
+impl<'a> DestroyAllCommand<'a> {
+    pub fn new(
+        project: &'a Project,
+        pool_interval: Duration,
+        events_log: Option<PathBuf>,
+        progress_stream: Option<ProgressStream>,
+        allow_protected: bool,
+        yes: bool,
+    ) -> Self {
+        Self {
+            project,
+            pool_interval,
+            events_log,
+            progress_stream,
+            allow_protected,
+            yes,
+            display: Display::new(),
+        }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        let mut order = StackGraph::from_project(self.project)?.deployment_order()?;
+        order.reverse();
+        let protected: Vec<_> = order
+            .iter()
+            .filter(|stack| self.project.is_protected(stack))
+            .cloned()
+            .collect();
+        if !protected.is_empty() && !self.allow_protected {
+            return Err(anyhow!(
+                "Protected stacks in the project file: {}, pass --allow-protected to destroy them",
+                protected.join(", ")
+            )
+            .into());
+        }
+
+        let mut deployed = Vec::new();
+        for stack in order {
+            let client = self.stack_client(&stack).await?;
+            let stack = self.project.stack_name(&stack);
+            match client.describe_stack(&stack).await {
+                Ok(_) => deployed.push((stack, client)),
+                Err(_) => info!("Stack {stack} is not deployed, skipping it"),
+            }
+        }
+        if deployed.is_empty() {
+            info!("No project stack is deployed");
+            return Ok(());
+        }
+
+        let stacks: Vec<_> = deployed.iter().map(|(stack, _)| stack.clone()).collect();
+        self.display
+            .print_stack_names("Stacks to destroy:", &stacks);
+        if !self.yes
+            && !self.display.ask_typed_confirm(
+                &format!("Type {} to destroy all these stacks", stacks.len()),
+                &stacks.len().to_string(),
+            )
+        {
+            return Ok(());
+        }
+
+        let mut results: Vec<(String, anyhow::Result<StackStatus>)> = Vec::new();
+        let mut destroyed = Vec::new();
+        for (stack, client) in deployed {
+            if results.iter().any(|(_, result)| !Self::succeeded(result)) {
+                results.push((stack, Err(anyhow!("Skipped after a previous failure"))));
+                continue;
+            }
+
+            let span = span!(Level::INFO, "destroy", stack = stack);
+            let _enter = span.enter();
+            let result = match Self::external_importers(&client, &stack, &destroyed).await {
+                Ok(importers) if !importers.is_empty() => Err(anyhow!(
+                    "Exports still imported by {}",
+                    importers.join(", ")
+                )),
+                Ok(_) => {
+                    DestroyCommand::new(
+                        client,
+                        stack.clone(),
+                        self.pool_interval,
+                        DestroyOptions::default(),
+                    )
+                    .delete()
+                    .await
+                }
+                Err(err) => Err(err),
+            };
+            if Self::succeeded(&result) {
+                destroyed.push(stack.clone());
+            }
+            results.push((stack, result));
+        }
+
+        self.display.print_operation_results("Stacks:", &results);
+        let failures: Vec<_> = results
+            .iter()
+            .filter(|(_, result)| !Self::succeeded(result))
+            .map(|(stack, _)| stack.as_str())
+            .collect();
+        if !failures.is_empty() {
+            return Err(anyhow!(
+                "Destroy failed for {} stack(s): {}",
+                failures.len(),
+                failures.join(", ")
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    async fn stack_client(&self, stack: &str) -> anyhow::Result<AwsClient> {
+        let client = AwsClient::new(self.project.region(stack))
+            .await
+            .with_notifications(self.project.notifications.clone())
+            .with_progress_stream(self.progress_stream.clone());
+        Ok(match self.events_log.as_ref() {
+            Some(path) => client.with_events_log(EventsLog::open(path)?),
+            None => client,
+        })
+    }
+
+    pub(crate) async fn external_importers(
+        client: &AwsClient,
+        stack: &str,
+        destroyed: &[String],
+    ) -> anyhow::Result<Vec<String>> {
+        let mut importers = Vec::new();
+        for output in client.describe_stack(stack).await?.outputs() {
+            let Some(export_name) = output.export_name() else {
+                continue;
+            };
+            for importer in client.list_imports(export_name).await? {
+                if !destroyed.contains(&importer) && !importers.contains(&importer) {
+                    importers.push(importer);
+                }
+            }
+        }
+        Ok(importers)
+    }
+
+    fn succeeded(result: &anyhow::Result<StackStatus>) -> bool {
+        matches!(result, Ok(StackStatus::DeleteComplete))
+    }
+}
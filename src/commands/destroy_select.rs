@@ -0,0 +1,213 @@
+use anyhow::anyhow;
+use aws_sdk_cloudformation::types::StackStatus;
+
+use std::{path::PathBuf, time::Duration};
+use tracing::{info, span, Level};
+
+use crate::{
+    aws_client::AwsClient,
+    commands::{
+        destroy::{DestroyCommand, DestroyOptions},
+        destroy_all::DestroyAllCommand,
+    },
+    display::Display,
+    error::PklformationError,
+    events_log::EventsLog,
+    progress::ProgressStream,
+    project::Project,
+};
+
+#[derive(Clone, Default)]
+pub struct StackFilter {
+    pub prefix: Option<String>,
+    pub tags: Vec<(String, String)>,
+}
+
+pub struct DestroySelectCommand<'a> {
+    project: &'a Project,
+    pool_interval: Duration,
+    filter: StackFilter,
+    events_log: Option<PathBuf>,
+    progress_stream: Option<ProgressStream>,
+    allow_protected: bool,
+    yes: bool,
+    display: Display,
+}
+
+impl<'a> DestroySelectCommand<'a> {
+    pub fn new(
+        project: &'a Project,
+        pool_interval: Duration,
+        filter: StackFilter,
+        events_log: Option<PathBuf>,
+        progress_stream: Option<ProgressStream>,
+        allow_protected: bool,
+        yes: bool,
+    ) -> Self {
+        Self {
+            project,
+            pool_interval,
+            filter,
+            events_log,
+            progress_stream,
+            allow_protected,
+            yes,
+            display: Display::new(),
+        }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        let client = self.client().await?;
+        let candidates = self.candidates(&client).await?;
+        if candidates.is_empty() {
+            info!("No stack matches the filters");
+            return Ok(());
+        }
+
+        let selected: Vec<_> = self
+            .display
+            .ask_multi_select("Stacks to destroy", &candidates)
+            .into_iter()
+            .map(|index| candidates[index].clone())
+            .collect();
+        if selected.is_empty() {
+            info!("No stack selected");
+            return Ok(());
+        }
+
+        for stack in &selected {
+            let resources: Vec<_> = client
+                .list_all_stack_resources(stack)
+                .await?
+                .iter()
+                .map(|resource| {
+                    format!(
+                        "{} ({})",
+                        resource.logical_resource_id().unwrap_or_default(),
+                        resource.resource_type().unwrap_or_default()
+                    )
+                })
+                .collect();
+            self.display.print_stack_names(
+                &format!("{stack}: {} resource(s) to delete", resources.len()),
+                &resources,
+            );
+        }
+        if !self.yes
+            && !self.display.ask_typed_confirm(
+                &format!("Type {} to destroy the selected stacks", selected.len()),
+                &selected.len().to_string(),
+            )
+        {
+            return Ok(());
+        }
+
+        // The stacks are unrelated, a failure doesn't stop the others
+        let mut results: Vec<(String, anyhow::Result<StackStatus>)> = Vec::new();
+        let mut destroyed = Vec::new();
+        for stack in selected {
+            let span = span!(Level::INFO, "destroy", stack = stack);
+            let _enter = span.enter();
+            let client = self.client().await?;
+            let result =
+                match DestroyAllCommand::external_importers(&client, &stack, &destroyed).await {
+                    Ok(importers) if !importers.is_empty() => Err(anyhow!(
+                        "Exports still imported by {}",
+                        importers.join(", ")
+                    )),
+                    Ok(_) => {
+                        DestroyCommand::new(
+                            client,
+                            stack.clone(),
+                            self.pool_interval,
+                            DestroyOptions::default(),
+                        )
+                        .delete()
+                        .await
+                    }
+                    Err(err) => Err(err),
+                };
+            info!(
+                "Stack {stack}: {}",
+                match &result {
+                    Ok(status) => status.as_str().to_string(),
+                    Err(err) => err.to_string(),
+                }
+            );
+            if matches!(result, Ok(StackStatus::DeleteComplete)) {
+                destroyed.push(stack.clone());
+            }
+            results.push((stack, result));
+        }
+
+        self.display.print_operation_results("Stacks:", &results);
+        let failures: Vec<_> = results
+            .iter()
+            .filter(|(_, result)| !matches!(result, Ok(StackStatus::DeleteComplete)))
+            .map(|(stack, _)| stack.as_str())
+            .collect();
+        if !failures.is_empty() {
+            return Err(anyhow!(
+                "Destroy failed for {} stack(s): {}",
+                failures.len(),
+                failures.join(", ")
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    async fn client(&self) -> anyhow::Result<AwsClient> {
+        let client = AwsClient::new(self.project.default_region())
+            .await
+            .with_notifications(self.project.notifications.clone())
+            .with_progress_stream(self.progress_stream.clone());
+        Ok(match self.events_log.as_ref() {
+            Some(path) => client.with_events_log(EventsLog::open(path)?),
+            None => client,
+        })
+    }
+
+    async fn candidates(&self, client: &AwsClient) -> anyhow::Result<Vec<String>> {
+        let protected: Vec<_> = self
+            .project
+            .stacks
+            .keys()
+            .filter(|stack| self.project.is_protected(stack))
+            .map(|stack| self.project.stack_name(stack))
+            .collect();
+
+        let mut candidates = Vec::new();
+        for summary in client.list_existing_stacks().await? {
+            let Some(stack) = summary.stack_name() else {
+                continue;
+            };
+            if self
+                .filter
+                .prefix
+                .as_deref()
+                .is_some_and(|prefix| !stack.starts_with(prefix))
+            {
+                continue;
+            }
+            if protected.iter().any(|name| name == stack) && !self.allow_protected {
+                info!("Stack {stack} is protected in the project file, skipping it");
+                continue;
+            }
+            // Stack summaries don't have the tags
+            if !self.filter.tags.is_empty() {
+                let tags = client.describe_stack(stack).await?.tags().to_vec();
+                let matches = self.filter.tags.iter().all(|(key, value)| {
+                    tags.iter()
+                        .any(|tag| tag.key() == Some(key) && tag.value() == Some(value))
+                });
+                if !matches {
+                    continue;
+                }
+            }
+            candidates.push(stack.to_string());
+        }
+        candidates.sort();
+        Ok(candidates)
+    }
+}
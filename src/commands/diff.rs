@@ -0,0 +1,64 @@
+use anyhow::Context;
+use aws_sdk_cloudformation::types::TemplateStage;
+use serde_json::Value;
+
+use std::path::PathBuf;
+use tracing::info;
+
+use crate::{
+    aws_client::AwsClient, diff, display::Display, error::PklformationError, pkl::PklOptions,
+    template_format,
+};
+
+pub struct DiffCommand {
+    client: AwsClient,
+    stack: String,
+    template: PathBuf,
+    pkl_options: PklOptions,
+    display: Display,
+}
+
+impl DiffCommand {
+    pub fn new(
+        client: AwsClient,
+        stack: String,
+        template: PathBuf,
+        pkl_options: PklOptions,
+    ) -> Self {
+        Self {
+            client,
+            stack,
+            template,
+            pkl_options,
+            display: Display::new(),
+        }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        let local_template: Value = serde_json::from_str(&template_format::load(
+            &self.template,
+            None,
+            &self.pkl_options,
+        )?)
+        .context("Invalid template JSON")?;
+        let deployed_template: Value = serde_json::from_str(
+            &self
+                .client
+                .get_template(&self.stack, TemplateStage::Original)
+                .await?,
+        )
+        .context("Deployed template is not JSON")?;
+
+        let differences = diff::diff(&deployed_template, &local_template);
+        if differences.is_empty() {
+            info!(
+                "No differences with the deployed template of {}",
+                self.stack
+            );
+        } else {
+            self.display.print_template_diff(&differences);
+        }
+
+        Ok(())
+    }
+}
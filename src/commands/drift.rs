@@ -0,0 +1,103 @@
+use anyhow::anyhow;
+use std::{collections::BTreeMap, time::Duration};
+use tracing::{info, span, warn, Level};
+
+use crate::{
+    aws_client::AwsClient,
+    display::{Display, OutputFormat},
+    drift::{self, DriftReport},
+    error::PklformationError,
+    notifications,
+    project::Project,
+    timings,
+};
+
+pub struct DriftCommand<'a> {
+    project: &'a Project,
+    stacks: Vec<String>,
+    pool_interval: Duration,
+    watch: Option<Duration>,
+    notify: bool,
+    display: Display,
+}
+
+impl<'a> DriftCommand<'a> {
+    pub fn new(
+        project: &'a Project,
+        stacks: Vec<String>,
+        pool_interval: Duration,
+        watch: Option<Duration>,
+        notify: bool,
+        output: OutputFormat,
+    ) -> Self {
+        Self {
+            project,
+            stacks,
+            pool_interval,
+            watch,
+            notify,
+            display: Display::new().output(output),
+        }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        let Some(interval) = self.watch else {
+            let reports = self.detect(&mut BTreeMap::new()).await;
+            let drifted = reports.iter().filter(|report| report.is_drifted()).count();
+            if drifted > 0 {
+                return Err(anyhow!("Drift detected on {drifted} stack(s)").into());
+            }
+            let failed = reports
+                .iter()
+                .filter(|report| report.error.is_some())
+                .count();
+            if failed > 0 {
+                return Err(anyhow!("Drift detection failed on {failed} stack(s)").into());
+            }
+            return Ok(());
+        };
+
+        // Only the drift that appeared or changed since the previous round is notified
+        let mut notified = BTreeMap::new();
+        loop {
+            self.detect(&mut notified).await;
+            info!(
+                "Next drift detection in {}",
+                timings::format_duration(interval)
+            );
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    async fn client(&self, stack: &str) -> AwsClient {
+        AwsClient::new(self.project.region(stack))
+            .await
+            .with_notifications(self.project.notifications.clone())
+    }
+
+    async fn detect(&self, notified: &mut BTreeMap<String, Vec<String>>) -> Vec<DriftReport> {
+        let mut reports = Vec::new();
+        for stack in &self.stacks {
+            let span = span!(Level::INFO, "drift", stack = stack);
+            let _enter = span.enter();
+            let client = self.client(stack).await;
+            let report =
+                drift::detect(&client, &self.project.stack_name(stack), self.pool_interval).await;
+            if let Some(error) = &report.error {
+                warn!("Drift detection of stack {stack}: {error}");
+            }
+            let fingerprint = report.fingerprint();
+            if report.is_drifted() && notified.get(stack) != Some(&fingerprint) {
+                if self.notify {
+                    notifications::notify_drift(&client, &report).await;
+                }
+                notified.insert(stack.clone(), fingerprint);
+            } else if !report.is_drifted() && report.error.is_none() {
+                notified.remove(stack);
+            }
+            reports.push(report);
+        }
+        self.display.print_drift_reports(&reports);
+        reports
+    }
+}
@@ -0,0 +1,40 @@
+use anyhow::Context;
+use std::path::PathBuf;
+use tracing::info;
+
+use crate::{error::PklformationError, pkl::PklOptions, template_format};
+
+pub struct EvalCommand {
+    template: PathBuf,
+    pkl_options: PklOptions,
+    out: Option<PathBuf>,
+}
+
+impl EvalCommand {
+    pub fn new(template: PathBuf, pkl_options: PklOptions, out: Option<PathBuf>) -> Self {
+        Self {
+            template,
+            pkl_options,
+            out,
+        }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        let template = template_format::load(&self.template, None, &self.pkl_options)?;
+
+        match self.out {
+            Some(out) => {
+                std::fs::write(&out, template)
+                    .with_context(|| format!("Unable to write {}", out.display()))?;
+                info!(
+                    "Template {} evaluated to {}",
+                    self.template.display(),
+                    out.display()
+                );
+            }
+            None => println!("{template}"),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,112 @@
+use aws_sdk_cloudformation::types::StackEvent;
+use chrono::{DateTime, Utc};
+use clap::Args;
+use tracing::info;
+
+use crate::{
+    aws_client::AwsClient,
+    commands::gc::parse_age,
+    display::{Display, OutputFormat},
+    error::PklformationError,
+};
+
+// Either an age relative to now, like 2h, or an RFC 3339 date
+pub fn parse_time(arg: &str) -> Result<DateTime<Utc>, String> {
+    match DateTime::parse_from_rfc3339(arg) {
+        Ok(date_time) => Ok(date_time.with_timezone(&Utc)),
+        Err(_) => Ok(Utc::now() - parse_age(arg)?),
+    }
+}
+
+#[derive(Args, Clone, Default)]
+pub struct EventFilter {
+    /// Only the events of this resource
+    #[arg(long)]
+    pub logical_id: Option<String>,
+    /// Only the events whose status contains this text, like FAILED or DELETE
+    #[arg(long)]
+    pub status: Option<String>,
+    /// Only the events after this time, an age like 2h or an RFC 3339 date
+    #[arg(long, value_parser = parse_time)]
+    pub since: Option<DateTime<Utc>>,
+    /// Only the events before this time, an age like 30m or an RFC 3339 date
+    #[arg(long, value_parser = parse_time)]
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl EventFilter {
+    fn timestamp(event: &StackEvent) -> Option<i64> {
+        event.timestamp().map(|timestamp| timestamp.secs())
+    }
+
+    fn is_older(&self, event: &StackEvent) -> bool {
+        self.since
+            .zip(Self::timestamp(event))
+            .is_some_and(|(since, timestamp)| timestamp < since.timestamp())
+    }
+
+    fn matches(&self, event: &StackEvent) -> bool {
+        let logical_id = self
+            .logical_id
+            .as_deref()
+            .is_none_or(|logical_id| event.logical_resource_id() == Some(logical_id));
+        let status = self.status.as_deref().is_none_or(|status| {
+            event.resource_status().is_some_and(|resource_status| {
+                resource_status
+                    .as_str()
+                    .contains(&status.to_ascii_uppercase())
+            })
+        });
+        let until = self
+            .until
+            .zip(Self::timestamp(event))
+            .is_none_or(|(until, timestamp)| timestamp <= until.timestamp());
+        logical_id && status && until && !self.is_older(event)
+    }
+}
+
+pub struct EventsCommand {
+    client: AwsClient,
+    stack: String,
+    filter: EventFilter,
+    limit: Option<usize>,
+    display: Display,
+}
+
+impl EventsCommand {
+    pub fn new(
+        client: AwsClient,
+        stack: String,
+        filter: EventFilter,
+        limit: Option<usize>,
+        output: OutputFormat,
+    ) -> Self {
+        Self {
+            client,
+            stack,
+            filter,
+            limit,
+            display: Display::new().output(output),
+        }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        let mut events = Vec::new();
+        self.client
+            .stack_events_by_page(&self.stack, |page| {
+                let reached_since = page.iter().any(|event| self.filter.is_older(event));
+                events.extend(page.into_iter().filter(|event| self.filter.matches(event)));
+                let reached_limit = self.limit.is_some_and(|limit| events.len() >= limit);
+                !reached_since && !reached_limit
+            })
+            .await?;
+        if let Some(limit) = self.limit {
+            events.truncate(limit);
+        }
+        if events.is_empty() {
+            info!("No event of stack {} matches the filters", self.stack);
+        }
+        self.display.print_filtered_stack_events(&events);
+        Ok(())
+    }
+}
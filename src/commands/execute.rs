@@ -0,0 +1,160 @@
+use anyhow::{anyhow, bail, Context};
+use aws_sdk_cloudformation::{
+    operation::describe_change_set::DescribeChangeSetOutput,
+    types::{ChangeSetStatus, ExecutionStatus, StackStatus},
+};
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{error, info};
+
+use crate::{
+    approval::ApprovalConfig,
+    audit_log::{self, AuditEvent, Confirmation},
+    aws_client::AwsClient,
+    cloudformation_api::CloudFormationApi,
+    display::Display,
+    error::PklformationError,
+    validation::Validator,
+};
+
+pub struct ExecuteCommand<C = AwsClient> {
+    client: C,
+    stack: String,
+    change_set: String,
+    pool_interval: Duration,
+    validator: Validator,
+    protected: bool,
+    approval: Option<ApprovalConfig>,
+    display: Display,
+}
+
+impl<C: CloudFormationApi> ExecuteCommand<C> {
+    pub fn new(
+        client: C,
+        stack: String,
+        change_set: String,
+        pool_interval: Duration,
+        validator: Validator,
+        protected: bool,
+        approval: Option<ApprovalConfig>,
+    ) -> Self {
+        Self {
+            client,
+            stack,
+            change_set,
+            pool_interval,
+            validator,
+            protected,
+            approval,
+            display: Display::new(),
+        }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        let change_set = self
+            .client
+            .describe_stack_change_set(&self.stack, &self.change_set)
+            .await?;
+        self.display.print_change_set(&change_set);
+        if change_set.status() != Some(&ChangeSetStatus::CreateComplete)
+            || change_set.execution_status() != Some(&ExecutionStatus::Available)
+        {
+            return Err(anyhow!(
+                "Change set {} can't be executed (status {:?}, execution status {:?})",
+                self.change_set,
+                change_set.status(),
+                change_set.execution_status()
+            )
+            .into());
+        }
+        let violations = self.validator.validate_change_set(&change_set);
+        if !violations.is_empty() {
+            self.display.print_violations(&violations);
+            return Err(PklformationError::Validation(format!(
+                "Change set {} violates {} guardrail(s), deploy with `pklformation up` to override them",
+                self.change_set,
+                violations.len()
+            )));
+        }
+
+        let change_set_id = change_set.change_set_id().context("Empty change set id")?;
+        let approval_only = self
+            .approval
+            .as_ref()
+            .is_some_and(|approval| approval.replace_confirm);
+        let accepted = approval_only
+            || (self
+                .display
+                .ask_confirm("Do you want to execute this change set?")
+                && (!self.protected
+                    || self.display.ask_confirm(&format!(
+                        "Stack {} is protected, do you really want to apply these changes?",
+                        self.stack
+                    ))));
+        let result = if accepted {
+            self.execute(&change_set).await
+        } else {
+            Ok(())
+        };
+        let status = match &result {
+            Ok(()) => match self.client.stack_status(&self.stack).await {
+                Ok((status, _reason)) => status.as_str().to_string(),
+                Err(err) => format!("UNKNOWN ({err})"),
+            },
+            Err(err) => format!("FAILED ({err})"),
+        };
+        audit_log::record(
+            &self.client,
+            AuditEvent {
+                operation: "execute",
+                stack: &self.stack,
+                change_set_arn: Some(change_set_id),
+                confirmation: Some(if approval_only {
+                    Confirmation::Skipped
+                } else {
+                    Confirmation::from_answer(accepted)
+                }),
+                status,
+            },
+        )
+        .await;
+        Ok(result?)
+    }
+
+    async fn execute(&self, change_set: &DescribeChangeSetOutput) -> anyhow::Result<()> {
+        if let Some(approval) = &self.approval {
+            approval.request(&self.stack, change_set).await?;
+        }
+        let change_set_id = change_set.change_set_id().context("Empty change set id")?;
+        let start_time = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs_f64();
+        self.client.execute_change_set(change_set_id, false).await?;
+        let (status, reason) = self
+            .client
+            .wait_until_stack_op_in_progress(&self.stack, self.pool_interval)
+            .await?;
+        match status {
+            StackStatus::CreateComplete
+            | StackStatus::UpdateComplete
+            | StackStatus::ImportComplete => {
+                info!("Change set {} executed successfully!", self.change_set);
+                Ok(())
+            }
+            _ => {
+                error!("Execution failed with status: {status:?}");
+                let events = self
+                    .client
+                    .describe_stack_events(&self.stack)
+                    .await?
+                    .into_iter()
+                    .filter(|p| {
+                        p.timestamp().map(|t| t.as_secs_f64()).unwrap_or_default() > start_time
+                    });
+                self.display.print_resources_errors(events);
+                bail!(
+                    "Execution of change set {} failed: {reason}",
+                    self.change_set
+                )
+            }
+        }
+    }
+}
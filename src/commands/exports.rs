@@ -0,0 +1,58 @@
+use crate::{
+    aws_client::AwsClient,
+    display::{Display, OutputFormat},
+    error::PklformationError,
+    views::{self, ExportView},
+};
+
+pub struct ExportsCommand {
+    client: AwsClient,
+    stack: Option<String>,
+    show_importers: bool,
+    display: Display,
+}
+
+impl ExportsCommand {
+    pub fn new(
+        client: AwsClient,
+        stack: Option<String>,
+        show_importers: bool,
+        output: OutputFormat,
+    ) -> Self {
+        Self {
+            client,
+            stack,
+            show_importers,
+            display: Display::new().output(output),
+        }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        let mut exports = Vec::new();
+        for export in self.client.list_exports().await? {
+            let exporting_stack = export
+                .exporting_stack_id()
+                .and_then(views::stack_name_from_id);
+            if self
+                .stack
+                .as_ref()
+                .is_some_and(|stack| exporting_stack.as_ref() != Some(stack))
+            {
+                continue;
+            }
+            let importers = if self.show_importers {
+                Some(
+                    self.client
+                        .list_imports(export.name().unwrap_or_default())
+                        .await?,
+                )
+            } else {
+                None
+            };
+            exports.push(ExportView::new(&export, importers));
+        }
+        exports.sort_by(|a, b| a.name.cmp(&b.name));
+        self.display.print_exports(&exports);
+        Ok(())
+    }
+}
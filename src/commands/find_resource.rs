@@ -0,0 +1,119 @@
+use aws_sdk_cloudformation::types::{StackResource, StackStatus};
+
+use tracing::{info, warn};
+
+use crate::{aws_client::AwsClient, display::Display, error::PklformationError};
+
+pub struct FindResourceCommand {
+    client: AwsClient,
+    physical_id: Option<String>,
+    resource_type: Option<String>,
+    name_contains: Option<String>,
+    allow_degraded: bool,
+    display: Display,
+}
+
+impl FindResourceCommand {
+    pub fn new(
+        client: AwsClient,
+        physical_id: Option<String>,
+        resource_type: Option<String>,
+        name_contains: Option<String>,
+        allow_degraded: bool,
+    ) -> Self {
+        Self {
+            client,
+            physical_id,
+            resource_type,
+            name_contains,
+            allow_degraded,
+            display: Display::new(),
+        }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        let resources = match self.physical_id.as_deref() {
+            Some(physical_id) => {
+                self.client
+                    .describe_stack_resources_by_physical_id(physical_id)
+                    .await?
+            }
+            None => self.search_all_stacks().await?,
+        };
+
+        let found: Vec<_> = resources
+            .into_iter()
+            .filter(|resource| self.matches(resource))
+            .collect();
+        if found.is_empty() {
+            info!("No matching resource found");
+        } else {
+            self.display.print_found_resources(&found);
+        }
+
+        Ok(())
+    }
+
+    async fn search_all_stacks(&self) -> anyhow::Result<Vec<StackResource>> {
+        let active_statuses: Vec<_> = StackStatus::values()
+            .iter()
+            .map(|status| StackStatus::from(*status))
+            .filter(|status| *status != StackStatus::DeleteComplete)
+            .collect();
+
+        let mut resources = Vec::new();
+        for stack in self.client.list_stacks(&active_statuses).await? {
+            let Some(stack_name) = stack.stack_name() else {
+                continue;
+            };
+            info!("Search resources of stack {stack_name}...");
+            let summaries = match self.client.list_all_stack_resources(stack_name).await {
+                Ok(summaries) => summaries,
+                Err(err)
+                    if self.allow_degraded && matches!(err, PklformationError::AccessDenied(_)) =>
+                {
+                    warn!("{err}, skipping stack {stack_name}");
+                    continue;
+                }
+                Err(err) => return Err(err.into()),
+            };
+            for summary in summaries {
+                resources.push(
+                    StackResource::builder()
+                        .stack_name(stack_name)
+                        .set_stack_id(stack.stack_id().map(str::to_owned))
+                        .set_logical_resource_id(summary.logical_resource_id)
+                        .set_physical_resource_id(summary.physical_resource_id)
+                        .set_resource_type(summary.resource_type)
+                        .set_resource_status(summary.resource_status)
+                        .set_timestamp(summary.last_updated_timestamp)
+                        .build(),
+                );
+            }
+        }
+
+        Ok(resources)
+    }
+
+    fn matches(&self, resource: &StackResource) -> bool {
+        let physical_id_matches = self
+            .physical_id
+            .as_deref()
+            .is_none_or(|physical_id| resource.physical_resource_id() == Some(physical_id));
+        let type_matches = self
+            .resource_type
+            .as_deref()
+            .is_none_or(|resource_type| resource.resource_type() == Some(resource_type));
+        let name_matches = self.name_contains.as_deref().is_none_or(|name| {
+            [
+                resource.logical_resource_id(),
+                resource.physical_resource_id(),
+            ]
+            .into_iter()
+            .flatten()
+            .any(|id| id.contains(name))
+        });
+
+        physical_id_matches && type_matches && name_matches
+    }
+}
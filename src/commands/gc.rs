@@ -0,0 +1,103 @@
+use anyhow::Context;
+use aws_sdk_cloudformation::types::{ChangeSetStatus, ChangeSetSummary, ExecutionStatus};
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+use crate::{aws_client::AwsClient, display::Display, error::PklformationError};
+
+pub fn parse_age(arg: &str) -> Result<Duration, String> {
+    let unit_start = arg
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("Missing unit in {arg:?}, use s, m, h or d"))?;
+    let (value, unit) = arg.split_at(unit_start);
+    let value: u64 = value.parse().map_err(|_| format!("Invalid age {arg:?}"))?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        _ => return Err(format!("Unknown unit {unit:?}, use s, m, h or d")),
+    };
+    value
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("Age {arg:?} is too large"))
+}
+
+pub struct GcCommand {
+    client: AwsClient,
+    stack: String,
+    older_than: Duration,
+    display: Display,
+}
+
+impl GcCommand {
+    pub fn new(client: AwsClient, stack: String, older_than: Duration) -> Self {
+        Self {
+            client,
+            stack,
+            older_than,
+            display: Display::new(),
+        }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .context("System clock before the Unix epoch")?
+            .as_secs();
+        let stale: Vec<_> = self
+            .client
+            .list_change_sets(&self.stack)
+            .await?
+            .into_iter()
+            .filter(|change_set| self.is_stale(change_set, now))
+            .collect();
+        if stale.is_empty() {
+            info!("No stale change set on stack {}", self.stack);
+            return Ok(());
+        }
+
+        let names: Vec<_> = stale
+            .iter()
+            .filter_map(|change_set| change_set.change_set_name().map(str::to_string))
+            .collect();
+        self.display.print_stack_names("Stale change sets:", &names);
+        if !self
+            .display
+            .ask_confirm("Do you want to delete these change sets?")
+        {
+            return Ok(());
+        }
+        for change_set_id in stale
+            .iter()
+            .filter_map(|change_set| change_set.change_set_id())
+        {
+            self.client.delete_change_set(change_set_id).await?;
+        }
+        info!(
+            "{} change set(s) deleted from stack {}",
+            stale.len(),
+            self.stack
+        );
+        Ok(())
+    }
+
+    // Failed change sets, like the ones left by previews without changes, are never useful
+    fn is_stale(&self, change_set: &ChangeSetSummary, now: u64) -> bool {
+        if change_set.status() == Some(&ChangeSetStatus::Failed) {
+            return true;
+        }
+        let in_progress = matches!(
+            change_set.status(),
+            Some(ChangeSetStatus::CreatePending | ChangeSetStatus::CreateInProgress)
+        ) || change_set.execution_status()
+            == Some(&ExecutionStatus::ExecuteInProgress);
+        let created = change_set
+            .creation_time()
+            .map(|time| time.secs().max(0) as u64)
+            .unwrap_or(now);
+        !in_progress && now.saturating_sub(created) > self.older_than.as_secs()
+    }
+}
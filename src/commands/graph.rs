@@ -0,0 +1,49 @@
+use anyhow::Context;
+use clap::ValueEnum;
+
+use std::path::PathBuf;
+use tracing::info;
+
+use crate::{error::PklformationError, project::Project, stack_graph::StackGraph};
+
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+pub enum GraphFormat {
+    #[default]
+    Dot,
+    Mermaid,
+}
+
+pub struct GraphCommand<'a> {
+    project: &'a Project,
+    format: GraphFormat,
+    out: Option<PathBuf>,
+}
+
+impl<'a> GraphCommand<'a> {
+    pub fn new(project: &'a Project, format: GraphFormat, out: Option<PathBuf>) -> Self {
+        Self {
+            project,
+            format,
+            out,
+        }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        let graph = StackGraph::from_project(self.project)?;
+        let graph = match self.format {
+            GraphFormat::Dot => graph.to_dot(),
+            GraphFormat::Mermaid => graph.to_mermaid(),
+        };
+
+        match self.out {
+            Some(out) => {
+                std::fs::write(&out, graph)
+                    .with_context(|| format!("Unable to write {}", out.display()))?;
+                info!("Stack graph written to {}", out.display());
+            }
+            None => print!("{graph}"),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+use anyhow::anyhow;
+
+use crate::{
+    aws_client::AwsClient,
+    diff,
+    display::{Display, OutputFormat},
+    error::PklformationError,
+    history::{self, HistoryEntry},
+    state_key,
+};
+
+pub struct HistoryCommand {
+    client: AwsClient,
+    stack: Option<String>,
+    operation: Option<String>,
+    limit: usize,
+    reveal: bool,
+    diff: bool,
+    display: Display,
+}
+
+impl HistoryCommand {
+    pub fn new(
+        client: AwsClient,
+        stack: Option<String>,
+        operation: Option<String>,
+        limit: usize,
+        reveal: bool,
+        diff: bool,
+        output: OutputFormat,
+    ) -> Self {
+        Self {
+            client,
+            stack,
+            operation,
+            limit,
+            reveal,
+            diff,
+            display: Display::new().output(output),
+        }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        // The values are only recorded encrypted, nothing can be shown or compared without the key
+        if (self.reveal || self.diff) && !state_key::configured() {
+            return Err(anyhow!(
+                "--reveal and --diff require the state key the values were encrypted with"
+            )
+            .into());
+        }
+        let mut entries: Vec<_> = history::load()?
+            .into_iter()
+            .rev()
+            .filter(|entry| {
+                self.stack
+                    .as_ref()
+                    .is_none_or(|stack| &entry.stack == stack)
+            })
+            .filter(|entry| {
+                self.operation
+                    .as_ref()
+                    .is_none_or(|operation| &entry.operation == operation)
+            })
+            .collect();
+        entries.truncate(self.limit);
+        if self.reveal || self.diff {
+            for entry in &mut entries {
+                history::reveal(&self.client, entry).await?;
+            }
+        }
+        if self.diff {
+            self.print_diffs(&entries);
+        } else {
+            self.display.print_history(&entries);
+        }
+        Ok(())
+    }
+
+    // Each run against the previous run of its stack, newest first like the history
+    fn print_diffs(&self, entries: &[HistoryEntry]) {
+        for (position, entry) in entries.iter().enumerate() {
+            let Some(previous) = entries[position + 1..]
+                .iter()
+                .find(|previous| previous.stack == entry.stack)
+            else {
+                continue;
+            };
+            let values = |entry: &HistoryEntry| {
+                (
+                    serde_json::to_value(&entry.parameters).unwrap_or_default(),
+                    serde_json::to_value(&entry.outputs).unwrap_or_default(),
+                )
+            };
+            let (old_parameters, old_outputs) = values(previous);
+            let (new_parameters, new_outputs) = values(entry);
+            self.display.print_stack_comparison(
+                &format!(
+                    "{} {} {}",
+                    previous.stack, previous.operation, previous.started_at
+                ),
+                &format!("{} {} {}", entry.stack, entry.operation, entry.started_at),
+                &[
+                    ("Parameters", diff::diff(&old_parameters, &new_parameters)),
+                    ("Outputs", diff::diff(&old_outputs, &new_outputs)),
+                ],
+            );
+        }
+    }
+}
@@ -1,31 +1,255 @@
-use aws_sdk_cloudformation::types::StackStatus;
+use anyhow::anyhow;
+use aws_sdk_cloudformation::primitives::DateTime;
+use aws_sdk_cloudformation::types::{Stack, StackStatus, StackSummary};
+use clap::{Args, ValueEnum};
+use futures_util::{stream, StreamExt, TryStreamExt};
+use regex::Regex;
+use std::collections::BTreeMap;
 
-use crate::{aws_client::AwsClient, display::Display};
+use crate::{
+    aws_client::AwsClient,
+    display::{Display, OutputFormat, StackTableLayout},
+    error::PklformationError,
+    pkl::parse_key_value,
+    project,
+};
+
+// DescribeStacks calls in flight for --detailed, low enough to stay under the API rate limit
+const DESCRIBE_CONCURRENCY: usize = 8;
+
+#[derive(Args, Clone, Default)]
+pub struct ListFilter {
+    /// Only the stacks whose name starts with this prefix
+    #[arg(long)]
+    pub name_prefix: Option<String>,
+    /// Only the stacks whose name matches this regular expression
+    #[arg(long)]
+    pub name_regex: Option<Regex>,
+    /// Only the stacks with this tag, can be repeated
+    #[arg(long = "tag", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    pub tags: Vec<(String, String)>,
+    /// Only the stacks missing tags required by the project file
+    #[arg(long)]
+    pub missing_tags: bool,
+    #[arg(skip)]
+    pub required_tags: Vec<String>,
+}
+
+impl ListFilter {
+    fn matches(
+        &self,
+        stack: &StackSummary,
+        tags: &BTreeMap<String, Vec<(String, String)>>,
+    ) -> bool {
+        let name = stack.stack_name().unwrap_or_default();
+        self.name_prefix
+            .as_deref()
+            .is_none_or(|prefix| name.starts_with(prefix))
+            && self
+                .name_regex
+                .as_ref()
+                .is_none_or(|regex| regex.is_match(name))
+            && (self.tags.is_empty()
+                || tags
+                    .get(stack.stack_id().unwrap_or_default())
+                    .is_some_and(|stack_tags| self.tags.iter().all(|tag| stack_tags.contains(tag))))
+            && (!self.missing_tags
+                || tags
+                    .get(stack.stack_id().unwrap_or_default())
+                    .is_some_and(|stack_tags| {
+                        let stack_tags = stack_tags.iter().cloned().collect();
+                        !project::missing_tags(&self.required_tags, &stack_tags).is_empty()
+                    }))
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum StackSort {
+    Name,
+    Status,
+    LastUpdated,
+    Created,
+}
+
+#[derive(Args, Clone, Default)]
+pub struct ListSort {
+    /// Order of the stacks, the order of the API by default
+    #[arg(long, value_enum)]
+    pub sort: Option<StackSort>,
+    /// Reverse the order, for instance the most recently updated first
+    #[arg(long, requires = "sort")]
+    pub desc: bool,
+}
+
+fn sort_key(time: Option<&DateTime>) -> (i64, u32) {
+    time.map(|time| (time.secs(), time.subsec_nanos()))
+        .unwrap_or_default()
+}
+
+impl ListSort {
+    fn apply(&self, stacks: &mut [StackSummary]) {
+        let Some(sort) = self.sort else {
+            return;
+        };
+        match sort {
+            StackSort::Name => stacks.sort_by(|a, b| a.stack_name().cmp(&b.stack_name())),
+            StackSort::Status => {
+                stacks.sort_by_key(|stack| stack.stack_status().map(|s| s.as_str().to_string()))
+            }
+            StackSort::LastUpdated => stacks
+                .sort_by_key(|stack| sort_key(stack.last_updated_time().or(stack.creation_time()))),
+            StackSort::Created => stacks.sort_by_key(|stack| sort_key(stack.creation_time())),
+        }
+        if self.desc {
+            stacks.reverse();
+        }
+    }
+}
 
 pub struct ListCommand {
     client: AwsClient,
     display: Display,
     status_filter: Option<Vec<StackStatus>>,
+    filter: ListFilter,
+    sort: ListSort,
+    limit: Option<usize>,
+    tree: bool,
+    detailed: bool,
+    output: OutputFormat,
 }
 
 impl ListCommand {
-    pub fn new(client: AwsClient, status_filter: Option<Vec<StackStatus>>) -> Self {
+    pub fn new(
+        client: AwsClient,
+        status_filter: Option<Vec<StackStatus>>,
+        filter: ListFilter,
+        sort: ListSort,
+        limit: Option<usize>,
+        stack_table_layout: StackTableLayout,
+        output: OutputFormat,
+    ) -> Self {
         Self {
             client,
-            display: Display::new(),
+            tree: stack_table_layout.tree,
+            detailed: stack_table_layout.detailed,
+            display: Display::new()
+                .stack_table_layout(stack_table_layout)
+                .output(output),
             status_filter,
+            filter,
+            sort,
+            limit,
+            output,
         }
     }
 
-    pub async fn run(self) -> anyhow::Result<()> {
-        let status_filter = self.status_filter.unwrap_or(vec![
+    pub async fn run(self) -> Result<(), PklformationError> {
+        let status_filter = self.status_filter.clone().unwrap_or(vec![
             StackStatus::CreateComplete,
             StackStatus::CreateInProgress,
             StackStatus::ImportComplete,
             StackStatus::ImportInProgress,
         ]);
-        let stacks = self.client.list_stacks(&status_filter).await?;
-        self.display.print_stack_summaries(&stacks);
+        if self.filter.missing_tags && self.filter.required_tags.is_empty() {
+            return Err(anyhow!("No required tags declared in the project file").into());
+        }
+        let tags = self.stack_tags().await?;
+
+        // Sorting and the tree need every stack, otherwise the text table is printed page by page
+        if self.sort.sort.is_some() || self.tree {
+            let mut stacks: Vec<_> = self
+                .client
+                .list_stacks(&status_filter)
+                .await?
+                .into_iter()
+                .filter(|stack| self.filter.matches(stack, &tags))
+                .collect();
+            self.sort.apply(&mut stacks);
+            if let Some(limit) = self.limit {
+                stacks.truncate(limit);
+            }
+            if !self.detailed || self.output == OutputFormat::Text {
+                self.display.print_stack_summaries(&stacks);
+            }
+            return Ok(self.print_details(&stacks).await?);
+        }
+
+        let mut stacks = Vec::new();
+        let mut listed = 0;
+        let mut widths = Vec::new();
+        self.client
+            .list_stacks_by_page(&status_filter, |page| {
+                let mut page: Vec<_> = page
+                    .into_iter()
+                    .filter(|stack| self.filter.matches(stack, &tags))
+                    .collect();
+                if let Some(limit) = self.limit {
+                    page.truncate(limit - listed);
+                }
+                listed += page.len();
+                if self.output == OutputFormat::Text && !page.is_empty() {
+                    self.display.print_stack_summaries_page(&page, &mut widths);
+                }
+                if self.output == OutputFormat::Json || self.detailed {
+                    stacks.extend(page);
+                }
+                self.limit.is_none_or(|limit| listed < limit)
+            })
+            .await?;
+        if self.output == OutputFormat::Json && !self.detailed {
+            self.display.print_stack_summaries(&stacks);
+        }
+        Ok(self.print_details(&stacks).await?)
+    }
+
+    async fn print_details(&self, stacks: &[StackSummary]) -> anyhow::Result<()> {
+        if !self.detailed {
+            return Ok(());
+        }
+        let stacks = self.describe_stacks(stacks).await?;
+        self.display.print_stack_details(&stacks);
         Ok(())
     }
+
+    // One DescribeStacks per stack, a few at a time, in the order of the summaries. The ids
+    // describe the deleted stacks too.
+    async fn describe_stacks(&self, stacks: &[StackSummary]) -> anyhow::Result<Vec<Stack>> {
+        stream::iter(stacks)
+            .map(|stack| {
+                self.client
+                    .describe_stack(stack.stack_id().or(stack.stack_name()).unwrap_or_default())
+            })
+            .buffered(DESCRIBE_CONCURRENCY)
+            .try_collect()
+            .await
+            .map_err(anyhow::Error::from)
+    }
+
+    // Stack summaries don't have the tags, they come from describing all the stacks at once
+    async fn stack_tags(&self) -> anyhow::Result<BTreeMap<String, Vec<(String, String)>>> {
+        if self.filter.tags.is_empty() && !self.filter.missing_tags {
+            return Ok(BTreeMap::new());
+        }
+        Ok(self
+            .client
+            .describe_all_stacks()
+            .await?
+            .iter()
+            .map(|stack| {
+                (
+                    stack.stack_id().unwrap_or_default().to_string(),
+                    stack
+                        .tags()
+                        .iter()
+                        .map(|tag| {
+                            (
+                                tag.key().unwrap_or_default().to_string(),
+                                tag.value().unwrap_or_default().to_string(),
+                            )
+                        })
+                        .collect(),
+                )
+            })
+            .collect())
+    }
 }
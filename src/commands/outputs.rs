@@ -0,0 +1,44 @@
+use std::path::PathBuf;
+
+use crate::{
+    aws_client::AwsClient,
+    display::OutputFormat,
+    error::PklformationError,
+    stack_outputs::{self, OutputsFormat},
+};
+
+pub struct OutputsCommand {
+    client: AwsClient,
+    stack: String,
+    format: Option<OutputsFormat>,
+    out: Option<PathBuf>,
+    output: OutputFormat,
+}
+
+impl OutputsCommand {
+    pub fn new(
+        client: AwsClient,
+        stack: String,
+        format: Option<OutputsFormat>,
+        out: Option<PathBuf>,
+        output: OutputFormat,
+    ) -> Self {
+        Self {
+            client,
+            stack,
+            format,
+            out,
+            output,
+        }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        let stack = self.client.describe_stack(&self.stack).await?;
+        let format = self
+            .format
+            .or_else(|| self.out.as_deref().map(OutputsFormat::from_extension))
+            .or((self.output == OutputFormat::Json).then_some(OutputsFormat::Json))
+            .unwrap_or_default();
+        Ok(stack_outputs::write(&stack, format, self.out.as_deref())?)
+    }
+}
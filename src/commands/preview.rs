@@ -1,37 +1,114 @@
 use anyhow::{anyhow, bail, Context};
 
-use aws_sdk_cloudformation::types::{ChangeSetType, StackStatus};
+use aws_sdk_cloudformation::{
+    operation::describe_change_set::DescribeChangeSetOutput,
+    types::{ChangeSetType, StackStatus},
+};
 
-use std::{path::PathBuf, process::Command, time::Duration};
-use tracing::{debug, info};
+use clap::Args;
+use std::{
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tracing::info;
 
-use crate::{aws_client::AwsClient, display::Display};
+use crate::{
+    aws_client::{AwsClient, ChangeSetNaming, ChangeSetOptions},
+    cloudformation_api::CloudFormationApi,
+    display::{ChangeSetFormat, ChangeSetLayout, Display, OutputFormat},
+    error::PklformationError,
+    history::{self, Run},
+    partition,
+    pkl::PklOptions,
+    template_format::{self, TemplateFormat},
+    validation::Validator,
+};
 
-pub struct PreviewCommand {
-    client: AwsClient,
+#[derive(Args, Clone, Default)]
+pub struct PreviewOptions {
+    #[command(flatten)]
+    pub change_set_layout: ChangeSetLayout,
+    #[command(flatten)]
+    pub pkl: PklOptions,
+    #[command(flatten)]
+    pub naming: ChangeSetNaming,
+    /// Format of the template, detected from its extension by default
+    #[arg(long, value_enum)]
+    pub template_format: Option<TemplateFormat>,
+    /// Format of the change set, for the terminal or for pull requests
+    #[arg(long, value_enum, default_value_t)]
+    pub format: ChangeSetFormat,
+    #[arg(skip)]
+    pub output: OutputFormat,
+}
+
+pub struct PreviewCommand<C = AwsClient> {
+    client: C,
     stack: String,
     template: PathBuf,
     pool_interval: Duration,
+    validator: Validator,
+    change_set_options: ChangeSetOptions,
+    pkl_options: PklOptions,
+    template_format: Option<TemplateFormat>,
     display: Display,
+    change_set_id: Mutex<Option<String>>,
 }
 
-impl PreviewCommand {
+impl<C: CloudFormationApi> PreviewCommand<C> {
     pub fn new(
-        client: AwsClient,
+        client: C,
         stack: String,
         template: PathBuf,
         pool_interval: Duration,
+        validator: Validator,
+        change_set_options: ChangeSetOptions,
+        options: PreviewOptions,
     ) -> Self {
         Self {
             client,
             stack,
             template,
             pool_interval,
-            display: Display::new(),
+            validator,
+            change_set_options: ChangeSetOptions {
+                naming: options.naming,
+                ..change_set_options
+            },
+            pkl_options: options.pkl,
+            template_format: options.template_format,
+            display: Display::with_change_set_layout(options.change_set_layout)
+                .change_set_format(options.format)
+                .output(options.output),
+            change_set_id: Mutex::new(None),
         }
     }
 
-    pub async fn run(self) -> anyhow::Result<()> {
+    pub async fn run(self) -> Result<(), PklformationError> {
+        let started = Instant::now();
+        let result = self.preview().await;
+        let change_set_id = self.change_set_id.lock().unwrap().clone();
+        history::record(
+            &self.client,
+            Run {
+                operation: "preview",
+                stack: &self.stack,
+                duration: started.elapsed(),
+                template: Some(&self.template),
+                parameters: &self.change_set_options.parameters,
+                change_set_id: change_set_id.as_deref(),
+                outcome: match &result {
+                    Ok(()) => "PREVIEWED".to_string(),
+                    Err(err) => format!("FAILED ({err})"),
+                },
+            },
+        )
+        .await;
+        Ok(result?)
+    }
+
+    async fn preview(&self) -> anyhow::Result<()> {
         let wait_result = self
             .client
             .wait_until_stack_op_in_progress(&self.stack, self.pool_interval)
@@ -55,7 +132,11 @@ impl PreviewCommand {
                     self.preview_exisint_change_set().await?;
                 }
                 _ => {
-                    tracing::error!("Preview failed with status: {last_status:?}, reason: {reason:?}. Check the AWS Console");
+                    let console_url = self.client.stack_console_url(&self.stack).await;
+                    tracing::error!(
+                        "Preview failed with status: {last_status:?}, reason: {reason:?}. Check the AWS Console: {}",
+                        console_url.as_deref().unwrap_or("-")
+                    );
                     return Ok(());
                 }
             }
@@ -64,45 +145,67 @@ impl PreviewCommand {
         Ok(())
     }
 
-    pub async fn eval_template(&self) -> anyhow::Result<String> {
-        let template_eval_result = Command::new("pkl")
-            .args([
-                "eval",
-                self.template.to_str().unwrap(),
-                "--project-dir",
-                self.template.parent().unwrap().to_str().unwrap(),
-                "--format",
-                "json",
-            ])
-            .output()?;
-
-        debug!("pkl eval result: {template_eval_result:?}");
-        if !template_eval_result.status.success() {
-            bail!(String::from_utf8(template_eval_result.stderr)?);
+    fn eval_template(&self) -> anyhow::Result<String> {
+        let template =
+            template_format::load(&self.template, self.template_format, &self.pkl_options)?;
+        let violations = self.validator.validate(&template)?;
+        if !violations.is_empty() {
+            self.display.print_violations(&violations);
+            bail!(PklformationError::Validation(format!(
+                "Template validation failed with {} violation(s)",
+                violations.len()
+            )));
         }
-
-        Ok(String::from_utf8(template_eval_result.stdout)?)
+        Ok(template)
     }
 
     async fn preview_new_change_set(&self, change_set_type: ChangeSetType) -> anyhow::Result<()> {
         info!("Preview stack {} ...", self.stack);
-        let template = self.eval_template().await?;
+        let template = self.eval_template()?;
+        partition::check_resource_types(&self.client, &template).await?;
+        // A stack being created has no change sets to list yet
+        if change_set_type != ChangeSetType::Create {
+            if let Some(change_set) = self
+                .client
+                .reusable_change_set(
+                    &self.stack,
+                    &template,
+                    &change_set_type,
+                    &self.change_set_options,
+                )
+                .await?
+            {
+                let change_set_id = change_set.change_set_id().context("Empty change set id")?;
+                info!("Reuse change set {change_set_id} created from the same template");
+                let change_set_description = self.client.describe_change_set(change_set_id).await?;
+                return self.check_change_set(&change_set_description);
+            }
+        }
+
         let change_set = self
             .client
-            .create_or_update_change_set(&self.stack, &template, change_set_type)
+            .create_or_update_change_set(
+                &self.stack,
+                &template,
+                change_set_type,
+                &self.change_set_options,
+            )
             .await?;
         let change_set_id = change_set.id().context("Empty change set id")?;
-        self.client
+        let (status, reason) = self
+            .client
             .wait_until_change_set_op_in_progress(change_set_id, self.pool_interval)
             .await?;
+        if AwsClient::is_empty_change_set(&status, &reason) {
+            info!("No changes on stack {}", self.stack);
+            return self.client.delete_change_set(change_set_id).await;
+        }
         let change_set_description = self.client.describe_change_set(change_set_id).await?;
-        self.display.print_change_set(&change_set_description);
-
-        Ok(())
+        self.check_change_set(&change_set_description)
     }
 
     async fn preview_exisint_change_set(&self) -> anyhow::Result<()> {
-        print!("Found a pending change set:");
+        info!("Found a pending change set");
         let pending_change_set = self
             .client
             .pending_change_set(&self.stack)
@@ -114,9 +217,74 @@ impl PreviewCommand {
             .as_deref()
             .context("Empty change set id")?;
         let pending_change_set_description = self.client.describe_change_set(change_set_id).await?;
-        self.display
-            .print_change_set(&pending_change_set_description);
+        self.check_change_set(&pending_change_set_description)
+    }
 
+    fn check_change_set(&self, change_set: &DescribeChangeSetOutput) -> anyhow::Result<()> {
+        *self.change_set_id.lock().unwrap() = change_set.change_set_id().map(str::to_string);
+        let violations = self.validator.validate_change_set(change_set);
+        self.display
+            .print_change_set_review(change_set, &violations);
+        let replacements = violations
+            .iter()
+            .filter(|violation| violation.is_replacement())
+            .count();
+        if replacements > 0 {
+            bail!(PklformationError::Validation(format!(
+                "The change set replaces or removes {replacements} resource(s) of a protected type"
+            )));
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cloudformation_api::mock::{isolate_user_data, MockCloudFormation},
+        validation::NamingRules,
+    };
+
+    const STACK: &str = "mock";
+
+    async fn preview(client: &MockCloudFormation) -> Result<(), PklformationError> {
+        isolate_user_data();
+        PreviewCommand::new(
+            client.clone(),
+            STACK.to_string(),
+            PathBuf::from(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/topic.json"
+            )),
+            Duration::ZERO,
+            Validator::new(&NamingRules::default()).unwrap(),
+            ChangeSetOptions::default(),
+            PreviewOptions::default(),
+        )
+        .run()
+        .await
+    }
+
+    #[tokio::test]
+    async fn new_stack_is_previewed_with_a_create_change_set() {
+        let client = MockCloudFormation::new();
+
+        preview(&client).await.unwrap();
+
+        let calls = client.calls();
+        assert!(calls.contains(&format!("CreateChangeSet(CREATE) {STACK}")));
+        assert!(!calls.iter().any(|call| call.starts_with("ListChangeSets")));
+    }
+
+    #[tokio::test]
+    async fn existing_stack_looks_for_a_reusable_change_set() {
+        let client = MockCloudFormation::new().with_stack_statuses([StackStatus::UpdateComplete]);
+
+        preview(&client).await.unwrap();
+
+        let calls = client.calls();
+        assert!(calls.contains(&format!("ListChangeSets {STACK}")));
+        assert!(calls.contains(&format!("CreateChangeSet(UPDATE) {STACK}")));
+    }
+}
@@ -0,0 +1,51 @@
+use tracing::info;
+
+use crate::{
+    audit_log::{self, AuditEvent},
+    aws_client::AwsClient,
+    error::PklformationError,
+};
+
+pub struct ProtectCommand {
+    client: AwsClient,
+    stack: String,
+    enabled: bool,
+}
+
+impl ProtectCommand {
+    pub fn new(client: AwsClient, stack: String, enabled: bool) -> Self {
+        Self {
+            client,
+            stack,
+            enabled,
+        }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        let result = self
+            .client
+            .update_termination_protection(&self.stack, self.enabled)
+            .await;
+        audit_log::record(
+            &self.client,
+            AuditEvent {
+                operation: if self.enabled { "protect" } else { "unprotect" },
+                stack: &self.stack,
+                change_set_arn: None,
+                confirmation: None,
+                status: match &result {
+                    Ok(()) => "SUCCEEDED".to_string(),
+                    Err(err) => format!("FAILED ({err})"),
+                },
+            },
+        )
+        .await;
+        result?;
+        info!(
+            "Termination protection of stack {} {}",
+            self.stack,
+            if self.enabled { "enabled" } else { "disabled" }
+        );
+        Ok(())
+    }
+}
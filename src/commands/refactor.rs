@@ -0,0 +1,228 @@
+use anyhow::{anyhow, Context};
+use aws_sdk_cloudformation::types::{
+    ResourceLocation, ResourceMapping, StackDefinition, StackRefactorExecutionStatus,
+    StackRefactorStatus,
+};
+use serde::Deserialize;
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::info;
+
+use crate::{
+    audit_log::{self, AuditEvent, Confirmation},
+    aws_client::AwsClient,
+    display::Display,
+    error::PklformationError,
+    pkl::PklOptions,
+    project::Project,
+    stack_outputs, template_format,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Location {
+    stack: String,
+    logical_id: String,
+}
+
+#[derive(Deserialize)]
+struct Move {
+    source: Location,
+    destination: Location,
+}
+
+// Written in pkl, JSON or YAML, with the project names of the stacks
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RefactorMapping {
+    description: Option<String>,
+    moves: Vec<Move>,
+}
+
+pub struct RefactorCommand<'a> {
+    project: &'a Project,
+    mapping: PathBuf,
+    pool_interval: Duration,
+    enable_stack_creation: bool,
+    yes: bool,
+    display: Display,
+}
+
+impl<'a> RefactorCommand<'a> {
+    pub fn new(
+        project: &'a Project,
+        mapping: PathBuf,
+        pool_interval: Duration,
+        enable_stack_creation: bool,
+        yes: bool,
+    ) -> Self {
+        Self {
+            project,
+            mapping,
+            pool_interval,
+            enable_stack_creation,
+            yes,
+            display: Display::new(),
+        }
+    }
+
+    fn load_mapping(path: &Path) -> anyhow::Result<RefactorMapping> {
+        let mapping = template_format::load(path, None, &PklOptions::default())?;
+        serde_json::from_str(&mapping)
+            .with_context(|| format!("Invalid refactor mapping {}", path.display()))
+    }
+
+    fn location(&self, location: &Location) -> ResourceLocation {
+        ResourceLocation::builder()
+            .stack_name(self.project.stack_name(&location.stack))
+            .logical_resource_id(&location.logical_id)
+            .build()
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        let mapping = Self::load_mapping(&self.mapping)?;
+        if mapping.moves.is_empty() {
+            return Err(anyhow!("No resource to move in {}", self.mapping.display()).into());
+        }
+        let stacks: BTreeSet<_> = mapping
+            .moves
+            .iter()
+            .flat_map(|m| [m.source.stack.as_str(), m.destination.stack.as_str()])
+            .collect();
+        let regions: BTreeSet<_> = stacks
+            .iter()
+            .map(|stack| self.project.region(stack))
+            .collect();
+        if regions.len() > 1 {
+            return Err(
+                anyhow!("Resources can only move between stacks of the same region").into(),
+            );
+        }
+
+        // The local templates are the state of the stacks after the refactor
+        let mut stack_definitions = Vec::new();
+        for stack in &stacks {
+            let pkl_options =
+                stack_outputs::pkl_options(self.project, stack, &PklOptions::default()).await?;
+            let template =
+                template_format::load(&self.project.template(stack, None)?, None, &pkl_options)?;
+            stack_definitions.push(
+                StackDefinition::builder()
+                    .stack_name(self.project.stack_name(stack))
+                    .template_body(template)
+                    .build(),
+            );
+        }
+        let resource_mappings = mapping
+            .moves
+            .iter()
+            .map(|m| {
+                ResourceMapping::builder()
+                    .source(self.location(&m.source))
+                    .destination(self.location(&m.destination))
+                    .build()
+            })
+            .collect();
+
+        let first_stack = stacks.first().context("No stack in the refactor")?;
+        let client = AwsClient::new(self.project.region(first_stack))
+            .await
+            .with_notifications(self.project.notifications.clone());
+        let stack_names = stacks
+            .iter()
+            .map(|stack| self.project.stack_name(stack))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        info!("Create stack refactor of {stack_names} ...");
+        let stack_refactor_id = client
+            .create_stack_refactor(
+                mapping.description.as_deref(),
+                self.enable_stack_creation,
+                resource_mappings,
+                stack_definitions,
+            )
+            .await?;
+        let stack_refactor = client
+            .wait_for_stack_refactor(&stack_refactor_id, self.pool_interval, |refactor| {
+                refactor.status() == Some(&StackRefactorStatus::CreateInProgress)
+            })
+            .await?;
+        if stack_refactor.status() != Some(&StackRefactorStatus::CreateComplete) {
+            return Err(anyhow!(
+                "Stack refactor {stack_refactor_id} failed: {}",
+                stack_refactor.status_reason().unwrap_or_default()
+            )
+            .into());
+        }
+
+        let actions = client
+            .list_stack_refactor_actions(&stack_refactor_id)
+            .await?;
+        self.display.print_stack_refactor_actions(&actions);
+
+        let confirmation = if self.yes {
+            Confirmation::Skipped
+        } else {
+            Confirmation::from_answer(
+                self.display
+                    .ask_confirm("Do you want to execute this refactor?"),
+            )
+        };
+        if matches!(confirmation, Confirmation::Declined) {
+            audit_log::record(
+                &client,
+                AuditEvent {
+                    operation: "refactor",
+                    stack: &stack_names,
+                    change_set_arn: Some(&stack_refactor_id),
+                    confirmation: Some(confirmation),
+                    status: "DECLINED".to_string(),
+                },
+            )
+            .await;
+            return Ok(());
+        }
+
+        client.execute_stack_refactor(&stack_refactor_id).await?;
+        let stack_refactor = client
+            .wait_for_stack_refactor(&stack_refactor_id, self.pool_interval, |refactor| {
+                matches!(
+                    refactor.execution_status(),
+                    Some(
+                        StackRefactorExecutionStatus::ExecuteInProgress
+                            | StackRefactorExecutionStatus::RollbackInProgress
+                    )
+                )
+            })
+            .await?;
+        let status = stack_refactor
+            .execution_status()
+            .map(|status| status.as_str().to_string())
+            .unwrap_or_default();
+        audit_log::record(
+            &client,
+            AuditEvent {
+                operation: "refactor",
+                stack: &stack_names,
+                change_set_arn: Some(&stack_refactor_id),
+                confirmation: Some(confirmation),
+                status: status.clone(),
+            },
+        )
+        .await;
+        if stack_refactor.execution_status() != Some(&StackRefactorExecutionStatus::ExecuteComplete)
+        {
+            return Err(anyhow!(
+                "Stack refactor {stack_refactor_id} ended with {status}: {}",
+                stack_refactor.execution_status_reason().unwrap_or_default()
+            )
+            .into());
+        }
+        info!("Stack refactor {stack_refactor_id} executed successfully!");
+        Ok(())
+    }
+}
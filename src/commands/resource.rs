@@ -0,0 +1,74 @@
+use anyhow::Context;
+use aws_sdk_cloudformation::types::TemplateStage;
+use serde_json::Value;
+
+use crate::{
+    aws_client::AwsClient,
+    display::{Display, OutputFormat},
+    error::PklformationError,
+    views::StackResourceDetailView,
+};
+
+pub struct ResourceCommand {
+    client: AwsClient,
+    stack: String,
+    logical_id: String,
+    properties: bool,
+    display: Display,
+}
+
+impl ResourceCommand {
+    pub fn new(
+        client: AwsClient,
+        stack: String,
+        logical_id: String,
+        properties: bool,
+        output: OutputFormat,
+    ) -> Self {
+        Self {
+            client,
+            stack,
+            logical_id,
+            properties,
+            display: Display::new().output(output),
+        }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        let resource = self
+            .client
+            .describe_stack_resource(&self.stack, &self.logical_id)
+            .await?;
+        let properties = if self.properties {
+            Some(self.template_properties().await?)
+        } else {
+            None
+        };
+        self.display
+            .print_stack_resource_detail(&StackResourceDetailView::new(&resource, properties));
+        Ok(())
+    }
+
+    // The properties as written in the deployed template, intrinsic functions unresolved
+    async fn template_properties(&self) -> anyhow::Result<Value> {
+        let template: Value = serde_json::from_str(
+            &self
+                .client
+                .get_template(&self.stack, TemplateStage::Original)
+                .await?,
+        )
+        .context("Deployed template is not JSON")?;
+        Ok(template
+            .get("Resources")
+            .and_then(|resources| resources.get(&self.logical_id))
+            .with_context(|| {
+                format!(
+                    "Resource {} not found in the deployed template",
+                    self.logical_id
+                )
+            })?
+            .get("Properties")
+            .cloned()
+            .unwrap_or(Value::Object(Default::default())))
+    }
+}
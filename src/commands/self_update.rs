@@ -0,0 +1,88 @@
+use anyhow::{anyhow, Context};
+
+use tracing::info;
+
+use crate::{display::Display, error::PklformationError, update};
+
+#[derive(Default)]
+pub struct SelfUpdateCommand {
+    display: Display,
+}
+
+impl SelfUpdateCommand {
+    pub fn new() -> Self {
+        Self {
+            display: Display::new(),
+        }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        let release = update::latest_release().await?;
+        if !release.is_newer() {
+            info!("pklformation {} is up to date", update::CURRENT_VERSION);
+            return Ok(());
+        }
+
+        let asset_name = update::platform_asset_name();
+        let asset = release
+            .asset(&asset_name)
+            .with_context(|| format!("Release {} has no {asset_name} binary", release.tag_name))?;
+        let checksum_asset = release
+            .asset(&format!("{asset_name}.sha256"))
+            .with_context(|| {
+                format!("Release {} has no {asset_name} checksum", release.tag_name)
+            })?;
+        let signature_asset = release
+            .asset(&format!("{asset_name}.sig"))
+            .with_context(|| {
+                format!("Release {} has no {asset_name} signature", release.tag_name)
+            })?;
+
+        if !self.display.ask_confirm(&format!(
+            "Update pklformation {} to {}?",
+            update::CURRENT_VERSION,
+            release.version()
+        )) {
+            return Ok(());
+        }
+
+        info!("Download {}...", asset.browser_download_url);
+        let binary = update::download(&asset.browser_download_url).await?;
+        let checksum =
+            String::from_utf8(update::download(&checksum_asset.browser_download_url).await?)
+                .context("Invalid checksum file")?;
+        let expected_checksum = checksum
+            .split_whitespace()
+            .next()
+            .context("Empty checksum file")?;
+        let actual_checksum = update::sha256_hex(&binary);
+        if !expected_checksum.eq_ignore_ascii_case(&actual_checksum) {
+            return Err(anyhow!("Checksum mismatch for {asset_name}: expected {expected_checksum}, got {actual_checksum}").into());
+        }
+        let signature =
+            String::from_utf8(update::download(&signature_asset.browser_download_url).await?)
+                .context("Invalid signature file")?;
+        update::verify_signature(&binary, &signature)
+            .with_context(|| format!("Unable to verify {asset_name}"))?;
+
+        let download_path =
+            std::env::temp_dir().join(format!("{asset_name}-{}", release.version()));
+        std::fs::write(&download_path, binary)
+            .with_context(|| format!("Unable to write {}", download_path.display()))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&download_path, std::fs::Permissions::from_mode(0o755))
+                .with_context(|| {
+                    format!("Unable to make {} executable", download_path.display())
+                })?;
+        }
+        let replace_result = self_replace::self_replace(&download_path);
+        std::fs::remove_file(&download_path)
+            .with_context(|| format!("Unable to remove {}", download_path.display()))?;
+        replace_result.context("Unable to replace the running binary")?;
+
+        info!("pklformation updated to {}!", release.version());
+        Ok(())
+    }
+}
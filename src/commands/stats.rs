@@ -0,0 +1,35 @@
+use anyhow::Context;
+use std::path::PathBuf;
+
+use crate::{
+    display::{Display, OutputFormat},
+    error::PklformationError,
+    pkl::PklOptions,
+    template_format,
+    views::TemplateStatsView,
+};
+
+pub struct StatsCommand {
+    template: PathBuf,
+    pkl_options: PklOptions,
+    display: Display,
+}
+
+impl StatsCommand {
+    pub fn new(template: PathBuf, pkl_options: PklOptions, output: OutputFormat) -> Self {
+        Self {
+            template,
+            pkl_options,
+            display: Display::new().output(output),
+        }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        let template = template_format::load(&self.template, None, &self.pkl_options)?;
+        let value: serde_json::Value = serde_json::from_str(&template)
+            .with_context(|| format!("Invalid template {}", self.template.display()))?;
+        self.display
+            .print_template_stats(&TemplateStatsView::new(&value, template.len()));
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+use anyhow::Context;
+use aws_sdk_cloudformation::types::TemplateStage;
+
+use std::path::PathBuf;
+use tracing::info;
+
+use crate::{aws_client::AwsClient, error::PklformationError};
+
+pub struct TemplateCommand {
+    client: AwsClient,
+    stack: String,
+    processed: bool,
+    out: Option<PathBuf>,
+}
+
+impl TemplateCommand {
+    pub fn new(client: AwsClient, stack: String, processed: bool, out: Option<PathBuf>) -> Self {
+        Self {
+            client,
+            stack,
+            processed,
+            out,
+        }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        let template_stage = if self.processed {
+            TemplateStage::Processed
+        } else {
+            TemplateStage::Original
+        };
+        let template = self
+            .client
+            .get_template(&self.stack, template_stage)
+            .await?;
+
+        match self.out {
+            Some(out) => {
+                std::fs::write(&out, template)
+                    .with_context(|| format!("Unable to write {}", out.display()))?;
+                info!(
+                    "Template of stack {} written to {}",
+                    self.stack,
+                    out.display()
+                );
+            }
+            None => println!("{template}"),
+        }
+
+        Ok(())
+    }
+}
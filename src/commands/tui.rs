@@ -0,0 +1,386 @@
+use anyhow::{anyhow, bail};
+use aws_sdk_cloudformation::types::{StackEvent, StackResourceSummary, StackStatus};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, Paragraph, Row, Table, Wrap},
+    DefaultTerminal, Frame,
+};
+use std::{
+    io::BufRead,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    aws_client::AwsClient,
+    commands::{
+        destroy::{DestroyCommand, DestroyOptions},
+        preview::{PreviewCommand, PreviewOptions},
+    },
+    display::TextColor,
+    error::PklformationError,
+    pkl::PklOptions,
+    project::Project,
+    stack_outputs,
+    validation::Validator,
+    views::StackView,
+};
+
+const KEY_POLL_INTERVAL: Duration = Duration::from_millis(200);
+const HELP: &str = "↑/↓ select  d describe  p preview  x destroy  r refresh  q quit";
+
+struct StackRow {
+    stack: String,
+    name: String,
+    client: AwsClient,
+    status: Option<StackStatus>,
+}
+
+struct Dashboard {
+    rows: Vec<StackRow>,
+    selected: ListState,
+    events: Vec<StackEvent>,
+    resources: Vec<StackResourceSummary>,
+    details: Option<StackView>,
+    message: String,
+}
+
+enum Action {
+    Preview,
+    Destroy,
+}
+
+pub struct TuiCommand<'a> {
+    project: &'a Project,
+    pool_interval: Duration,
+    validator: Validator,
+}
+
+impl<'a> TuiCommand<'a> {
+    pub fn new(project: &'a Project, pool_interval: Duration, validator: Validator) -> Self {
+        Self {
+            project,
+            pool_interval,
+            validator,
+        }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        if self.project.stacks.is_empty() {
+            return Err(anyhow!("No stacks declared in the project file").into());
+        }
+        let mut rows = Vec::new();
+        for stack in self.project.stacks.keys() {
+            rows.push(StackRow {
+                stack: stack.clone(),
+                name: self.project.stack_name(stack),
+                client: self.client(stack).await,
+                status: None,
+            });
+        }
+        let mut dashboard = Dashboard {
+            rows,
+            selected: ListState::default().with_selected(Some(0)),
+            events: Vec::new(),
+            resources: Vec::new(),
+            details: None,
+            message: HELP.to_string(),
+        };
+
+        let mut terminal = ratatui::init();
+        let result = self.event_loop(&mut terminal, &mut dashboard).await;
+        ratatui::restore();
+        Ok(result?)
+    }
+
+    async fn client(&self, stack: &str) -> AwsClient {
+        AwsClient::new(self.project.region(stack))
+            .await
+            .with_notifications(self.project.notifications.clone())
+    }
+
+    async fn event_loop(
+        &self,
+        terminal: &mut DefaultTerminal,
+        dashboard: &mut Dashboard,
+    ) -> anyhow::Result<()> {
+        let mut last_refresh: Option<Instant> = None;
+        loop {
+            if last_refresh.is_none_or(|last| last.elapsed() >= self.pool_interval) {
+                dashboard.refresh().await;
+                last_refresh = Some(Instant::now());
+            }
+            terminal.draw(|frame| dashboard.draw(frame))?;
+
+            if !event::poll(KEY_POLL_INTERVAL)? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') => return Ok(()),
+                KeyCode::Esc if dashboard.details.is_some() => dashboard.details = None,
+                KeyCode::Esc => return Ok(()),
+                KeyCode::Up | KeyCode::Char('k') => {
+                    dashboard.selected.select_previous();
+                    dashboard.details = None;
+                    last_refresh = None;
+                }
+                KeyCode::Down | KeyCode::Char('j') => {
+                    let last = dashboard.rows.len() - 1;
+                    let next = dashboard
+                        .selected
+                        .selected()
+                        .map_or(0, |i| (i + 1).min(last));
+                    dashboard.selected.select(Some(next));
+                    dashboard.details = None;
+                    last_refresh = None;
+                }
+                KeyCode::Char('r') => last_refresh = None,
+                KeyCode::Char('d') => dashboard.describe().await,
+                KeyCode::Char('p') => {
+                    self.suspend(terminal, dashboard, Action::Preview).await?;
+                    last_refresh = None;
+                }
+                KeyCode::Char('x') => {
+                    self.suspend(terminal, dashboard, Action::Destroy).await?;
+                    last_refresh = None;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Preview and destroy run as the regular commands, on the restored terminal, for their prompts
+    async fn suspend(
+        &self,
+        terminal: &mut DefaultTerminal,
+        dashboard: &mut Dashboard,
+        action: Action,
+    ) -> anyhow::Result<()> {
+        let Some(row) = dashboard.selected_row() else {
+            return Ok(());
+        };
+        let stack = row.stack.clone();
+        ratatui::restore();
+        let result = match action {
+            Action::Preview => self.preview(&stack).await,
+            Action::Destroy => self.destroy(&stack).await,
+        };
+        if let Err(err) = &result {
+            eprintln!("{err:#}");
+        }
+        println!("Press Enter to return to the dashboard");
+        let mut line = String::new();
+        std::io::stdin().lock().read_line(&mut line)?;
+        *terminal = ratatui::init();
+        dashboard.message = match result {
+            Ok(()) => HELP.to_string(),
+            Err(err) => format!("{err}"),
+        };
+        Ok(())
+    }
+
+    async fn preview(&self, stack: &str) -> anyhow::Result<()> {
+        Ok(PreviewCommand::new(
+            self.client(stack).await,
+            self.project.stack_name(stack),
+            self.project.template(stack, None)?,
+            self.pool_interval,
+            self.validator
+                .clone()
+                .with_immutable_resources(self.project.immutable_resources(stack)),
+            self.project.change_set_options(stack),
+            PreviewOptions {
+                pkl: stack_outputs::pkl_options(self.project, stack, &PklOptions::default())
+                    .await?,
+                ..Default::default()
+            },
+        )
+        .run()
+        .await?)
+    }
+
+    async fn destroy(&self, stack: &str) -> anyhow::Result<()> {
+        if self.project.is_protected(stack) {
+            bail!("Stack {stack} is protected in the project file, destroy it with `pklformation destroy --allow-protected`");
+        }
+        Ok(DestroyCommand::new(
+            self.client(stack).await,
+            self.project.stack_name(stack),
+            self.pool_interval,
+            DestroyOptions::default(),
+        )
+        .run()
+        .await?)
+    }
+}
+
+impl Dashboard {
+    fn selected_row(&self) -> Option<&StackRow> {
+        self.selected.selected().and_then(|i| self.rows.get(i))
+    }
+
+    // Errors are shown in the status line, the dashboard keeps running
+    async fn refresh(&mut self) {
+        for row in self.rows.iter_mut() {
+            row.status = row
+                .client
+                .stack_status(&row.name)
+                .await
+                .ok()
+                .map(|(status, _)| status);
+        }
+        let Some(row) = self.selected_row() else {
+            return;
+        };
+        if row.status.is_none() {
+            self.events.clear();
+            self.resources.clear();
+            return;
+        }
+        let (events, resources) = (
+            row.client.recent_stack_events(&row.name).await,
+            row.client.list_all_stack_resources(&row.name).await,
+        );
+        match (events, resources) {
+            (Ok(events), Ok(resources)) => {
+                self.events = events;
+                self.resources = resources;
+            }
+            (Err(err), _) | (_, Err(err)) => self.message = format!("{err}"),
+        }
+    }
+
+    async fn describe(&mut self) {
+        let Some(row) = self.selected_row() else {
+            return;
+        };
+        match row.client.describe_stack(&row.name).await {
+            Ok(stack) => self.details = Some(StackView::from(&stack)),
+            Err(err) => self.message = format!("{err}"),
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status_line] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [stacks, right] =
+            Layout::horizontal([Constraint::Percentage(30), Constraint::Percentage(70)])
+                .areas(main);
+        let [top, bottom] =
+            Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(right);
+
+        let items: Vec<_> = self
+            .rows
+            .iter()
+            .map(|row| {
+                let status = match &row.status {
+                    Some(status) => Span::styled(
+                        status.as_str(),
+                        TextColor::from_stack_status(status).tui_color(),
+                    ),
+                    None => Span::raw("NOT DEPLOYED").dim(),
+                };
+                ListItem::new(vec![Line::raw(row.name.as_str()), Line::from(status)])
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::bordered().title(" Stacks "))
+            .highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(list, stacks, &mut self.selected);
+
+        match &self.details {
+            Some(details) => frame.render_widget(Self::details(details), right),
+            None => {
+                frame.render_widget(self.events_pane(), top);
+                frame.render_widget(self.resources_pane(), bottom);
+            }
+        }
+        frame.render_widget(Line::raw(self.message.as_str()).dim(), status_line);
+    }
+
+    fn events_pane(&self) -> Paragraph<'_> {
+        let lines: Vec<_> = self
+            .events
+            .iter()
+            .map(|event| {
+                let status = event.resource_status();
+                Line::from(vec![
+                    Span::raw(event.timestamp().map(|t| t.to_string()).unwrap_or_default()).dim(),
+                    Span::raw(" "),
+                    Span::raw(event.logical_resource_id().unwrap_or_default()),
+                    Span::raw(" "),
+                    Span::styled(
+                        status.map(|s| s.as_str()).unwrap_or_default(),
+                        status
+                            .map(|s| TextColor::from_resource_status(s).tui_color())
+                            .unwrap_or_default(),
+                    ),
+                    Span::raw(" "),
+                    Span::raw(event.resource_status_reason().unwrap_or_default()).dim(),
+                ])
+            })
+            .collect();
+        Paragraph::new(lines).block(Block::bordered().title(" Events "))
+    }
+
+    fn resources_pane(&self) -> Table<'_> {
+        let rows = self.resources.iter().map(|resource| {
+            let status = resource.resource_status();
+            Row::new(vec![
+                Span::raw(resource.logical_resource_id().unwrap_or_default()),
+                Span::raw(resource.resource_type().unwrap_or_default()),
+                Span::styled(
+                    status.map(|s| s.as_str()).unwrap_or_default(),
+                    status
+                        .map(|s| TextColor::from_resource_status(s).tui_color())
+                        .unwrap_or_default(),
+                ),
+            ])
+        });
+        Table::new(
+            rows,
+            [
+                Constraint::Percentage(35),
+                Constraint::Percentage(40),
+                Constraint::Percentage(25),
+            ],
+        )
+        .header(Row::new(["Logical ID", "Type", "Status"]).bold())
+        .block(Block::bordered().title(" Resources "))
+    }
+
+    fn details(stack: &StackView) -> Paragraph<'_> {
+        let mut lines = vec![Line::raw(format!("Stack name: {}", stack.name))];
+        let fields = [
+            ("Id", stack.id.as_deref()),
+            ("Console", stack.console_url.as_deref()),
+            ("Description", stack.description.as_deref()),
+            ("Creation time", stack.creation_time.as_deref()),
+            ("Last update time", stack.last_updated_time.as_deref()),
+            ("Status", stack.status.as_ref().map(|s| s.as_str())),
+            ("Status reason", stack.status_reason.as_deref()),
+        ];
+        for (label, value) in fields {
+            if let Some(value) = value {
+                lines.push(Line::raw(format!("{label}: {value}")));
+            }
+        }
+        if !stack.parameters.is_empty() {
+            lines.push(Line::raw("Parameters:"));
+            for (key, value) in &stack.parameters {
+                lines.push(Line::raw(format!("  {key}: {value}")));
+            }
+        }
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::bordered().title(" Describe (Esc to close) "))
+    }
+}
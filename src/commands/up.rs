@@ -1,46 +1,254 @@
 use anyhow::{bail, Context};
 
-use aws_sdk_cloudformation::types::{ChangeSetStatus, ChangeSetType, StackStatus};
+use aws_sdk_cloudformation::{
+    operation::describe_change_set::DescribeChangeSetOutput,
+    types::{ChangeSetStatus, ChangeSetType, OnStackFailure, ResourceStatus, StackStatus},
+};
 
+use clap::{Args, ValueEnum};
 use std::{
     path::PathBuf,
-    process::Command,
-    time::{Duration, SystemTime, UNIX_EPOCH},
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tracing::{info, warn};
+
+use crate::{
+    approval::ApprovalConfig,
+    audit_log::{self, AuditEvent, Confirmation},
+    aws_client::{AwsClient, ChangeSetNaming, ChangeSetOptions},
+    browser,
+    cloudformation_api::CloudFormationApi,
+    display::{ChangeSetLayout, Display},
+    durations::DurationHistory,
+    error::PklformationError,
+    history::{self, Run},
+    interrupt, nested_stacks,
+    notifications::{self, Completion},
+    partition,
+    pkl::PklOptions,
+    project, quotas,
+    stack_outputs::{self, OutputsFormat},
+    template_format::{self, TemplateFormat},
+    timings,
+    validation::Validator,
 };
-use tracing::{debug, info};
 
-use crate::{aws_client::AwsClient, display::Display};
+const SLOWEST_RESOURCES: usize = 10;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum OnFailure {
+    #[value(name = "ROLLBACK")]
+    Rollback,
+    #[value(name = "DELETE")]
+    Delete,
+    #[value(name = "DO_NOTHING")]
+    DoNothing,
+}
+
+impl From<OnFailure> for OnStackFailure {
+    fn from(on_failure: OnFailure) -> Self {
+        match on_failure {
+            OnFailure::Rollback => OnStackFailure::Rollback,
+            OnFailure::Delete => OnStackFailure::Delete,
+            OnFailure::DoNothing => OnStackFailure::DoNothing,
+        }
+    }
+}
 
-pub struct UpCommand {
-    client: AwsClient,
+#[derive(Args, Clone, Default)]
+pub struct UpOptions {
+    #[command(flatten)]
+    pub change_set_layout: ChangeSetLayout,
+    #[command(flatten)]
+    pub pkl: PklOptions,
+    #[command(flatten)]
+    pub naming: ChangeSetNaming,
+    /// Format of the template, detected from its extension by default
+    #[arg(long, value_enum)]
+    pub template_format: Option<TemplateFormat>,
+    /// Execute change sets even if they modify or remove resources marked as immutable
+    #[arg(long)]
+    pub allow_immutable_changes: bool,
+    /// Execute change sets even if they replace or remove resources of a protected type
+    #[arg(long)]
+    pub allow_replacement: bool,
+    /// CloudWatch alarm that rolls the stack back when it goes to ALARM, can be repeated
+    #[arg(long = "rollback-alarm")]
+    pub rollback_alarms: Vec<String>,
+    /// Minutes the rollback alarms are monitored after the resources are deployed
+    #[arg(long, value_parser = clap::value_parser!(i32).range(0..=180))]
+    pub monitoring_minutes: Option<i32>,
+    /// What CloudFormation does when the creation of the stack fails
+    #[arg(long, value_enum)]
+    pub on_failure: Option<OnFailure>,
+    /// Keep the resources already updated when an update fails
+    #[arg(long)]
+    pub disable_rollback: bool,
+    /// Cancel the operation when the stack is not created or updated within this many minutes
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub timeout_in_minutes: Option<u64>,
+    /// SNS topic that receives the stack events, can be repeated
+    #[arg(long = "notification-arn")]
+    pub notification_arns: Vec<String>,
+    /// Execute change sets without resource changes, like tag updates, without asking
+    #[arg(long)]
+    pub skip_empty_confirm: bool,
+    /// Skip the typed confirmation before deleting a stack whose creation failed
+    #[arg(long)]
+    pub yes: bool,
+    /// Write the stack outputs to this file after a successful deployment, formatted after its extension
+    #[arg(long)]
+    pub outputs_file: Option<PathBuf>,
+    /// Publish each stack output as an SSM parameter under this path after a successful deployment
+    #[arg(long, value_name = "ssm:PATH", value_parser = stack_outputs::parse_ssm_prefix)]
+    pub publish_outputs: Option<String>,
+    /// Open the console page of the stack in the browser when up fails
+    #[arg(long)]
+    pub open_on_failure: bool,
+    /// Only warn when the stack misses tags required by the project file
+    #[arg(long)]
+    pub no_enforce_tags: bool,
+    #[arg(skip)]
+    pub protected: bool,
+    #[arg(skip)]
+    pub required_tags: Vec<String>,
+    #[arg(skip)]
+    pub approval: Option<ApprovalConfig>,
+}
+
+pub struct UpCommand<C = AwsClient> {
+    client: C,
     stack: String,
     template: PathBuf,
     pool_interval: Duration,
+    validator: Validator,
+    change_set_options: ChangeSetOptions,
+    options: UpOptions,
     display: Display,
+    executed_change_set: Mutex<Option<String>>,
+    confirmation: Mutex<Option<(String, Confirmation)>>,
 }
 
-impl UpCommand {
+impl<C: CloudFormationApi> UpCommand<C> {
     pub fn new(
-        client: AwsClient,
+        client: C,
         stack: String,
         template: PathBuf,
         pool_interval: Duration,
+        validator: Validator,
+        change_set_options: ChangeSetOptions,
+        options: UpOptions,
     ) -> Self {
         Self {
             client,
             stack,
             template,
             pool_interval,
-            display: Display::new(),
+            validator,
+            change_set_options: ChangeSetOptions {
+                rollback_alarms: options.rollback_alarms.clone(),
+                monitoring_minutes: options.monitoring_minutes,
+                on_stack_failure: options.on_failure.map(Into::into),
+                notification_arns: options.notification_arns.clone(),
+                naming: options.naming.clone(),
+                ..change_set_options
+            },
+            display: Display::with_change_set_layout(options.change_set_layout),
+            options,
+            executed_change_set: Mutex::new(None),
+            confirmation: Mutex::new(None),
+        }
+    }
+
+    pub async fn run(self) -> Result<StackStatus, PklformationError> {
+        let started = Instant::now();
+        let result = self.up().await;
+        let stack = self.client.describe_stack(&self.stack).await.ok();
+        let completion = Completion {
+            operation: "up",
+            stack: &self.stack,
+            stack_id: stack.as_ref().and_then(|stack| stack.stack_id()),
+            duration: started.elapsed(),
+            result: &result,
+        };
+        let executed_change_set = self.executed_change_set.lock().unwrap().clone();
+        history::record(
+            &self.client,
+            Run {
+                operation: "up",
+                stack: &self.stack,
+                duration: completion.duration,
+                template: Some(&self.template),
+                parameters: &self.change_set_options.parameters,
+                change_set_id: executed_change_set.as_deref(),
+                outcome: completion.status(),
+            },
+        )
+        .await;
+        let confirmation = self.confirmation.lock().unwrap().clone();
+        audit_log::record(
+            &self.client,
+            AuditEvent {
+                operation: "up",
+                stack: &self.stack,
+                change_set_arn: confirmation.as_ref().map(|(arn, _)| arn.as_str()),
+                confirmation: confirmation.as_ref().map(|(_, confirmation)| *confirmation),
+                status: completion.status(),
+            },
+        )
+        .await;
+        if self.options.open_on_failure && completion.failed() {
+            match completion.console_url() {
+                Some(console_url) => browser::open(&console_url),
+                None => warn!("Stack {} has no console page to open", self.stack),
+            }
+        }
+        let failed = completion.failed();
+        notifications::notify(&self.client, completion).await;
+        let status = result?;
+        // Aborted and detached runs are not failures, rolled back and failed operations are
+        if failed {
+            return Err(PklformationError::StackFailed {
+                stack: self.stack.clone(),
+                status,
+                reason: stack
+                    .as_ref()
+                    .and_then(|stack| stack.stack_status_reason())
+                    .map(str::to_string),
+            });
+        }
+        Ok(status)
+    }
+
+    fn check_required_tags(&self) -> anyhow::Result<()> {
+        let missing =
+            project::missing_tags(&self.options.required_tags, &self.change_set_options.tags);
+        if missing.is_empty() {
+            return Ok(());
         }
+        let message = format!(
+            "Stack {} misses the tag(s) required by the project: {}",
+            self.stack,
+            missing.join(", ")
+        );
+        if self.options.no_enforce_tags {
+            warn!("{message}");
+            return Ok(());
+        }
+        bail!(PklformationError::Validation(format!(
+            "{message}, declare them in the project file or pass --no-enforce-tags"
+        )))
     }
 
-    pub async fn run(self) -> anyhow::Result<()> {
+    async fn up(&self) -> anyhow::Result<StackStatus> {
+        self.check_required_tags()?;
         let wait_result = self
             .client
             .wait_until_stack_op_in_progress(&self.stack, self.pool_interval)
             .await;
 
+        let started = Instant::now();
         let start_time = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs_f64();
         if wait_result.is_err() {
             self.create_or_update(ChangeSetType::Create).await?;
@@ -62,93 +270,420 @@ impl UpCommand {
                 StackStatus::ReviewInProgress => {
                     self.continue_pending_change_set().await?;
                 }
+                StackStatus::UpdateRollbackFailed => {
+                    self.recover_rollback().await?;
+                    self.create_or_update(ChangeSetType::Update).await?;
+                }
                 _ => {
-                    tracing::error!("Up failed with status: {last_status:?}, reason: {reason:?}. Check the AWS Console");
-                    return Ok(());
+                    let console_url = self.client.stack_console_url(&self.stack).await;
+                    tracing::error!(
+                        "Up failed with status: {last_status:?}, reason: {reason:?}. Check the AWS Console: {}",
+                        console_url.as_deref().unwrap_or("-")
+                    );
+                    bail!(PklformationError::StackFailed {
+                        stack: self.stack.clone(),
+                        status: last_status,
+                        reason: Some(reason),
+                    });
                 }
             }
         }
 
-        let (op_status, _reason) = self
-            .client
-            .wait_until_stack_op_in_progress(&self.stack, self.pool_interval)
-            .await?;
+        let deadline = self
+            .options
+            .timeout_in_minutes
+            .map(|minutes| Instant::now() + Duration::from_secs(minutes * 60));
+        let Some(op_status) = self.wait_for_stack_operation(deadline).await? else {
+            let (status, _reason) = self.client.stack_status(&self.stack).await?;
+            info!(
+                "Detached from stack {}, its operation keeps running ({status:?})",
+                self.stack
+            );
+            return Ok(status);
+        };
 
+        if op_status == StackStatus::ReviewInProgress {
+            self.clean_up_empty_stack().await?;
+            info!("Up aborted!");
+            return Ok(op_status);
+        }
+
+        let events: Vec<_> = self
+            .client
+            .describe_stack_events(&self.stack)
+            .await?
+            .into_iter()
+            .filter(|p| p.timestamp().map(|t| t.as_secs_f64()).unwrap_or_default() > start_time)
+            .collect();
+        let mut resource_timings = timings::resource_timings(&events);
+        let mut history = DurationHistory::load();
+        history.record(&self.stack, &resource_timings);
+        if let Err(err) = history.save() {
+            warn!("Unable to save the resource durations: {err}");
+        }
+        resource_timings.truncate(SLOWEST_RESOURCES);
+        self.display.print_resource_timings(&resource_timings);
         match op_status {
             StackStatus::CreateComplete | StackStatus::UpdateComplete => {
-                info!("Up compleated successfully!")
-            }
-            StackStatus::ReviewInProgress => {
-                self.clean_up_empty_stack().await?;
-                info!("Up aborted!")
+                info!("Up compleated successfully!");
+                self.print_summary(started).await;
+                self.export_outputs().await?;
             }
             _ => {
                 tracing::error!("Up failed with status: {op_status:?}");
-                let events = self
-                    .client
-                    .describe_stack_events(&self.stack)
-                    .await?
-                    .into_iter()
-                    .filter(|p| {
-                        p.timestamp().map(|t| t.as_secs_f64()).unwrap_or_default() > start_time
-                    });
-                self.display.print_resources_errors(events);
+                self.display.print_resources_errors(events.iter().cloned());
+                match nested_stacks::root_causes(&self.client, &events, start_time).await {
+                    Ok(failures) => self.display.print_nested_failures(&failures),
+                    Err(err) => warn!("Unable to find the failures of the nested stacks: {err}"),
+                }
             }
         }
+        Ok(op_status)
+    }
+
+    async fn export_outputs(&self) -> anyhow::Result<()> {
+        if self.options.outputs_file.is_none() && self.options.publish_outputs.is_none() {
+            return Ok(());
+        }
+        let stack = self.client.describe_stack(&self.stack).await?;
+        if let Some(outputs_file) = self.options.outputs_file.as_deref() {
+            stack_outputs::write(
+                &stack,
+                OutputsFormat::from_extension(outputs_file),
+                Some(outputs_file),
+            )?;
+        }
+        if let Some(prefix) = self.options.publish_outputs.as_deref() {
+            stack_outputs::publish(&self.client, &stack, prefix).await?;
+        }
         Ok(())
     }
 
-    pub async fn eval_template(&self) -> anyhow::Result<String> {
-        let template_eval_result = Command::new("pkl")
-            .args([
-                "eval",
-                self.template.to_str().unwrap(),
-                "--project-dir",
-                self.template.parent().unwrap().to_str().unwrap(),
-                "--format",
-                "json",
-            ])
-            .output()?;
+    // Only the change set executed by this run, the stack keeps the one of its last deployment
+    async fn print_summary(&self, started: Instant) {
+        let executed_change_set = self.executed_change_set.lock().unwrap().clone();
+        let Some(change_set_id) = executed_change_set else {
+            return;
+        };
+        match self.client.describe_change_set(&change_set_id).await {
+            Ok(change_set) => self
+                .display
+                .print_deployment_summary(&change_set, started.elapsed()),
+            Err(err) => warn!("Unable to summarize the deployment: {err}"),
+        }
+    }
 
-        debug!("pkl eval result: {template_eval_result:?}");
-        if !template_eval_result.status.success() {
-            bail!(String::from_utf8(template_eval_result.stderr)?);
+    // Ctrl-C offers to keep waiting, to detach from the operation or to cancel it
+    async fn wait_for_stack_operation(
+        &self,
+        deadline: Option<Instant>,
+    ) -> anyhow::Result<Option<StackStatus>> {
+        let interrupts = interrupt::handle();
+        loop {
+            tokio::select! {
+                result = self.client.wait_until_stack_op_in_progress_or_deadline(
+                    &self.stack,
+                    self.pool_interval,
+                    deadline,
+                ) => {
+                    let (status, _reason) = result?;
+                    if deadline.is_none_or(|deadline| Instant::now() < deadline) {
+                        return Ok(Some(status));
+                    }
+                    warn!(
+                        "Stack {} still in status {status:?} after {} minute(s)",
+                        self.stack,
+                        self.options.timeout_in_minutes.unwrap_or_default()
+                    );
+                    self.cancel_operation(&status).await?;
+                    let (status, _reason) = self
+                        .client
+                        .wait_until_stack_op_in_progress(&self.stack, self.pool_interval)
+                        .await?;
+                    return Ok(Some(status));
+                }
+                _ = interrupts.interrupted() => {}
+            }
+
+            let choice = self.display.ask_select(
+                "Interrupted, what do you want to do?",
+                &[
+                    "Keep waiting",
+                    "Detach and leave the operation running",
+                    "Cancel the operation",
+                ],
+            );
+            match choice {
+                Some(0) => {}
+                Some(2) => {
+                    let (status, _reason) = self.client.stack_status(&self.stack).await?;
+                    if !self.cancel_operation(&status).await? {
+                        warn!(
+                            "Stack {} is in status {status:?}, nothing to cancel",
+                            self.stack
+                        );
+                    }
+                }
+                _ => return Ok(None),
+            }
         }
+    }
+
+    // Deleting a stack being created cancels the creation
+    async fn cancel_operation(&self, status: &StackStatus) -> anyhow::Result<bool> {
+        match status {
+            StackStatus::CreateInProgress => {
+                warn!("Cancel the creation of stack {}, delete it", self.stack);
+                self.client.delete_stack(&self.stack).await?;
+            }
+            StackStatus::UpdateInProgress => {
+                warn!("Cancel the update of stack {}", self.stack);
+                self.client.cancel_update_stack(&self.stack).await?;
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
 
-        Ok(String::from_utf8(template_eval_result.stdout)?)
+    fn eval_template(&self) -> anyhow::Result<String> {
+        let template = template_format::load(
+            &self.template,
+            self.options.template_format,
+            &self.options.pkl,
+        )?;
+        let violations = self.validator.validate(&template)?;
+        if !violations.is_empty() {
+            self.display.print_violations(&violations);
+            bail!(PklformationError::Validation(format!(
+                "Template validation failed with {} violation(s)",
+                violations.len()
+            )));
+        }
+        Ok(template)
     }
 
     async fn create_or_update(&self, change_set_type: ChangeSetType) -> anyhow::Result<()> {
         info!("Create stack {} ...", self.stack);
-        let template = self.eval_template().await?;
+        let template = self.eval_template()?;
+        let creating = change_set_type == ChangeSetType::Create;
+        partition::check_resource_types(&self.client, &template).await?;
+        quotas::preflight(&self.client, &self.stack, &change_set_type).await?;
         let change_set = self
             .client
-            .create_or_update_change_set(&self.stack, &template, change_set_type)
+            .create_or_update_change_set(
+                &self.stack,
+                &template,
+                change_set_type,
+                &self.change_set_options,
+            )
             .await?;
         let change_set_id = change_set.id().context("Empty change set id")?;
-        self.client
-            .wait_until_change_set_op_in_progress(change_set_id, self.pool_interval)
-            .await?;
+        // An interrupted creation would leave a change set behind, and an empty stack in review
+        let interrupts = interrupt::handle();
+        let (status, reason) = tokio::select! {
+            result = self
+                .client
+                .wait_until_change_set_op_in_progress(change_set_id, self.pool_interval) => {
+                result?
+            }
+            _ = interrupts.interrupted() => {
+                self.client.delete_change_set(change_set_id).await?;
+                if creating {
+                    self.clean_up_empty_stack().await?;
+                }
+                bail!(PklformationError::Aborted(format!(
+                    "Interrupted, change set {change_set_id} deleted"
+                )));
+            }
+        };
+        drop(interrupts);
+        if AwsClient::is_empty_change_set(&status, &reason) {
+            info!("No changes to deploy on stack {}", self.stack);
+            self.client.delete_change_set(change_set_id).await?;
+            return Ok(());
+        }
         let change_set_description = self.client.describe_change_set(change_set_id).await?;
         self.display.print_change_set(&change_set_description);
+        if let Err(err) = self.check_change_set(&change_set_description) {
+            self.client.delete_change_set(change_set_id).await?;
+            return Err(err);
+        }
 
-        if self.display.ask_confirm("Do you want to continue?") {
-            self.client.execute_change_set(change_set_id).await?;
+        self.print_failure_behavior(creating);
+        let empty = change_set_description.changes().is_empty();
+        if empty {
+            info!("No resource changes on stack {}", self.stack);
+        }
+        let confirmation = if (empty && self.options.skip_empty_confirm) || self.approval_only() {
+            Confirmation::Skipped
+        } else {
+            Confirmation::from_answer(self.confirm_execution("Do you want to continue?"))
+        };
+        *self.confirmation.lock().unwrap() = Some((change_set_id.to_string(), confirmation));
+        if !matches!(confirmation, Confirmation::Declined) {
+            if let Err(err) = self.request_approval(&change_set_description).await {
+                self.client.delete_change_set(change_set_id).await?;
+                return Err(err);
+            }
+            self.client
+                .execute_change_set(change_set_id, !creating && self.options.disable_rollback)
+                .await?;
+            *self.executed_change_set.lock().unwrap() = Some(change_set_id.to_string());
             self.client
                 .wait_until_change_set_op_in_progress(change_set_id, self.pool_interval)
                 .await?;
         } else {
             self.client.delete_change_set(change_set_id).await?;
+            if creating {
+                self.clean_up_empty_stack().await?;
+            }
+            bail!(PklformationError::Aborted(format!(
+                "Change set {change_set_id} declined, stack {} left unchanged",
+                self.stack
+            )));
         }
 
         Ok(())
     }
 
+    fn print_failure_behavior(&self, creating: bool) {
+        let behavior = match (creating, self.options.on_failure) {
+            (true, Some(OnFailure::Delete)) => "delete the stack",
+            (true, Some(OnFailure::DoNothing)) => "keep the created resources",
+            (true, _) => "roll back",
+            (false, _) if self.options.disable_rollback => "keep the updated resources",
+            (false, _) => "roll back",
+        };
+        info!("On failure: {behavior}");
+    }
+
+    // Protected stacks ask twice, so a single stray keystroke can not change them
+    fn confirm_execution(&self, msg: &str) -> bool {
+        self.display.ask_confirm(msg)
+            && (!self.options.protected
+                || self.display.ask_confirm(&format!(
+                    "Stack {} is protected, do you really want to apply these changes?",
+                    self.stack
+                )))
+    }
+
+    fn approval_only(&self) -> bool {
+        self.options
+            .approval
+            .as_ref()
+            .is_some_and(|approval| approval.replace_confirm)
+    }
+
+    async fn request_approval(&self, change_set: &DescribeChangeSetOutput) -> anyhow::Result<()> {
+        match &self.options.approval {
+            Some(approval) => approval.request(&self.stack, change_set).await,
+            None => Ok(()),
+        }
+    }
+
+    fn check_change_set(&self, change_set: &DescribeChangeSetOutput) -> anyhow::Result<()> {
+        let violations = self.validator.validate_change_set(change_set);
+        if violations.is_empty() {
+            return Ok(());
+        }
+
+        self.display.print_violations(&violations);
+        let (replacements, immutables): (Vec<_>, Vec<_>) = violations
+            .iter()
+            .partition(|violation| violation.is_replacement());
+        if !replacements.is_empty() {
+            if !self.options.allow_replacement {
+                bail!(PklformationError::Validation("The change set replaces or removes resources of a protected type, pass --allow-replacement to execute it".to_string()))
+            }
+            warn!("Protected resource types are replaced or removed, continuing as --allow-replacement is set");
+        }
+        if !immutables.is_empty() {
+            if !self.options.allow_immutable_changes {
+                bail!(PklformationError::Validation("The change set affects immutable resources, pass --allow-immutable-changes to execute it".to_string()))
+            }
+            warn!(
+                "Immutable resources are affected, continuing as --allow-immutable-changes is set"
+            );
+        }
+        Ok(())
+    }
+
+    // Resources that failed to roll back can be skipped, their state is then left as is
+    async fn recover_rollback(&self) -> anyhow::Result<()> {
+        warn!("The last rollback of stack {} failed", self.stack);
+        let failed: Vec<_> = self
+            .client
+            .list_all_stack_resources(&self.stack)
+            .await?
+            .into_iter()
+            .filter(|resource| resource.resource_status() == Some(&ResourceStatus::UpdateFailed))
+            .collect();
+        let items: Vec<_> = failed
+            .iter()
+            .map(|resource| {
+                format!(
+                    "{} ({}): {}",
+                    resource.logical_resource_id().unwrap_or_default(),
+                    resource.resource_type().unwrap_or_default(),
+                    resource
+                        .resource_status_reason()
+                        .unwrap_or("Unknown reason")
+                )
+            })
+            .collect();
+        let resources_to_skip: Vec<_> = self
+            .display
+            .ask_multi_select("Resources to skip during the rollback:", &items)
+            .into_iter()
+            .filter_map(|index| failed[index].logical_resource_id().map(str::to_string))
+            .collect();
+        if !self.display.ask_confirm(&format!(
+            "Do you want to continue the rollback of stack {} skipping {} resource(s)?",
+            self.stack,
+            resources_to_skip.len()
+        )) {
+            bail!(PklformationError::Aborted(format!(
+                "Stack {} is still in status UpdateRollbackFailed",
+                self.stack
+            )));
+        }
+
+        self.client
+            .continue_update_rollback(&self.stack, resources_to_skip)
+            .await?;
+        let (status, reason) = self
+            .client
+            .wait_until_stack_op_in_progress(&self.stack, self.pool_interval)
+            .await?;
+        if status != StackStatus::UpdateRollbackComplete {
+            bail!(
+                "Rollback of stack {} ended with status {status:?}: {reason}",
+                self.stack
+            );
+        }
+        info!(
+            "Rollback of stack {} complete, resume the deployment",
+            self.stack
+        );
+        Ok(())
+    }
+
     async fn recreate(&self) -> anyhow::Result<()> {
         info!(
             "Past creation of the stack {} failed, re-create stack...",
             self.stack
         );
+        if !self.options.yes
+            && !self.display.ask_typed_confirm(
+                &format!("Type the stack name to delete and re-create {}", self.stack),
+                &self.stack,
+            )
+        {
+            bail!(PklformationError::Aborted(format!(
+                "Stack {} not re-created",
+                self.stack
+            )));
+        }
         info!("Re-create stack {}...", self.stack);
         self.client.delete_stack(&self.stack).await?;
         let _ = self
@@ -173,11 +708,20 @@ impl UpCommand {
                 self.client.describe_change_set(change_set_id).await?;
             self.display
                 .print_change_set(&pending_change_set_description);
-            if self
-                .display
-                .ask_confirm("Do you want to apply this change set?")
-            {
-                self.client.execute_change_set(change_set_id).await?;
+            self.check_change_set(&pending_change_set_description)?;
+            // Pending change sets belong to stacks in review, so they always create the stack
+            self.print_failure_behavior(true);
+            let accepted = self.approval_only()
+                || self.confirm_execution("Do you want to apply this change set?");
+            *self.confirmation.lock().unwrap() = Some((
+                change_set_id.to_string(),
+                Confirmation::from_answer(accepted),
+            ));
+            if accepted {
+                self.request_approval(&pending_change_set_description)
+                    .await?;
+                self.client.execute_change_set(change_set_id, false).await?;
+                *self.executed_change_set.lock().unwrap() = Some(change_set_id.to_string());
                 self.client
                     .wait_until_change_set_op_in_progress(change_set_id, self.pool_interval)
                     .await?;
@@ -199,6 +743,11 @@ impl UpCommand {
                         reason
                     );
                 }
+            } else {
+                bail!(PklformationError::Aborted(format!(
+                    "Change set {change_set_id} declined, it is left pending on stack {}",
+                    self.stack
+                )));
             }
         } else {
             self.create_or_update(ChangeSetType::Create).await?;
@@ -217,3 +766,175 @@ impl UpCommand {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use aws_sdk_cloudformation::types::ChangeSetSummary;
+
+    use super::*;
+    use crate::{
+        approval::ApprovalHook,
+        cloudformation_api::mock::{isolate_user_data, MockCloudFormation, MOCK_CHANGE_SET_ID},
+        validation::NamingRules,
+    };
+
+    const STACK: &str = "mock";
+
+    // The approval hook replaces the interactive confirmation, `true` approves every change set
+    fn options(yes: bool) -> UpOptions {
+        UpOptions {
+            yes,
+            approval: Some(ApprovalConfig {
+                hook: ApprovalHook::Command {
+                    command: "true".to_string(),
+                    args: Vec::new(),
+                },
+                replace_confirm: true,
+            }),
+            ..Default::default()
+        }
+    }
+
+    async fn up(
+        client: &MockCloudFormation,
+        options: UpOptions,
+    ) -> Result<StackStatus, PklformationError> {
+        isolate_user_data();
+        UpCommand::new(
+            client.clone(),
+            STACK.to_string(),
+            PathBuf::from(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/topic.json"
+            )),
+            Duration::ZERO,
+            Validator::new(&NamingRules::default()).unwrap(),
+            ChangeSetOptions::default(),
+            options,
+        )
+        .run()
+        .await
+    }
+
+    #[tokio::test]
+    async fn review_in_progress_executes_the_pending_change_set() {
+        let client = MockCloudFormation::new()
+            .with_stack_statuses([StackStatus::ReviewInProgress, StackStatus::CreateComplete])
+            .with_pending_change_set(
+                ChangeSetSummary::builder()
+                    .change_set_id(MOCK_CHANGE_SET_ID)
+                    .build(),
+            );
+
+        let status = up(&client, options(false)).await.unwrap();
+
+        assert_eq!(status, StackStatus::CreateComplete);
+        let calls = client.calls();
+        assert!(calls.contains(&format!("ExecuteChangeSet {MOCK_CHANGE_SET_ID}")));
+        assert!(!calls.iter().any(|call| call.starts_with("CreateChangeSet")));
+    }
+
+    #[tokio::test]
+    async fn rollback_complete_recreates_the_stack() {
+        let client = MockCloudFormation::new().with_stack_statuses([
+            StackStatus::RollbackComplete,
+            StackStatus::DeleteComplete,
+            StackStatus::CreateComplete,
+        ]);
+
+        let status = up(&client, options(true)).await.unwrap();
+
+        assert_eq!(status, StackStatus::CreateComplete);
+        let calls = client.calls();
+        let position = |call: &str| calls.iter().position(|c| c == call);
+        let deleted = position(&format!("DeleteStack {STACK}")).unwrap();
+        let created = position(&format!("CreateChangeSet(CREATE) {STACK}")).unwrap();
+        let executed = position(&format!("ExecuteChangeSet {MOCK_CHANGE_SET_ID}")).unwrap();
+        assert!(deleted < created && created < executed);
+    }
+
+    #[tokio::test]
+    async fn rollback_complete_is_not_recreated_without_confirmation() {
+        let client = MockCloudFormation::new().with_stack_statuses([StackStatus::RollbackComplete]);
+
+        let err = up(&client, options(false)).await.unwrap_err();
+
+        assert!(matches!(err, PklformationError::Aborted(_)));
+        assert!(!client
+            .calls()
+            .iter()
+            .any(|call| call.starts_with("DeleteStack")));
+    }
+
+    #[tokio::test]
+    async fn failed_rollback_is_not_continued_without_confirmation() {
+        let client =
+            MockCloudFormation::new().with_stack_statuses([StackStatus::UpdateRollbackFailed]);
+
+        let err = up(&client, options(true)).await.unwrap_err();
+
+        assert!(matches!(err, PklformationError::Aborted(_)));
+        let calls = client.calls();
+        assert!(!calls
+            .iter()
+            .any(|call| call.starts_with("ContinueUpdateRollback")));
+        assert!(!calls.iter().any(|call| call.starts_with("CreateChangeSet")));
+    }
+
+    #[tokio::test]
+    async fn rolled_back_update_fails_with_its_exit_code() {
+        let client = MockCloudFormation::new().with_stack_statuses([
+            StackStatus::UpdateComplete,
+            StackStatus::UpdateRollbackComplete,
+        ]);
+
+        let err = up(&client, options(false)).await.unwrap_err();
+
+        assert!(matches!(
+            err,
+            PklformationError::StackFailed {
+                status: StackStatus::UpdateRollbackComplete,
+                ..
+            }
+        ));
+        assert_eq!(err.exit_code(), 7);
+        assert!(client
+            .calls()
+            .contains(&format!("ExecuteChangeSet {MOCK_CHANGE_SET_ID}")));
+    }
+
+    // Without a terminal the confirmation is declined
+    #[tokio::test]
+    async fn declined_change_set_is_aborted_and_deleted() {
+        let client = MockCloudFormation::new().with_stack_statuses([StackStatus::UpdateComplete]);
+
+        let err = up(&client, UpOptions::default()).await.unwrap_err();
+
+        assert!(matches!(err, PklformationError::Aborted(_)));
+        assert_eq!(err.exit_code(), 6);
+        let calls = client.calls();
+        assert!(calls.contains(&format!("DeleteChangeSet {MOCK_CHANGE_SET_ID}")));
+        assert!(!calls
+            .iter()
+            .any(|call| call.starts_with("ExecuteChangeSet")));
+    }
+
+    #[tokio::test]
+    async fn empty_change_set_is_deleted_without_execution() {
+        let client = MockCloudFormation::new()
+            .with_stack_statuses([StackStatus::UpdateComplete])
+            .with_change_set_statuses([(
+                ChangeSetStatus::Failed,
+                "The submitted information didn't contain changes.".to_string(),
+            )]);
+
+        let status = up(&client, options(false)).await.unwrap();
+
+        assert_eq!(status, StackStatus::UpdateComplete);
+        let calls = client.calls();
+        assert!(calls.contains(&format!("DeleteChangeSet {MOCK_CHANGE_SET_ID}")));
+        assert!(!calls
+            .iter()
+            .any(|call| call.starts_with("ExecuteChangeSet")));
+    }
+}
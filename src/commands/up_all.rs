@@ -0,0 +1,118 @@
+use anyhow::anyhow;
+use aws_sdk_cloudformation::types::StackStatus;
+
+use std::{path::PathBuf, time::Duration};
+use tracing::{info, span, Level};
+
+use crate::{
+    aws_client::AwsClient,
+    commands::up::{UpCommand, UpOptions},
+    display::Display,
+    error::PklformationError,
+    events_log::EventsLog,
+    progress::ProgressStream,
+    project::Project,
+    stack_graph::StackGraph,
+    stack_outputs,
+    validation::Validator,
+};
+
+pub struct UpAllCommand<'a> {
+    project: &'a Project,
+    pool_interval: Duration,
+    validator: Validator,
+    options: UpOptions,
+    events_log: Option<PathBuf>,
+    progress_stream: Option<ProgressStream>,
+    display: Display,
+}
+
+impl<'a> UpAllCommand<'a> {
+    pub fn new(
+        project: &'a Project,
+        pool_interval: Duration,
+        validator: Validator,
+        options: UpOptions,
+        events_log: Option<PathBuf>,
+        progress_stream: Option<ProgressStream>,
+    ) -> Self {
+        Self {
+            project,
+            pool_interval,
+            validator,
+            options,
+            events_log,
+            progress_stream,
+            display: Display::new(),
+        }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        let order = StackGraph::from_project(self.project)?.deployment_order()?;
+        info!("Deployment order: {}", order.join(" -> "));
+
+        let mut results = Vec::new();
+        for stack in &order {
+            if results.iter().any(|(_, result)| !Self::succeeded(result)) {
+                results.push((
+                    stack.clone(),
+                    Err(anyhow!("Skipped after a previous failure")),
+                ));
+                continue;
+            }
+
+            let span = span!(Level::INFO, "up", stack = stack);
+            let _enter = span.enter();
+            let result = self.up(stack).await;
+            results.push((stack.clone(), result));
+        }
+
+        self.display.print_operation_results("Stacks:", &results);
+        let failures = results
+            .iter()
+            .filter(|(_, result)| !Self::succeeded(result))
+            .count();
+        if failures > 0 {
+            return Err(anyhow!("Up failed for {failures} stack(s)").into());
+        }
+        Ok(())
+    }
+
+    async fn up(&self, stack: &str) -> anyhow::Result<StackStatus> {
+        let template = self.project.template(stack, None)?;
+        let mut client = AwsClient::new(self.project.region(stack))
+            .await
+            .with_notifications(self.project.notifications.clone())
+            .with_progress_stream(self.progress_stream.clone());
+        if let Some(path) = self.events_log.as_ref() {
+            client = client.with_events_log(EventsLog::open(path)?);
+        }
+
+        Ok(UpCommand::new(
+            client,
+            self.project.stack_name(stack),
+            template,
+            self.pool_interval,
+            self.validator
+                .clone()
+                .with_immutable_resources(self.project.immutable_resources(stack)),
+            self.project.change_set_options(stack),
+            UpOptions {
+                pkl: stack_outputs::pkl_options(self.project, stack, &self.options.pkl).await?,
+                protected: self.project.is_protected(stack),
+                approval: self.project.approval(),
+                required_tags: self.project.required_tags.clone(),
+                ..self.options.clone()
+            },
+        )
+        .run()
+        .await?)
+    }
+
+    fn succeeded(result: &anyhow::Result<StackStatus>) -> bool {
+        matches!(
+            result,
+            Ok(StackStatus::CreateComplete | StackStatus::UpdateComplete)
+        )
+    }
+}
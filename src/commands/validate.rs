@@ -0,0 +1,69 @@
+use anyhow::Context;
+
+use std::path::PathBuf;
+use tracing::info;
+
+use crate::{
+    aws_client::AwsClient, display::Display, error::PklformationError, intrinsics, pkl::PklOptions,
+    reports::ReportFormat, template_format, validation::Validator,
+};
+
+pub struct ValidateCommand {
+    template: PathBuf,
+    validator: Validator,
+    pkl_options: PklOptions,
+    schemas_client: Option<AwsClient>,
+    report: Option<(ReportFormat, Option<PathBuf>)>,
+    display: Display,
+}
+
+impl ValidateCommand {
+    pub fn new(
+        template: PathBuf,
+        validator: Validator,
+        pkl_options: PklOptions,
+        schemas_client: Option<AwsClient>,
+        report: Option<(ReportFormat, Option<PathBuf>)>,
+    ) -> Self {
+        Self {
+            template,
+            validator,
+            pkl_options,
+            schemas_client,
+            report,
+            display: Display::new(),
+        }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        let template = template_format::load(&self.template, None, &self.pkl_options)?;
+        let mut violations = self.validator.validate(&template)?;
+        if let Some(client) = self.schemas_client.as_ref() {
+            let template = serde_json::from_str(&template).context("Invalid template JSON")?;
+            violations.extend(intrinsics::check_attributes(client, &template).await?);
+        }
+        if let Some((format, out)) = self.report.as_ref() {
+            let report = format.render(&self.template, &violations);
+            match out {
+                Some(out) => {
+                    std::fs::write(out, report)
+                        .with_context(|| format!("Unable to write {}", out.display()))?;
+                    info!("Validation report written to {}", out.display());
+                }
+                None => println!("{report}"),
+            }
+        }
+        if !violations.is_empty() {
+            if self.report.is_none() {
+                self.display.print_violations(&violations);
+            }
+            return Err(PklformationError::Validation(format!(
+                "Template validation failed with {} violation(s)",
+                violations.len()
+            )));
+        }
+
+        info!("Template {} is valid", self.template.display());
+        Ok(())
+    }
+}
@@ -0,0 +1,29 @@
+use tracing::info;
+
+use crate::{error::PklformationError, update};
+
+pub struct VersionCommand {
+    check: bool,
+}
+
+impl VersionCommand {
+    pub fn new(check: bool) -> Self {
+        Self { check }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        println!("pklformation {}", update::CURRENT_VERSION);
+        if self.check {
+            let release = update::latest_release().await?;
+            if release.is_newer() {
+                info!(
+                    "pklformation {} is available, run `pklformation self-update` to upgrade",
+                    release.version()
+                );
+            } else {
+                info!("pklformation is up to date");
+            }
+        }
+        Ok(())
+    }
+}
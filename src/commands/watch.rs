@@ -0,0 +1,54 @@
+use anyhow::Context;
+use std::time::Duration;
+use tracing::info;
+
+use crate::{aws_client::AwsClient, display::Display, error::PklformationError};
+
+const LATEST_EVENTS: usize = 10;
+
+pub struct WatchCommand {
+    client: AwsClient,
+    stack: String,
+    interval: Duration,
+    display: Display,
+}
+
+impl WatchCommand {
+    pub fn new(client: AwsClient, stack: String, interval: Duration) -> Self {
+        Self {
+            client,
+            stack,
+            interval,
+            display: Display::new(),
+        }
+    }
+
+    pub async fn run(self) -> Result<(), PklformationError> {
+        // Deleted stacks can only be described by their id
+        let stack_id = self
+            .client
+            .describe_stack(&self.stack)
+            .await?
+            .stack_id()
+            .context("Stack without id")?
+            .to_string();
+        loop {
+            let stack = self.client.describe_stack(&stack_id).await?;
+            let resources = self.client.list_stack_resources(&stack_id).await?;
+            let events = self.client.recent_stack_events(&stack_id).await?;
+
+            self.display.clear_screen();
+            self.display.print_stack(&stack);
+            self.display.print_stack_resources(&stack_id, &resources);
+            self.display
+                .print_stack_events(&events[..events.len().min(LATEST_EVENTS)]);
+
+            let status = stack.stack_status().context("Stack without status")?;
+            if !AwsClient::stack_op_in_progres(status) {
+                info!("Stack {} is stable in status {status:?}", self.stack);
+                return Ok(());
+            }
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::debug;
+
+use crate::{
+    aws_client::{AccessDenied, AwsClient},
+    partition::Partition,
+};
+
+pub const STACK_NAME_TAG: &str = "aws:cloudformation:stack-name";
+const COST_METRIC: &str = "UnblendedCost";
+const GET_COST_AND_USAGE: &str = "AWSInsightsIndexService.GetCostAndUsage";
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceCost {
+    pub service: String,
+    pub amount: f64,
+    pub unit: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CostAndUsage {
+    #[serde(default)]
+    results_by_time: Vec<ResultByTime>,
+    next_page_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ResultByTime {
+    #[serde(default)]
+    groups: Vec<Group>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Group {
+    keys: Vec<String>,
+    metrics: BTreeMap<String, Metric>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Metric {
+    amount: String,
+    unit: String,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    #[serde(rename = "__type")]
+    code: Option<String>,
+    #[serde(alias = "Message")]
+    message: Option<String>,
+}
+
+// Cost Explorer has a single region per partition, its endpoint follows the shared config
+fn endpoint(client: &AwsClient) -> anyhow::Result<(&'static str, String)> {
+    let partition = client
+        .region()
+        .and_then(Partition::from_region)
+        .unwrap_or(Partition::Aws);
+    let region = match partition {
+        Partition::Aws => "us-east-1",
+        Partition::AwsCn => "cn-northwest-1",
+        Partition::AwsUsGov => bail!("Cost Explorer is not available in aws-us-gov"),
+    };
+    let url = match client.endpoint_url() {
+        Some(url) => url.to_string(),
+        None => format!("https://ce.{region}.{}/", partition.dns_suffix()),
+    };
+    Ok((region, url))
+}
+
+async fn get_cost_and_usage(
+    client: &AwsClient,
+    request: &serde_json::Value,
+) -> anyhow::Result<CostAndUsage> {
+    let (region, url) = endpoint(client)?;
+    let (status, text) = client
+        .signed_json_post(
+            "ce",
+            region,
+            &url,
+            GET_COST_AND_USAGE,
+            serde_json::to_vec(request)?,
+        )
+        .await?;
+    if status.is_success() {
+        return serde_json::from_str(&text).context("Invalid Cost Explorer response");
+    }
+    let error: ErrorResponse = serde_json::from_str(&text).unwrap_or(ErrorResponse {
+        code: None,
+        message: Some(text),
+    });
+    let code = error.code.unwrap_or_default();
+    let message = error.message.unwrap_or_else(|| "no details".to_string());
+    // The error type is prefixed with its namespace
+    if code.ends_with("AccessDeniedException") {
+        return Err(AccessDenied {
+            action: "ce:GetCostAndUsage",
+            feature: "the cost report",
+            message,
+        }
+        .into());
+    }
+    bail!("Cost Explorer request failed with {status}: {code} {message}")
+}
+
+/// Cost of the resources tagged with the stack name, by service, from start to end (exclusive)
+pub async fn by_service(
+    client: &AwsClient,
+    stack: &str,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> anyhow::Result<Vec<ServiceCost>> {
+    let mut costs: BTreeMap<String, ServiceCost> = BTreeMap::new();
+    let mut next_page_token: Option<String> = None;
+    loop {
+        let mut request = json!({
+            "TimePeriod": {
+                "Start": start.format("%Y-%m-%d").to_string(),
+                "End": end.format("%Y-%m-%d").to_string(),
+            },
+            "Granularity": "MONTHLY",
+            "Metrics": [COST_METRIC],
+            "Filter": {
+                "Tags": {
+                    "Key": STACK_NAME_TAG,
+                    "Values": [stack],
+                    "MatchOptions": ["EQUALS"],
+                },
+            },
+            "GroupBy": [{ "Type": "DIMENSION", "Key": "SERVICE" }],
+        });
+        if let Some(token) = &next_page_token {
+            request["NextPageToken"] = json!(token);
+        }
+        let response = get_cost_and_usage(client, &request).await?;
+        // A period spanning several months has a result per month, summed by service
+        for group in response
+            .results_by_time
+            .into_iter()
+            .flat_map(|result| result.groups)
+        {
+            let Some(metric) = group.metrics.get(COST_METRIC) else {
+                continue;
+            };
+            let service = group.keys.join(", ");
+            let amount: f64 = metric.amount.parse().unwrap_or_default();
+            costs
+                .entry(service.clone())
+                .or_insert_with(|| ServiceCost {
+                    service,
+                    amount: 0.0,
+                    unit: metric.unit.clone(),
+                })
+                .amount += amount;
+        }
+        next_page_token = response.next_page_token;
+        if next_page_token.is_none() {
+            break;
+        }
+        debug!("Fetching the next page of costs");
+    }
+
+    let mut costs: Vec<_> = costs.into_values().collect();
+    costs.sort_by(|a, b| b.amount.total_cmp(&a.amount));
+    Ok(costs)
+}
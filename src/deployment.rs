@@ -0,0 +1,340 @@
+use anyhow::{bail, Context};
+use aws_sdk_cloudformation::types::{
+    ChangeSetStatus, ChangeSetType, Output, ResourceChange, StackEvent, StackStatus,
+};
+
+use std::{
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::info;
+
+use crate::{
+    aws_client::{AwsClient, ChangeSetOptions},
+    display::Display,
+    error::PklformationError,
+    partition,
+    pkl::PklOptions,
+    quotas, template_format,
+    validation::{NamingRules, Validator, Violation},
+};
+
+const DEFAULT_POOL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Approval {
+    /// Execute the change set without asking
+    Auto,
+    /// Ask for a confirmation on the terminal before executing the change set
+    #[default]
+    Prompt,
+    /// Create and report the change set, then delete it without executing it
+    Never,
+}
+
+#[derive(Debug)]
+pub struct DeploymentResult {
+    pub stack_status: Option<StackStatus>,
+    pub executed: bool,
+    pub changes: Vec<ResourceChange>,
+    pub violations: Vec<Violation>,
+    pub outputs: Vec<Output>,
+    pub events: Vec<StackEvent>,
+}
+
+#[derive(Default)]
+pub struct DeploymentBuilder {
+    stack: Option<String>,
+    template_path: Option<PathBuf>,
+    region: Option<String>,
+    client: Option<AwsClient>,
+    change_set_options: ChangeSetOptions,
+    approval: Approval,
+    pool_interval: Option<Duration>,
+    validator: Option<Validator>,
+    allow_immutable_changes: bool,
+    allow_replacement: bool,
+    pkl_options: PklOptions,
+}
+
+impl DeploymentBuilder {
+    pub fn stack(mut self, stack: impl Into<String>) -> Self {
+        self.stack = Some(stack.into());
+        self
+    }
+
+    pub fn template_path(mut self, template_path: impl Into<PathBuf>) -> Self {
+        self.template_path = Some(template_path.into());
+        self
+    }
+
+    pub fn region(mut self, region: impl Into<String>) -> Self {
+        self.region = Some(region.into());
+        self
+    }
+
+    // Takes precedence over the region, for the clients configured in code
+    pub fn client(mut self, client: AwsClient) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    pub fn parameter(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.change_set_options
+            .parameters
+            .insert(key.into(), value.into());
+        self
+    }
+
+    pub fn tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.change_set_options
+            .tags
+            .insert(key.into(), value.into());
+        self
+    }
+
+    pub fn pkl_property(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.pkl_options
+            .properties
+            .push((name.into(), value.into()));
+        self
+    }
+
+    pub fn approval(mut self, approval: Approval) -> Self {
+        self.approval = approval;
+        self
+    }
+
+    pub fn pool_interval(mut self, pool_interval: Duration) -> Self {
+        self.pool_interval = Some(pool_interval);
+        self
+    }
+
+    pub fn validator(mut self, validator: Validator) -> Self {
+        self.validator = Some(validator);
+        self
+    }
+
+    pub fn allow_immutable_changes(mut self, allow_immutable_changes: bool) -> Self {
+        self.allow_immutable_changes = allow_immutable_changes;
+        self
+    }
+
+    pub fn allow_replacement(mut self, allow_replacement: bool) -> Self {
+        self.allow_replacement = allow_replacement;
+        self
+    }
+
+    pub fn build(self) -> Result<Deployment, PklformationError> {
+        Ok(Deployment {
+            stack: self.stack.context("A deployment requires a stack name")?,
+            template_path: self
+                .template_path
+                .context("A deployment requires a template path")?,
+            region: self.region,
+            client: self.client,
+            change_set_options: self.change_set_options,
+            approval: self.approval,
+            pool_interval: self.pool_interval.unwrap_or(DEFAULT_POOL_INTERVAL),
+            validator: match self.validator {
+                Some(validator) => validator,
+                None => Validator::new(&NamingRules::default())?,
+            },
+            allow_immutable_changes: self.allow_immutable_changes,
+            allow_replacement: self.allow_replacement,
+            pkl_options: self.pkl_options,
+        })
+    }
+
+    pub async fn execute(self) -> Result<DeploymentResult, PklformationError> {
+        self.build()?.execute().await
+    }
+}
+
+pub struct Deployment {
+    stack: String,
+    template_path: PathBuf,
+    region: Option<String>,
+    client: Option<AwsClient>,
+    change_set_options: ChangeSetOptions,
+    approval: Approval,
+    pool_interval: Duration,
+    validator: Validator,
+    allow_immutable_changes: bool,
+    allow_replacement: bool,
+    pkl_options: PklOptions,
+}
+
+impl Deployment {
+    pub fn builder() -> DeploymentBuilder {
+        DeploymentBuilder::default()
+    }
+
+    pub async fn execute(&self) -> Result<DeploymentResult, PklformationError> {
+        Ok(self.deploy().await?)
+    }
+
+    async fn deploy(&self) -> anyhow::Result<DeploymentResult> {
+        let default_client;
+        let client = match &self.client {
+            Some(client) => client,
+            None => {
+                default_client = AwsClient::new(self.region.as_deref()).await;
+                &default_client
+            }
+        };
+        let template = template_format::load(&self.template_path, None, &self.pkl_options)?;
+        let violations = self.validator.validate(&template)?;
+        if !violations.is_empty() {
+            bail!(PklformationError::Validation(format!(
+                "Template validation failed with {} violation(s)",
+                violations.len()
+            )));
+        }
+
+        let change_set_type = self.change_set_type(client).await?;
+        let creating = change_set_type == ChangeSetType::Create;
+        partition::check_resource_types(client, &template).await?;
+        quotas::preflight(client, &self.stack, &change_set_type).await?;
+        let start_time = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs_f64();
+        let change_set = client
+            .create_or_update_change_set(
+                &self.stack,
+                &template,
+                change_set_type,
+                &self.change_set_options,
+            )
+            .await?;
+        let change_set_id = change_set.id().context("Empty change set id")?;
+        let (change_set_status, reason) = client
+            .wait_until_change_set_op_in_progress(change_set_id, self.pool_interval)
+            .await?;
+        if change_set_status != ChangeSetStatus::CreateComplete {
+            client.delete_change_set(change_set_id).await?;
+            if creating {
+                self.clean_up_empty_stack(client).await?;
+            }
+            if !AwsClient::is_empty_change_set(&change_set_status, &reason) {
+                bail!("Change set {change_set_id} failed: {reason}");
+            }
+            info!("No changes to deploy on stack {}", self.stack);
+            return self
+                .result(client, false, Vec::new(), Vec::new(), start_time)
+                .await;
+        }
+        let change_set = client.describe_change_set(change_set_id).await?;
+        let changes: Vec<_> = change_set
+            .changes()
+            .iter()
+            .filter_map(|change| change.resource_change().cloned())
+            .collect();
+
+        let violations = self.validator.validate_change_set(&change_set);
+        let (replacements, immutables): (Vec<_>, Vec<_>) = violations
+            .iter()
+            .partition(|violation| violation.is_replacement());
+        if !replacements.is_empty() && !self.allow_replacement {
+            client.delete_change_set(change_set_id).await?;
+            bail!("The change set replaces or removes resources of a protected type");
+        }
+        if !immutables.is_empty() && !self.allow_immutable_changes {
+            client.delete_change_set(change_set_id).await?;
+            bail!("The change set affects immutable resources");
+        }
+
+        let approved = match self.approval {
+            Approval::Auto => true,
+            Approval::Prompt => {
+                let display = Display::new();
+                display.print_change_set(&change_set);
+                display.ask_confirm("Do you want to continue?")
+            }
+            Approval::Never => false,
+        };
+        if !approved {
+            client.delete_change_set(change_set_id).await?;
+            if creating {
+                self.clean_up_empty_stack(client).await?;
+            }
+            return self
+                .result(client, false, changes, violations, start_time)
+                .await;
+        }
+
+        client.execute_change_set(change_set_id, false).await?;
+        client
+            .wait_until_stack_op_in_progress(&self.stack, self.pool_interval)
+            .await?;
+        self.result(client, true, changes, violations, start_time)
+            .await
+    }
+
+    async fn change_set_type(&self, client: &AwsClient) -> anyhow::Result<ChangeSetType> {
+        let Ok((status, reason)) = client
+            .wait_until_stack_op_in_progress(&self.stack, self.pool_interval)
+            .await
+        else {
+            return Ok(ChangeSetType::Create);
+        };
+        match status {
+            StackStatus::DeleteComplete | StackStatus::ReviewInProgress => {
+                Ok(ChangeSetType::Create)
+            }
+            StackStatus::CreateComplete
+            | StackStatus::ImportComplete
+            | StackStatus::UpdateComplete
+            | StackStatus::UpdateRollbackComplete => Ok(ChangeSetType::Update),
+            _ => bail!(
+                "Stack {} is in status {status:?} ({reason}), recover it with `pklformation up`",
+                self.stack
+            ),
+        }
+    }
+
+    async fn clean_up_empty_stack(&self, client: &AwsClient) -> anyhow::Result<()> {
+        if client.pending_change_set(&self.stack).await?.is_none() {
+            client.delete_stack(&self.stack).await?;
+        }
+        Ok(())
+    }
+
+    async fn result(
+        &self,
+        client: &AwsClient,
+        executed: bool,
+        changes: Vec<ResourceChange>,
+        violations: Vec<Violation>,
+        start_time: f64,
+    ) -> anyhow::Result<DeploymentResult> {
+        let Ok(stack) = client.describe_stack(&self.stack).await else {
+            return Ok(DeploymentResult {
+                stack_status: None,
+                executed,
+                changes,
+                violations,
+                outputs: Vec::new(),
+                events: Vec::new(),
+            });
+        };
+        let events = client
+            .describe_stack_events(&self.stack)
+            .await?
+            .into_iter()
+            .filter(|event| {
+                event
+                    .timestamp()
+                    .map(|t| t.as_secs_f64())
+                    .unwrap_or_default()
+                    > start_time
+            })
+            .collect();
+        Ok(DeploymentResult {
+            stack_status: stack.stack_status().cloned(),
+            executed,
+            changes,
+            violations,
+            outputs: stack.outputs().to_vec(),
+            events,
+        })
+    }
+}
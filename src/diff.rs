@@ -0,0 +1,82 @@
+use serde_json::Value;
+
+pub enum Difference {
+    Added {
+        path: String,
+        value: Value,
+    },
+    Removed {
+        path: String,
+        value: Value,
+    },
+    Changed {
+        path: String,
+        old: Value,
+        new: Value,
+    },
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+fn diff_at(path: String, old: &Value, new: &Value, differences: &mut Vec<Difference>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            for (key, old_value) in old {
+                match new.get(key) {
+                    Some(new_value) => {
+                        diff_at(child_path(&path, key), old_value, new_value, differences)
+                    }
+                    None => differences.push(Difference::Removed {
+                        path: child_path(&path, key),
+                        value: old_value.clone(),
+                    }),
+                }
+            }
+            for (key, new_value) in new {
+                if !old.contains_key(key) {
+                    differences.push(Difference::Added {
+                        path: child_path(&path, key),
+                        value: new_value.clone(),
+                    });
+                }
+            }
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            for index in 0..old.len().max(new.len()) {
+                let item_path = format!("{path}[{index}]");
+                match (old.get(index), new.get(index)) {
+                    (Some(old_value), Some(new_value)) => {
+                        diff_at(item_path, old_value, new_value, differences)
+                    }
+                    (Some(old_value), None) => differences.push(Difference::Removed {
+                        path: item_path,
+                        value: old_value.clone(),
+                    }),
+                    (None, Some(new_value)) => differences.push(Difference::Added {
+                        path: item_path,
+                        value: new_value.clone(),
+                    }),
+                    (None, None) => {}
+                }
+            }
+        }
+        _ if old != new => differences.push(Difference::Changed {
+            path,
+            old: old.clone(),
+            new: new.clone(),
+        }),
+        _ => {}
+    }
+}
+
+pub fn diff(old: &Value, new: &Value) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    diff_at(String::new(), old, new, &mut differences);
+    differences
+}
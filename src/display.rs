@@ -4,13 +4,41 @@ use aws_sdk_cloudformation::{
         list_stack_resources::ListStackResourcesOutput,
     },
     types::{
-        ChangeAction, ChangeSetStatus, Parameter, Replacement, RequiresRecreation, ResourceStatus,
-        Stack, StackEvent, StackStatus, StackSummary,
+        ChangeAction, ChangeSetStatus, ChangeSetSummary, Replacement, RequiresRecreation,
+        ResourceChange, ResourceLocation, ResourceStatus, Stack, StackDriftStatus, StackEvent,
+        StackRefactorAction, StackRefactorActionType, StackResource, StackResourceDriftStatus,
+        StackResourceSummary, StackStatus, StackSummary,
     },
 };
+use chrono::NaiveDate;
+use clap::{Args, ValueEnum};
 use colored::Colorize;
-use dialoguer::Confirm;
-use std::io::Write;
+use comfy_table::{presets, Attribute, Cell, ColumnConstraint, ContentArrangement, Table, Width};
+use dialoguer::{Confirm, FuzzySelect, Input, MultiSelect, Select};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    io::{IsTerminal, Write},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{
+    costs::ServiceCost,
+    diff::Difference,
+    drift::DriftReport,
+    history::HistoryEntry,
+    nested_stacks::NestedFailure,
+    partition,
+    project::StackConfig,
+    timings::{self, ResourceTiming},
+    validation::{Violation, MAX_RESOURCES, MAX_TEMPLATE_BODY_BYTES},
+    views::{
+        ChangeSetReviewView, ChangeSetView, ExportView, StackDescriptionView, StackEventView,
+        StackResourceDetailView, StackResourceView, StackSummaryView, StackTreeView, StackView,
+        TemplateStatsView, ViolationView,
+    },
+};
 
 const UNKNOWN_RESOURCE_TYPE: &str = "UNKNOW RESOURCE TYPE";
 const UNKNOWN_REASON: &str = "UNKNOW REASON";
@@ -30,7 +58,7 @@ impl std::fmt::Display for ChangeActionSimbol {
     }
 }
 
-enum TextColor {
+pub(crate) enum TextColor {
     Green,
     Yellow,
     Red,
@@ -146,6 +174,43 @@ impl TextColor {
             TextColor::Default => str.to_string(),
         }
     }
+
+    pub(crate) fn tui_color(&self) -> ratatui::style::Color {
+        match self {
+            TextColor::Green => ratatui::style::Color::Green,
+            TextColor::Yellow => ratatui::style::Color::Yellow,
+            TextColor::Red => ratatui::style::Color::Red,
+            TextColor::Purple => ratatui::style::Color::Magenta,
+            TextColor::Default => ratatui::style::Color::Reset,
+        }
+    }
+
+    fn table_color(&self) -> comfy_table::Color {
+        match self {
+            TextColor::Green => comfy_table::Color::Green,
+            TextColor::Yellow => comfy_table::Color::Yellow,
+            TextColor::Red => comfy_table::Color::Red,
+            TextColor::Purple => comfy_table::Color::Magenta,
+            TextColor::Default => comfy_table::Color::Reset,
+        }
+    }
+}
+
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+// https://docs.github.com/en/actions/using-workflows/workflow-commands-for-github-actions
+fn annotation_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn annotation_property(text: &str) -> String {
+    annotation_data(text)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
 }
 
 macro_rules! str_repeat {
@@ -189,23 +254,310 @@ macro_rules! pprintln {
     }};
 }
 
-pub struct Display {}
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ChangeGroupBy {
+    Type,
+    Action,
+}
+
+impl ChangeGroupBy {
+    fn key(&self, rc: &ResourceChange) -> String {
+        match self {
+            ChangeGroupBy::Type => ChangeSortBy::Type.key(rc),
+            ChangeGroupBy::Action => ChangeSortBy::Action.key(rc),
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ChangeSortBy {
+    LogicalId,
+    Type,
+    Action,
+}
+
+impl ChangeSortBy {
+    fn key(&self, rc: &ResourceChange) -> String {
+        match self {
+            ChangeSortBy::LogicalId => rc
+                .logical_resource_id()
+                .unwrap_or(UNKNOWN_RESOURCE_LOGICAL_ID)
+                .to_string(),
+            ChangeSortBy::Type => rc
+                .resource_type()
+                .unwrap_or(UNKNOWN_RESOURCE_TYPE)
+                .to_string(),
+            ChangeSortBy::Action => rc.action().map(|a| format!("{a:?}")).unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Args, Clone, Copy, Default)]
+pub struct ChangeSetLayout {
+    #[arg(long)]
+    pub group_by: Option<ChangeGroupBy>,
+    #[arg(long)]
+    pub sort: Option<ChangeSortBy>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StackColumn {
+    Name,
+    Status,
+    LastUpdated,
+    Drift,
+    Description,
+}
+
+impl StackColumn {
+    fn header(&self) -> &'static str {
+        match self {
+            StackColumn::Name => "NAME",
+            StackColumn::Status => "STATUS",
+            StackColumn::LastUpdated => "LAST UPDATED",
+            StackColumn::Drift => "DRIFT",
+            StackColumn::Description => "DESCRIPTION",
+        }
+    }
+
+    fn cell(&self, stack: &StackSummaryView) -> Cell {
+        match self {
+            StackColumn::Name => Cell::new(&stack.name),
+            StackColumn::Status => match &stack.status {
+                Some(status) => Cell::new(status.as_str())
+                    .fg(TextColor::from_stack_status(status).table_color()),
+                None => Cell::new(""),
+            },
+            StackColumn::LastUpdated => Cell::new(
+                stack
+                    .last_updated_time
+                    .as_deref()
+                    .or(stack.creation_time.as_deref())
+                    .unwrap_or_default(),
+            ),
+            StackColumn::Drift => Cell::new(
+                stack
+                    .drift_status
+                    .as_ref()
+                    .map(|drift| drift.as_str())
+                    .unwrap_or_default(),
+            ),
+            StackColumn::Description => Cell::new(stack.description.as_deref().unwrap_or_default()),
+        }
+    }
+}
+
+#[derive(Args, Clone, Default)]
+pub struct StackTableLayout {
+    /// Comma separated columns of the stack table, all of them by default
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub columns: Vec<StackColumn>,
+    /// Don't wrap the table to the width of the terminal
+    #[arg(long)]
+    pub wide: bool,
+    /// Group the nested stacks under their parent stack, as an indented tree
+    #[arg(long, conflicts_with = "columns")]
+    pub tree: bool,
+    /// Then describe every listed stack for its tags, outputs and drift status
+    #[arg(long)]
+    pub detailed: bool,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ChangeSetFormat {
+    #[default]
+    Text,
+    /// Collapsible table to post as a pull request comment
+    Markdown,
+    /// Workflow commands annotating the replacements, deletions and violations
+    GithubAnnotations,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    // Returns whether the output is colored, for the logs
+    pub fn apply(self) -> bool {
+        let enabled = match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                std::env::var_os("NO_COLOR").is_none_or(|no_color| no_color.is_empty())
+                    && std::io::stdout().is_terminal()
+            }
+        };
+        colored::control::set_override(enabled);
+        enabled
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+// Where the output goes instead of stdout, shared so the caller can read back what was written
+pub type Sink = Arc<Mutex<dyn Write + Send>>;
+
+// Sink of the displays without their own, like the pager of the whole run
+static DEFAULT_SINK: Mutex<Option<Sink>> = Mutex::new(None);
+
+pub fn set_default_sink(sink: Option<Sink>) {
+    *DEFAULT_SINK.lock().unwrap() = sink;
+}
+
+// A print reaches the sink in one piece: it is buffered and written under a single lock, so the
+// prints of concurrent tasks don't interleave
+struct SinkWriter {
+    sink: Sink,
+    buffer: Vec<u8>,
+}
+
+impl Write for SinkWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        let mut sink = self.sink.lock().unwrap();
+        sink.write_all(&self.buffer)?;
+        self.buffer.clear();
+        sink.flush()
+    }
+}
+
+impl Drop for SinkWriter {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[derive(Default)]
+pub struct Display {
+    change_set_layout: ChangeSetLayout,
+    change_set_format: ChangeSetFormat,
+    stack_table_layout: StackTableLayout,
+    output: OutputFormat,
+    sink: Option<Sink>,
+}
 impl Display {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            change_set_layout: ChangeSetLayout::default(),
+            change_set_format: ChangeSetFormat::default(),
+            stack_table_layout: StackTableLayout::default(),
+            output: OutputFormat::default(),
+            sink: None,
+        }
+    }
+
+    pub fn with_change_set_layout(change_set_layout: ChangeSetLayout) -> Self {
+        Self {
+            change_set_layout,
+            ..Self::new()
+        }
+    }
+
+    pub fn change_set_format(mut self, change_set_format: ChangeSetFormat) -> Self {
+        self.change_set_format = change_set_format;
+        self
+    }
+
+    pub fn stack_table_layout(mut self, stack_table_layout: StackTableLayout) -> Self {
+        self.stack_table_layout = stack_table_layout;
+        self
     }
 
+    pub fn output(mut self, output: OutputFormat) -> Self {
+        self.output = output;
+        self
+    }
+
+    #[cfg(test)]
+    pub fn sink(mut self, sink: Sink) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+
+    // Every print method writes through this
+    fn out(&self) -> Box<dyn Write> {
+        let sink = self
+            .sink
+            .clone()
+            .or_else(|| DEFAULT_SINK.lock().unwrap().clone());
+        match sink {
+            Some(sink) => Box::new(SinkWriter {
+                sink,
+                buffer: Vec::new(),
+            }),
+            None => Box::new(std::io::stdout().lock()),
+        }
+    }
+
+    fn print_json(&self, value: &impl Serialize) {
+        let mut lock = self.out();
+        let json = serde_json::to_string_pretty(value).expect("views serialize to JSON");
+        writeln!(lock, "{json}").unwrap();
+    }
+
+    // An interrupted prompt is a refusal
     pub fn ask_confirm(&self, msg: &str) -> bool {
         Confirm::new()
             .with_prompt(msg)
             .default(false)
             .interact()
-            .unwrap()
+            .unwrap_or(false)
+    }
+
+    pub fn ask_typed_confirm(&self, msg: &str, expected: &str) -> bool {
+        Input::<String>::new()
+            .with_prompt(msg)
+            .allow_empty(true)
+            .interact_text()
+            .is_ok_and(|answer| answer.trim() == expected)
+    }
+
+    pub fn ask_multi_select(&self, msg: &str, items: &[String]) -> Vec<usize> {
+        MultiSelect::new()
+            .with_prompt(msg)
+            .items(items)
+            .interact()
+            .unwrap_or_default()
+    }
+
+    pub fn ask_select(&self, msg: &str, items: &[&str]) -> Option<usize> {
+        Select::new()
+            .with_prompt(msg)
+            .items(items)
+            .default(0)
+            .interact()
+            .ok()
+    }
+
+    pub fn ask_fuzzy_select(&self, msg: &str, items: &[String]) -> Option<usize> {
+        FuzzySelect::new()
+            .with_prompt(msg)
+            .items(items)
+            .default(0)
+            .interact_opt()
+            .ok()
+            .flatten()
     }
 
     pub fn print_change_set(&self, change_set: &DescribeChangeSetOutput) {
-        let stdout = std::io::stdout();
-        let mut lock = stdout.lock();
+        if self.output == OutputFormat::Json {
+            return self.print_json(&ChangeSetView::from(change_set));
+        }
+        let mut lock = self.out();
 
         pprintln!(
             lock,
@@ -235,146 +587,353 @@ impl Display {
             }
         }
 
-        change_set
+        let mut resource_changes: Vec<_> = change_set
             .changes()
             .iter()
             .filter_map(|c| c.resource_change.as_ref())
-            .for_each(|rc| {
-                pprintln!(
-                    lock,
-                    "{} {} ({})",
-                    2,
-                    TextColor::from_change_action(rc.action().unwrap()),
-                    ChangeActionSimbol(rc.action().unwrap().clone()),
-                    rc.logical_resource_id
-                        .as_deref()
-                        .unwrap_or(UNKNOWN_RESOURCE_LOGICAL_ID),
-                    rc.resource_type.as_deref().unwrap_or(UNKNOWN_RESOURCE_TYPE),
-                );
-
-                pprintln!(
-                    lock,
-                    "Action: {:?}",
-                    4,
-                    TextColor::from_change_action(rc.action().unwrap()),
-                    rc.action().unwrap()
-                );
+            .collect();
+        if let Some(sort) = self.change_set_layout.sort {
+            resource_changes.sort_by_key(|rc| sort.key(rc));
+        }
 
-                if let Some(replacement) = rc.replacement() {
+        match self.change_set_layout.group_by {
+            Some(group_by) => {
+                resource_changes.sort_by_key(|rc| group_by.key(rc));
+                for group in resource_changes.chunk_by(|a, b| group_by.key(a) == group_by.key(b)) {
                     pprintln!(
                         lock,
-                        "Replacement: {replacement:?}",
-                        4,
-                        TextColor::from_replacement(replacement)
+                        "{} ({})",
+                        1,
+                        TextColor::Default,
+                        group_by.key(group[0]),
+                        group.len()
                     );
+                    for rc in group {
+                        self.print_resource_change(&mut lock, rc);
+                    }
+                }
+            }
+            None => {
+                for rc in resource_changes {
+                    self.print_resource_change(&mut lock, rc);
                 }
+            }
+        }
+    }
+
+    fn print_resource_change(&self, lock: &mut impl Write, rc: &ResourceChange) {
+        pprintln!(
+            lock,
+            "{} {} ({})",
+            2,
+            TextColor::from_change_action(rc.action().unwrap()),
+            ChangeActionSimbol(rc.action().unwrap().clone()),
+            rc.logical_resource_id
+                .as_deref()
+                .unwrap_or(UNKNOWN_RESOURCE_LOGICAL_ID),
+            rc.resource_type.as_deref().unwrap_or(UNKNOWN_RESOURCE_TYPE),
+        );
 
-                if let Some(change_res_id) = rc.change_set_id() {
+        pprintln!(
+            lock,
+            "Action: {:?}",
+            4,
+            TextColor::from_change_action(rc.action().unwrap()),
+            rc.action().unwrap()
+        );
+
+        if let Some(replacement) = rc.replacement() {
+            pprintln!(
+                lock,
+                "Replacement: {replacement:?}",
+                4,
+                TextColor::from_replacement(replacement)
+            );
+        }
+
+        if let Some(change_res_id) = rc.change_set_id() {
+            pprintln!(
+                lock,
+                "Physical Resource: {change_res_id}",
+                4,
+                TextColor::Default
+            );
+        }
+
+        if !rc.scope().is_empty() {
+            let scope = &rc
+                .scope()
+                .iter()
+                .map(|s| format!("{s:?}"))
+                .collect::<Vec<String>>()
+                .join(", ");
+            pprintln!(lock, "Change Scope: {scope}", 4, TextColor::Default);
+        }
+
+        if !rc.details().is_empty() {
+            pprintln!(lock, "Changed Properties", 4, TextColor::Default);
+            for detail in rc.details() {
+                if let Some(target) = detail.target() {
                     pprintln!(
                         lock,
-                        "Physical Resource: {change_res_id}",
-                        4,
-                        TextColor::Default
+                        "{} {}",
+                        6,
+                        TextColor::Default,
+                        target
+                            .attribute()
+                            .map(|a| format! {"{a:?}"})
+                            .unwrap_or_else(|| "".to_string()),
+                        target.name().unwrap_or_default()
                     );
+                    if let Some(requires_recreation) = target.requires_recreation() {
+                        pprintln!(
+                            lock,
+                            "{:?}",
+                            8,
+                            TextColor::from_requires_recreation(requires_recreation),
+                            requires_recreation
+                        )
+                    }
                 }
 
-                if !rc.scope().is_empty() {
-                    let scope = &rc
-                        .scope()
-                        .iter()
-                        .map(|s| format!("{s:?}"))
-                        .collect::<Vec<String>>()
-                        .join(", ");
-                    pprintln!(lock, "Change Scope: {scope}", 4, TextColor::Default);
+                if let Some(causing_eentity) = detail.causing_entity() {
+                    pprintln!(
+                        lock,
+                        "Causing entity: {causing_eentity}",
+                        8,
+                        TextColor::Default
+                    );
                 }
-
-                if !rc.details().is_empty() {
-                    pprintln!(lock, "Changed Properties", 4, TextColor::Default);
-                    for detail in rc.details() {
-                        if let Some(target) = detail.target() {
-                            pprintln!(
-                                lock,
-                                "{} {}",
-                                6,
-                                TextColor::Default,
-                                target
-                                    .attribute()
-                                    .map(|a| format! {"{a:?}"})
-                                    .unwrap_or_else(|| "".to_string()),
-                                target.name().unwrap_or_default()
-                            );
-                            if let Some(requires_recreation) = target.requires_recreation() {
-                                pprintln!(
-                                    lock,
-                                    "{:?}",
-                                    8,
-                                    TextColor::from_requires_recreation(requires_recreation),
-                                    requires_recreation
-                                )
-                            }
-                        }
-
-                        if let Some(causing_eentity) = detail.causing_entity() {
-                            pprintln!(
-                                lock,
-                                "Causing entity: {causing_eentity}",
-                                8,
-                                TextColor::Default
-                            );
-                        }
-                        if let Some(change_source) = detail.change_source() {
-                            pprintln!(
-                                lock,
-                                "Causing entity: {change_source:?}",
-                                8,
-                                TextColor::Default
-                            );
-                        }
-                    }
+                if let Some(change_source) = detail.change_source() {
+                    pprintln!(
+                        lock,
+                        "Causing entity: {change_source:?}",
+                        8,
+                        TextColor::Default
+                    );
                 }
-            })
+            }
+        }
     }
 
     pub fn print_stack_summaries(&self, stacks: &[StackSummary]) {
-        let stdout = std::io::stdout();
-        let mut lock = stdout.lock();
+        if self.stack_table_layout.tree {
+            return self.print_stack_tree(stacks);
+        }
+        let stacks: Vec<_> = stacks.iter().map(StackSummaryView::from).collect();
+        match self.output {
+            OutputFormat::Json => self.print_json(&stacks),
+            OutputFormat::Text => self.render_stack_summaries(&stacks),
+        }
+    }
+
+    fn print_stack_tree(&self, stacks: &[StackSummary]) {
+        let trees = StackTreeView::build(stacks);
+        if self.output == OutputFormat::Json {
+            return self.print_json(&trees);
+        }
+        let mut lock = self.out();
+        for tree in &trees {
+            Self::render_stack_tree(&mut lock, tree, "", None);
+        }
+    }
+
+    // last is None for the roots, which have no branch
+    fn render_stack_tree(
+        lock: &mut impl Write,
+        tree: &StackTreeView,
+        prefix: &str,
+        last: Option<bool>,
+    ) {
+        let branch = match last {
+            None => "",
+            Some(true) => "└── ",
+            Some(false) => "├── ",
+        };
+        let status = match &tree.stack.status {
+            Some(status) => TextColor::from_stack_status(status).colorize(status.as_str()),
+            None => String::new(),
+        };
+        writeln!(lock, "{prefix}{branch}{} {status}", tree.stack.name).unwrap();
+        let prefix = match last {
+            None => prefix.to_string(),
+            Some(true) => format!("{prefix}    "),
+            Some(false) => format!("{prefix}│   "),
+        };
+        for (index, nested) in tree.nested.iter().enumerate() {
+            Self::render_stack_tree(lock, nested, &prefix, Some(index + 1 == tree.nested.len()));
+        }
+    }
+
+    // Pages after the first one reuse the widths of the columns of the first one, so the
+    // rows of a streamed listing stay aligned
+    pub fn print_stack_summaries_page(&self, stacks: &[StackSummary], widths: &mut Vec<u16>) {
+        let stacks: Vec<_> = stacks.iter().map(StackSummaryView::from).collect();
+        let table = self.stack_summaries_table(&stacks, widths);
+        if widths.is_empty() {
+            *widths = table.column_max_content_widths();
+        }
+
+        let mut lock = self.out();
+        writeln!(lock, "{table}").unwrap();
+    }
+
+    fn render_stack_summaries(&self, stacks: &[StackSummaryView]) {
+        let table = self.stack_summaries_table(stacks, &[]);
+
+        let mut lock = self.out();
+        writeln!(lock, "{table}").unwrap();
+    }
+
+    fn stack_summaries_table(&self, stacks: &[StackSummaryView], widths: &[u16]) -> Table {
+        let columns = match self.stack_table_layout.columns.as_slice() {
+            [] => StackColumn::value_variants(),
+            columns => columns,
+        };
+        let mut table = Table::new();
+        table.load_preset(presets::NOTHING).set_content_arrangement(
+            if self.stack_table_layout.wide {
+                ContentArrangement::Disabled
+            } else {
+                ContentArrangement::Dynamic
+            },
+        );
+        if widths.is_empty() {
+            table.set_header(columns.iter().map(StackColumn::header));
+        }
         for stack in stacks {
+            table.add_row(columns.iter().map(|column| column.cell(stack)));
+        }
+        // Constraints apply to the existing columns, and their widths include the padding
+        if !widths.is_empty() {
+            table.set_constraints(
+                widths
+                    .iter()
+                    .map(|width| ColumnConstraint::LowerBoundary(Width::Fixed(width + 2))),
+            );
+        }
+        if colored::control::SHOULD_COLORIZE.should_colorize() {
+            table.enforce_styling();
+        } else {
+            table.force_no_tty();
+        }
+        table
+    }
+
+    pub fn print_change_set_summaries(&self, change_sets: &[ChangeSetSummary]) {
+        let mut lock = self.out();
+        for change_set in change_sets {
             pprintln!(
                 lock,
-                "Stack name: {}",
+                "Change set: {}",
                 0,
                 TextColor::Default,
-                stack.stack_name().unwrap_or_default(),
+                change_set.change_set_name().unwrap_or_default(),
             );
-
-            if let Some(status) = stack.stack_status() {
+            if let Some(status) = change_set.status() {
                 pprintln!(
                     lock,
                     "Status: {status:?}",
                     1,
-                    TextColor::from_stack_status(status),
-                )
+                    TextColor::from_change_set_status(status),
+                );
+            }
+            if let Some(execution_status) = change_set.execution_status() {
+                pprintln!(
+                    lock,
+                    "Execution status: {execution_status:?}",
+                    1,
+                    TextColor::Default
+                );
+            }
+            if let Some(creation_time) = change_set.creation_time() {
+                pprintln!(
+                    lock,
+                    "Creation time: {creation_time}",
+                    1,
+                    TextColor::Default
+                );
+            }
+            if let Some(description) = change_set.description() {
+                pprintln!(lock, "Description: {description}", 1, TextColor::Default);
             }
         }
     }
 
     pub fn print_stack(&self, stack: &Stack) {
-        let stdout = std::io::stdout();
-        let mut lock = stdout.lock();
-        pprintln!(
-            lock,
-            "Stack name: {}",
-            0,
-            TextColor::Default,
-            stack.stack_name().unwrap_or_default()
-        );
-        if let Some(parent) = stack.parent_id() {
+        let stack = StackView::from(stack);
+        match self.output {
+            OutputFormat::Json => self.print_json(&stack),
+            OutputFormat::Text => self.render_stack(&stack),
+        }
+    }
+
+    // Follows the summaries of list, JSON only has the details since they include the summaries
+    pub fn print_stack_details(&self, stacks: &[Stack]) {
+        let stacks: Vec<_> = stacks.iter().map(StackView::from).collect();
+        match self.output {
+            OutputFormat::Json => self.print_json(&stacks),
+            OutputFormat::Text => self.render_stack_details(&stacks),
+        }
+    }
+
+    fn render_stack_details(&self, stacks: &[StackView]) {
+        let mut lock = self.out();
+        for stack in stacks {
+            writeln!(lock).unwrap();
+            pprintln!(lock, "{}", 0, TextColor::Default, stack.name);
+            if let Some(drift_status) = &stack.drift_status {
+                pprintln!(
+                    lock,
+                    "Drift status: {}",
+                    2,
+                    Self::drift_color(drift_status),
+                    drift_status.as_str()
+                );
+            }
+            if !stack.tags.is_empty() {
+                pprintln!(lock, "Tags:", 2, TextColor::Default);
+                for (key, value) in &stack.tags {
+                    pprintln!(lock, "{key}: {value}", 4, TextColor::Default);
+                }
+            }
+            if !stack.outputs.is_empty() {
+                pprintln!(lock, "Outputs:", 2, TextColor::Default);
+                for output in &stack.outputs {
+                    pprintln!(
+                        lock,
+                        "{}: {}",
+                        4,
+                        TextColor::Default,
+                        output.key,
+                        output.value.as_deref().unwrap_or_default()
+                    );
+                }
+            }
+        }
+    }
+
+    fn drift_color(drift_status: &StackDriftStatus) -> TextColor {
+        match drift_status {
+            StackDriftStatus::InSync => TextColor::Green,
+            StackDriftStatus::Drifted => TextColor::Yellow,
+            _ => TextColor::Default,
+        }
+    }
+
+    fn render_stack(&self, stack: &StackView) {
+        let mut lock = self.out();
+        pprintln!(lock, "Stack name: {}", 0, TextColor::Default, stack.name);
+        if let Some(parent) = &stack.parent_id {
             pprintln!(lock, "Parent: {parent}", 0, TextColor::Default);
         }
-        if let Some(description) = stack.description() {
+        if let Some(console_url) = &stack.console_url {
+            pprintln!(lock, "Console: {console_url}", 0, TextColor::Default);
+        }
+        if let Some(description) = &stack.description {
             pprintln!(lock, "Description: {description}", 0, TextColor::Default);
         }
-        if let Some(creation_date) = stack.creation_time() {
+        if let Some(creation_date) = &stack.creation_time {
             pprintln!(
                 lock,
                 "Creation time: {creation_date}",
@@ -382,7 +941,7 @@ impl Display {
                 TextColor::Default
             );
         }
-        if let Some(last_updated_time) = stack.last_updated_time() {
+        if let Some(last_updated_time) = &stack.last_updated_time {
             pprintln!(
                 lock,
                 "Last update time: {last_updated_time}",
@@ -390,49 +949,139 @@ impl Display {
                 TextColor::Default
             );
         }
-        if let Some(stack_status) = stack.stack_status() {
+        if let Some(protected) = stack.termination_protection {
+            pprintln!(
+                lock,
+                "Termination protection: {}",
+                0,
+                TextColor::Default,
+                if protected { "enabled" } else { "disabled" }
+            );
+        }
+        if let Some(stack_status) = &stack.status {
             let color = TextColor::from_stack_status(stack_status);
             pprintln!(lock, "Status: {stack_status:?}", 0, color);
-            if let Some(stack_status_reason) = stack.stack_status_reason() {
+            if let Some(stack_status_reason) = &stack.status_reason {
                 pprintln!(lock, "Status reason: {stack_status_reason}", 0, color);
             }
         }
+        if let Some(drift_status) = &stack.drift_status {
+            let color = Self::drift_color(drift_status);
+            pprintln!(lock, "Drift status: {}", 0, color, drift_status.as_str());
+            if let Some(last_check_time) = &stack.drift_last_check_time {
+                pprintln!(
+                    lock,
+                    "Last drift check: {last_check_time}",
+                    0,
+                    TextColor::Default
+                );
+            }
+        }
 
-        if !stack.parameters().is_empty() {
+        if !stack.parameters.is_empty() {
             pprintln!(lock, "Parameters:", 0, TextColor::Default);
-            for Parameter {
-                parameter_key: key,
-                parameter_value: value,
-                ..
-            } in stack.parameters()
-            {
-                let key = key.clone().unwrap_or_default();
-                let value = value.clone().unwrap_or_default();
+            for (key, value) in &stack.parameters {
                 pprintln!(lock, "{key}:{value}", 0, TextColor::Default);
             }
         }
-    }
-
-    pub fn print_stack_resources(&self, resources: &ListStackResourcesOutput) {
-        let stdout = std::io::stdout();
-        let mut lock = stdout.lock();
-        pprintln!(lock, "Stack resources:", 0, TextColor::Default);
-        for resource in resources.stack_resource_summaries() {
-            if let Some(logical_id) = resource.physical_resource_id() {
+        if !stack.outputs.is_empty() {
+            pprintln!(lock, "Outputs:", 0, TextColor::Default);
+            for output in &stack.outputs {
                 pprintln!(
                     lock,
-                    "{logical_id} ({:?})",
-                    4,
+                    "{}: {}",
+                    2,
                     TextColor::Default,
-                    resource.resource_type().unwrap_or_default()
+                    output.key,
+                    output.value.as_deref().unwrap_or_default()
                 );
+                if let Some(description) = &output.description {
+                    pprintln!(lock, "Description: {description}", 4, TextColor::Default);
+                }
+                if let Some(export_name) = &output.export_name {
+                    pprintln!(lock, "Export: {export_name}", 4, TextColor::Default);
+                }
+            }
+        }
+        if !stack.tags.is_empty() {
+            pprintln!(lock, "Tags:", 0, TextColor::Default);
+            for (key, value) in &stack.tags {
+                pprintln!(lock, "{key}: {value}", 2, TextColor::Default);
+            }
+        }
+        if !stack.capabilities.is_empty() {
+            pprintln!(lock, "Capabilities:", 0, TextColor::Default);
+            for capability in &stack.capabilities {
+                pprintln!(lock, "{capability}", 2, TextColor::Default);
+            }
+        }
+        if let Some(rollback) = &stack.rollback_configuration {
+            pprintln!(lock, "Rollback configuration:", 0, TextColor::Default);
+            if let Some(minutes) = rollback.monitoring_time_in_minutes {
+                pprintln!(
+                    lock,
+                    "Monitoring time: {minutes} min",
+                    2,
+                    TextColor::Default
+                );
+            }
+            for trigger in &rollback.triggers {
+                pprintln!(
+                    lock,
+                    "Trigger: {} ({})",
+                    2,
+                    TextColor::Default,
+                    trigger.arn,
+                    trigger.r#type
+                );
+            }
+        }
+        if !stack.notification_arns.is_empty() {
+            pprintln!(lock, "Notification ARNs:", 0, TextColor::Default);
+            for arn in &stack.notification_arns {
+                pprintln!(lock, "{arn}", 2, TextColor::Default);
             }
+        }
+    }
 
-            if let Some(physical_id) = resource.physical_resource_id() {
+    pub fn print_stack_resources(&self, stack_id: &str, resources: &ListStackResourcesOutput) {
+        let resources = Self::stack_resource_views(stack_id, resources);
+        match self.output {
+            OutputFormat::Json => self.print_json(&resources),
+            OutputFormat::Text => self.render_stack_resources(&resources),
+        }
+    }
+
+    fn stack_resource_views(
+        stack_id: &str,
+        resources: &ListStackResourcesOutput,
+    ) -> Vec<StackResourceView> {
+        resources
+            .stack_resource_summaries()
+            .iter()
+            .map(|resource| StackResourceView::new(resource, Some(stack_id)))
+            .collect()
+    }
+
+    fn render_stack_resources(&self, resources: &[StackResourceView]) {
+        let mut lock = self.out();
+        pprintln!(lock, "Stack resources:", 0, TextColor::Default);
+        for resource in resources {
+            if let Some(logical_id) = &resource.physical_id {
+                pprintln!(
+                    lock,
+                    "{logical_id} ({:?})",
+                    4,
+                    TextColor::Default,
+                    resource.resource_type.as_deref().unwrap_or_default()
+                );
+            }
+
+            if let Some(physical_id) = &resource.physical_id {
                 pprintln!(lock, "Physical ID: {physical_id}", 6, TextColor::Default);
             }
 
-            if let Some(last_updated_timestamp) = resource.last_updated_timestamp() {
+            if let Some(last_updated_timestamp) = &resource.last_updated_time {
                 pprintln!(
                     lock,
                     "Last updated timestamp: {last_updated_timestamp}",
@@ -441,7 +1090,7 @@ impl Display {
                 );
             }
 
-            if let Some(resource_status) = resource.resource_status() {
+            if let Some(resource_status) = &resource.status {
                 pprintln!(
                     lock,
                     "Status: {resource_status:?}",
@@ -449,12 +1098,548 @@ impl Display {
                     TextColor::from_resource_status(resource_status)
                 );
             }
+
+            if let Some(console_url) = &resource.console_url {
+                pprintln!(lock, "Console: {console_url}", 6, TextColor::Default);
+            }
+        }
+    }
+
+    pub fn print_stack_resource_detail(&self, resource: &StackResourceDetailView) {
+        if self.output == OutputFormat::Json {
+            return self.print_json(resource);
+        }
+        let mut lock = self.out();
+        pprintln!(
+            lock,
+            "{} ({})",
+            0,
+            TextColor::Default,
+            resource
+                .logical_id
+                .as_deref()
+                .unwrap_or(UNKNOWN_RESOURCE_LOGICAL_ID),
+            resource
+                .resource_type
+                .as_deref()
+                .unwrap_or(UNKNOWN_RESOURCE_TYPE)
+        );
+        let fields = [
+            ("Stack", resource.stack_name.as_deref()),
+            ("Physical ID", resource.physical_id.as_deref()),
+            ("Description", resource.description.as_deref()),
+            (
+                "Last updated timestamp",
+                resource.last_updated_time.as_deref(),
+            ),
+        ];
+        for (label, value) in fields {
+            if let Some(value) = value {
+                pprintln!(lock, "{label}: {value}", 2, TextColor::Default);
+            }
+        }
+        if let Some(resource_status) = &resource.status {
+            let color = TextColor::from_resource_status(resource_status);
+            pprintln!(lock, "Status: {resource_status:?}", 2, color);
+            if let Some(status_reason) = &resource.status_reason {
+                pprintln!(lock, "Status reason: {status_reason}", 2, color);
+            }
+        }
+        if let Some(drift_status) = &resource.drift_status {
+            let color = match drift_status {
+                StackResourceDriftStatus::InSync => TextColor::Green,
+                StackResourceDriftStatus::Modified | StackResourceDriftStatus::Deleted => {
+                    TextColor::Yellow
+                }
+                _ => TextColor::Default,
+            };
+            pprintln!(lock, "Drift status: {}", 2, color, drift_status.as_str());
+            if let Some(last_check_time) = &resource.drift_last_check_time {
+                pprintln!(
+                    lock,
+                    "Last drift check: {last_check_time}",
+                    2,
+                    TextColor::Default
+                );
+            }
+        }
+        if let Some(console_url) = &resource.console_url {
+            pprintln!(lock, "Console: {console_url}", 2, TextColor::Default);
+        }
+        let documents = [
+            ("Metadata", &resource.metadata),
+            ("Properties", &resource.properties),
+        ];
+        for (label, document) in documents {
+            if let Some(document) = document {
+                let json = serde_json::to_string_pretty(document).expect("JSON values serialize");
+                pprintln!(lock, "{label}:", 2, TextColor::Default);
+                for line in json.lines() {
+                    pprintln!(lock, "{line}", 4, TextColor::Default);
+                }
+            }
+        }
+    }
+
+    // A single JSON document for the stack and its resources, they can't be read otherwise
+    pub fn print_stack_description(
+        &self,
+        stack: &Stack,
+        resources: Option<&ListStackResourcesOutput>,
+    ) {
+        match self.output {
+            OutputFormat::Json => self.print_json(&StackDescriptionView {
+                stack: StackView::from(stack),
+                resources: stack
+                    .stack_id()
+                    .zip(resources)
+                    .map(|(stack_id, resources)| Self::stack_resource_views(stack_id, resources))
+                    .unwrap_or_default(),
+            }),
+            OutputFormat::Text => {
+                self.print_stack(stack);
+                if let Some((stack_id, resources)) = stack.stack_id().zip(resources) {
+                    self.print_stack_resources(stack_id, resources);
+                }
+            }
+        }
+    }
+
+    pub fn print_deployment_summary(
+        &self,
+        change_set: &DescribeChangeSetOutput,
+        duration: Duration,
+    ) {
+        let (mut added, mut changed, mut destroyed, mut replaced) = (0, 0, 0, 0);
+        for rc in change_set
+            .changes()
+            .iter()
+            .filter_map(|change| change.resource_change())
+        {
+            match (rc.action(), rc.replacement()) {
+                (Some(ChangeAction::Add | ChangeAction::Import), _) => added += 1,
+                (Some(ChangeAction::Remove), _) => destroyed += 1,
+                (_, Some(Replacement::True)) => replaced += 1,
+                _ => changed += 1,
+            }
+        }
+        let mut lock = self.out();
+        pprintln!(
+            lock,
+            "{added} added, {changed} changed, {destroyed} destroyed, {replaced} replaced, took {}",
+            0,
+            TextColor::Green,
+            timings::format_duration(duration)
+        );
+    }
+
+    pub fn print_history(&self, entries: &[HistoryEntry]) {
+        if self.output == OutputFormat::Json {
+            return self.print_json(&entries);
+        }
+        // Only revealed entries have their values
+        let revealed = entries.iter().any(|entry| entry.outputs.is_some());
+        let mut header = vec![
+            "STARTED",
+            "OPERATION",
+            "STACK",
+            "OUTCOME",
+            "DURATION",
+            "CHANGE SET",
+            "CALLER",
+        ];
+        if revealed {
+            header.extend(["PARAMETERS", "OUTPUTS"]);
+        }
+        let mut table = Table::new();
+        table.load_preset(presets::NOTHING).set_header(header);
+        let values = |values: Option<&BTreeMap<String, String>>| {
+            values
+                .into_iter()
+                .flatten()
+                .map(|(key, value)| format!("{key}={value}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        for entry in entries {
+            let mut row = vec![
+                Cell::new(&entry.started_at),
+                Cell::new(&entry.operation),
+                Cell::new(&entry.stack),
+                Cell::new(&entry.outcome),
+                Cell::new(timings::format_duration(Duration::from_secs(
+                    entry.duration_seconds,
+                ))),
+                Cell::new(entry.change_set_id.as_deref().unwrap_or("-")),
+                Cell::new(entry.caller.as_deref().unwrap_or("-")),
+            ];
+            if revealed {
+                row.push(Cell::new(values(
+                    entry.outputs.is_some().then_some(&entry.parameters),
+                )));
+                row.push(Cell::new(values(entry.outputs.as_ref())));
+            }
+            table.add_row(row);
+        }
+        if !colored::control::SHOULD_COLORIZE.should_colorize() {
+            table.force_no_tty();
+        }
+
+        let mut lock = self.out();
+        writeln!(lock, "{table}").unwrap();
+    }
+
+    pub fn print_stack_refactor_actions(&self, actions: &[StackRefactorAction]) {
+        let mut table = Table::new();
+        table.load_preset(presets::NOTHING).set_header([
+            "ACTION",
+            "ENTITY",
+            "RESOURCE",
+            "DETECTION",
+            "DESCRIPTION",
+        ]);
+        let location = |location: Option<&ResourceLocation>| {
+            location
+                .map(|location| {
+                    format!(
+                        "{}/{}",
+                        location.stack_name().unwrap_or_default(),
+                        location.logical_resource_id().unwrap_or_default()
+                    )
+                })
+                .unwrap_or_default()
+        };
+        for action in actions {
+            let resource = match action.resource_mapping() {
+                Some(mapping) => format!(
+                    "{} -> {}",
+                    location(mapping.source()),
+                    location(mapping.destination())
+                ),
+                None => action
+                    .physical_resource_id()
+                    .or(action.resource_identifier())
+                    .unwrap_or_default()
+                    .to_string(),
+            };
+            let action_type = action.action().map(|a| a.as_str()).unwrap_or_default();
+            table.add_row([
+                Cell::new(action_type).fg(match action.action() {
+                    Some(StackRefactorActionType::Create) => TextColor::Green.table_color(),
+                    _ => TextColor::Yellow.table_color(),
+                }),
+                Cell::new(action.entity().map(|e| e.as_str()).unwrap_or_default()),
+                Cell::new(resource),
+                Cell::new(action.detection().map(|d| d.as_str()).unwrap_or_default()),
+                Cell::new(action.description().unwrap_or_default()),
+            ]);
+        }
+        if !colored::control::SHOULD_COLORIZE.should_colorize() {
+            table.force_no_tty();
+        }
+
+        let mut lock = self.out();
+        writeln!(lock, "{table}").unwrap();
+    }
+
+    pub fn print_costs(
+        &self,
+        stack: &str,
+        start: NaiveDate,
+        end: NaiveDate,
+        costs: &[ServiceCost],
+    ) {
+        let total: f64 = costs.iter().map(|cost| cost.amount).sum();
+        let unit = costs.first().map_or("USD", |cost| cost.unit.as_str());
+        if self.output == OutputFormat::Json {
+            return self.print_json(&serde_json::json!({
+                "stack": stack,
+                "start": start.to_string(),
+                "end": end.to_string(),
+                "total": total,
+                "unit": unit,
+                "services": costs,
+            }));
+        }
+        let mut lock = self.out();
+        pprintln!(
+            lock,
+            "Cost of stack {} from {} to {}",
+            0,
+            TextColor::Default,
+            stack,
+            start,
+            end
+        );
+        let mut table = Table::new();
+        table
+            .load_preset(presets::NOTHING)
+            .set_header(["SERVICE", "COST"]);
+        for cost in costs {
+            table.add_row([
+                Cell::new(&cost.service),
+                Cell::new(format!("{:.2} {}", cost.amount, cost.unit)),
+            ]);
+        }
+        table.add_row([
+            Cell::new("Total").add_attribute(Attribute::Bold),
+            Cell::new(format!("{total:.2} {unit}")).add_attribute(Attribute::Bold),
+        ]);
+        if !colored::control::SHOULD_COLORIZE.should_colorize() {
+            table.force_no_tty();
+        }
+        writeln!(lock, "{table}").unwrap();
+    }
+
+    pub fn print_template_stats(&self, stats: &TemplateStatsView) {
+        if self.output == OutputFormat::Json {
+            return self.print_json(stats);
+        }
+        let mut lock = self.out();
+        let over = |value: usize, limit: usize| {
+            if value > limit {
+                TextColor::Red
+            } else {
+                TextColor::Default
+            }
+        };
+        pprintln!(
+            lock,
+            "Resources: {} (limit {MAX_RESOURCES})",
+            0,
+            over(stats.resources, MAX_RESOURCES),
+            stats.resources
+        );
+        for (label, value) in [
+            ("Nested stacks", stats.nested_stacks),
+            ("Parameters", stats.parameters),
+            ("Conditions", stats.conditions),
+            ("Mappings", stats.mappings),
+            ("Outputs", stats.outputs),
+        ] {
+            pprintln!(lock, "{label}: {value}", 0, TextColor::Default);
+        }
+        pprintln!(
+            lock,
+            "Size: {} bytes, {} minified (inline limit {MAX_TEMPLATE_BODY_BYTES})",
+            0,
+            over(stats.minified_size_bytes, MAX_TEMPLATE_BODY_BYTES),
+            stats.size_bytes,
+            stats.minified_size_bytes
+        );
+        if stats.resources_by_type.is_empty() {
+            return;
+        }
+        let mut by_count: Vec<_> = stats.resources_by_type.iter().collect();
+        by_count.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let mut table = Table::new();
+        table
+            .load_preset(presets::NOTHING)
+            .set_header(["TYPE", "COUNT"]);
+        for (resource_type, count) in by_count {
+            table.add_row([Cell::new(resource_type), Cell::new(count)]);
+        }
+        if !colored::control::SHOULD_COLORIZE.should_colorize() {
+            table.force_no_tty();
+        }
+        writeln!(lock, "{table}").unwrap();
+    }
+
+    pub fn print_drift_reports(&self, reports: &[DriftReport]) {
+        if self.output == OutputFormat::Json {
+            return self.print_json(&reports);
+        }
+        let mut table = Table::new();
+        table
+            .load_preset(presets::NOTHING)
+            .set_header(["STACK", "DRIFT", "RESOURCES"]);
+        for report in reports {
+            let status = match (&report.status, &report.error) {
+                (_, Some(error)) => Cell::new(error).fg(TextColor::Red.table_color()),
+                (Some(status), None) if report.is_drifted() => {
+                    Cell::new(status).fg(TextColor::Yellow.table_color())
+                }
+                (Some(status), None) => Cell::new(status).fg(TextColor::Green.table_color()),
+                (None, None) => Cell::new("-"),
+            };
+            let resources: Vec<_> = report
+                .drifted
+                .iter()
+                .map(|resource| {
+                    format!(
+                        "{} ({}): {}",
+                        resource.logical_id, resource.resource_type, resource.status
+                    )
+                })
+                .collect();
+            table.add_row([
+                Cell::new(&report.stack),
+                status,
+                Cell::new(resources.join("\n")),
+            ]);
+        }
+        if !colored::control::SHOULD_COLORIZE.should_colorize() {
+            table.force_no_tty();
+        }
+
+        let mut lock = self.out();
+        writeln!(lock, "{table}").unwrap();
+    }
+
+    pub fn print_exports(&self, exports: &[ExportView]) {
+        if self.output == OutputFormat::Json {
+            return self.print_json(&exports);
+        }
+        let show_importers = exports.iter().any(|export| export.importers.is_some());
+        let mut table = Table::new();
+        table.load_preset(presets::NOTHING);
+        if show_importers {
+            table.set_header(["NAME", "VALUE", "STACK", "IMPORTERS"]);
+        } else {
+            table.set_header(["NAME", "VALUE", "STACK"]);
+        }
+        for export in exports {
+            let mut row = vec![
+                Cell::new(&export.name),
+                Cell::new(export.value.as_deref().unwrap_or_default()),
+                Cell::new(export.exporting_stack.as_deref().unwrap_or_default()),
+            ];
+            if let Some(importers) = &export.importers {
+                row.push(Cell::new(if importers.is_empty() {
+                    "-".to_string()
+                } else {
+                    importers.join(", ")
+                }));
+            }
+            table.add_row(row);
+        }
+        if !colored::control::SHOULD_COLORIZE.should_colorize() {
+            table.force_no_tty();
+        }
+
+        let mut lock = self.out();
+        writeln!(lock, "{table}").unwrap();
+    }
+
+    pub fn print_resource_timings(&self, resource_timings: &[ResourceTiming]) {
+        if resource_timings.is_empty() {
+            return;
+        }
+        let mut table = Table::new();
+        table
+            .load_preset(presets::NOTHING)
+            .set_header(["RESOURCE", "TYPE", "STATUS", "DURATION"]);
+        for timing in resource_timings {
+            table.add_row([
+                Cell::new(&timing.logical_id),
+                Cell::new(&timing.resource_type),
+                Cell::new(timing.status.as_str())
+                    .fg(TextColor::from_resource_status(&timing.status).table_color()),
+                Cell::new(timings::format_duration(timing.duration)),
+            ]);
+        }
+        if !colored::control::SHOULD_COLORIZE.should_colorize() {
+            table.force_no_tty();
+        }
+
+        let mut lock = self.out();
+        pprintln!(lock, "Slowest resources:", 0, TextColor::Default);
+        writeln!(lock, "{table}").unwrap();
+    }
+
+    pub fn print_stack_events(&self, events: &[StackEvent]) {
+        let mut lock = self.out();
+        pprintln!(lock, "Latest events:", 0, TextColor::Default);
+        for event in events {
+            let color = event
+                .resource_status()
+                .map(TextColor::from_resource_status)
+                .unwrap_or(TextColor::Default);
+            pprintln!(
+                lock,
+                "{} {} {:?}",
+                2,
+                color,
+                event.timestamp().map(|t| t.to_string()).unwrap_or_default(),
+                event
+                    .logical_resource_id()
+                    .unwrap_or(UNKNOWN_RESOURCE_LOGICAL_ID),
+                event
+                    .resource_status()
+                    .map(|s| s.as_str())
+                    .unwrap_or_default()
+            );
+            if let Some(reason) = event.resource_status_reason() {
+                pprintln!(lock, "{reason}", 4, color);
+            }
+        }
+    }
+
+    // Only terminals are cleared, piped output keeps every refresh
+    pub fn print_filtered_stack_events(&self, events: &[StackEvent]) {
+        let events: Vec<_> = events.iter().map(StackEventView::from).collect();
+        if self.output == OutputFormat::Json {
+            return self.print_json(&events);
+        }
+        let mut table = Table::new();
+        table.load_preset(presets::NOTHING).set_header([
+            "TIMESTAMP",
+            "RESOURCE",
+            "TYPE",
+            "STATUS",
+            "REASON",
+        ]);
+        for event in &events {
+            let status = Cell::new(
+                event
+                    .status
+                    .as_ref()
+                    .map(|status| status.as_str())
+                    .unwrap_or_default(),
+            );
+            table.add_row([
+                Cell::new(event.timestamp.as_deref().unwrap_or_default()),
+                Cell::new(
+                    event
+                        .logical_id
+                        .as_deref()
+                        .unwrap_or(UNKNOWN_RESOURCE_LOGICAL_ID),
+                ),
+                Cell::new(
+                    event
+                        .resource_type
+                        .as_deref()
+                        .unwrap_or(UNKNOWN_RESOURCE_TYPE),
+                ),
+                match &event.status {
+                    Some(resource_status) => {
+                        status.fg(TextColor::from_resource_status(resource_status).table_color())
+                    }
+                    None => status,
+                },
+                Cell::new(event.status_reason.as_deref().unwrap_or_default()),
+            ]);
+        }
+        if !colored::control::SHOULD_COLORIZE.should_colorize() {
+            table.force_no_tty();
+        }
+
+        let mut lock = self.out();
+        writeln!(lock, "{table}").unwrap();
+    }
+
+    pub fn clear_screen(&self) {
+        if self.sink.is_none()
+            && DEFAULT_SINK.lock().unwrap().is_none()
+            && std::io::stdout().is_terminal()
+        {
+            let mut lock = self.out();
+            write!(lock, "\x1B[2J\x1B[H").unwrap();
         }
     }
 
     pub fn print_resources_errors(&self, events: impl Iterator<Item = StackEvent>) {
-        let stdout = std::io::stdout();
-        let mut lock = stdout.lock();
+        let mut lock = self.out();
         events
             .filter(|p| {
                 matches!(
@@ -487,6 +1672,477 @@ impl Display {
                     TextColor::Red,
                     error.resource_properties().unwrap_or(""),
                 );
+                if let Some(console_url) = error
+                    .stack_id()
+                    .zip(error.logical_resource_id())
+                    .and_then(|(stack_id, logical_id)| {
+                        partition::events_console_url(stack_id, logical_id)
+                    })
+                {
+                    pprintln!(lock, "console: {console_url}", 0, TextColor::Red);
+                }
             });
     }
+
+    pub fn print_nested_failures(&self, failures: &[NestedFailure]) {
+        let mut lock = self.out();
+        for failure in failures {
+            pprintln!(
+                lock,
+                "Root cause in nested stack {}:",
+                0,
+                TextColor::Red,
+                failure.path.join("/")
+            );
+            pprintln!(
+                lock,
+                "{}: {}",
+                2,
+                TextColor::Red,
+                failure
+                    .event
+                    .resource_type()
+                    .unwrap_or(UNKNOWN_RESOURCE_TYPE),
+                failure
+                    .event
+                    .logical_resource_id()
+                    .unwrap_or(UNKNOWN_RESOURCE_LOGICAL_ID)
+            );
+            pprintln!(
+                lock,
+                "reason: {}",
+                2,
+                TextColor::Red,
+                failure
+                    .event
+                    .resource_status_reason()
+                    .unwrap_or(UNKNOWN_REASON)
+            );
+            if let Some(console_url) = failure
+                .event
+                .stack_id()
+                .zip(failure.event.logical_resource_id())
+                .and_then(|(stack_id, logical_id)| {
+                    partition::events_console_url(stack_id, logical_id)
+                })
+            {
+                pprintln!(lock, "console: {console_url}", 2, TextColor::Red);
+            }
+        }
+    }
+
+    pub fn print_change_set_review(
+        &self,
+        change_set: &DescribeChangeSetOutput,
+        violations: &[Violation],
+    ) {
+        let review = ChangeSetReviewView {
+            change_set: ChangeSetView::from(change_set),
+            violations: violations.iter().map(ViolationView::from).collect(),
+        };
+        match (self.output, self.change_set_format) {
+            (OutputFormat::Json, _) => self.print_json(&review),
+            (OutputFormat::Text, ChangeSetFormat::Text) => {
+                self.print_change_set(change_set);
+                self.print_violations(violations);
+            }
+            (OutputFormat::Text, ChangeSetFormat::Markdown) => self.render_markdown_review(&review),
+            (OutputFormat::Text, ChangeSetFormat::GithubAnnotations) => {
+                self.render_github_annotations(&review)
+            }
+        }
+    }
+
+    fn render_markdown_review(&self, review: &ChangeSetReviewView) {
+        let mut lock = self.out();
+        let change_set = &review.change_set;
+        writeln!(lock, "<details>").unwrap();
+        writeln!(
+            lock,
+            "<summary>Change set <code>{}</code> of stack <code>{}</code>: {} change(s)</summary>\n",
+            change_set.name.as_deref().unwrap_or_default(),
+            change_set.stack_name.as_deref().unwrap_or_default(),
+            change_set.changes.len()
+        )
+        .unwrap();
+        writeln!(lock, "| | Logical ID | Type | Action | Replacement |").unwrap();
+        writeln!(lock, "|---|---|---|---|---|").unwrap();
+        for change in &change_set.changes {
+            writeln!(
+                lock,
+                "| {} | {} | {} | {} | {} |",
+                change
+                    .action
+                    .clone()
+                    .map(|action| ChangeActionSimbol(action).to_string())
+                    .unwrap_or_default(),
+                markdown_cell(change.logical_id.as_deref().unwrap_or_default()),
+                markdown_cell(change.resource_type.as_deref().unwrap_or_default()),
+                change
+                    .action
+                    .as_ref()
+                    .map(|a| a.as_str())
+                    .unwrap_or_default(),
+                change
+                    .replacement
+                    .as_ref()
+                    .map(|r| r.as_str())
+                    .unwrap_or_default(),
+            )
+            .unwrap();
+        }
+        writeln!(lock, "\n</details>").unwrap();
+
+        if !review.violations.is_empty() {
+            writeln!(lock, "\n**Violations**\n").unwrap();
+            for violation in &review.violations {
+                writeln!(
+                    lock,
+                    "- `{}` {} ({})",
+                    violation.rule,
+                    markdown_cell(&violation.message),
+                    violation.location
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    fn render_github_annotations(&self, review: &ChangeSetReviewView) {
+        let mut lock = self.out();
+        for change in &review.change_set.changes {
+            let resource = format!(
+                "{} ({})",
+                change
+                    .logical_id
+                    .as_deref()
+                    .unwrap_or(UNKNOWN_RESOURCE_LOGICAL_ID),
+                change
+                    .resource_type
+                    .as_deref()
+                    .unwrap_or(UNKNOWN_RESOURCE_TYPE)
+            );
+            let annotation = match (&change.action, &change.replacement) {
+                (Some(ChangeAction::Remove), _) => {
+                    Some(("error", "Deletion", format!("{resource} is deleted")))
+                }
+                (_, Some(Replacement::True)) => {
+                    Some(("error", "Replacement", format!("{resource} is replaced")))
+                }
+                (_, Some(Replacement::Conditional)) => Some((
+                    "warning",
+                    "Conditional replacement",
+                    format!("{resource} may be replaced"),
+                )),
+                _ => None,
+            };
+            if let Some((level, title, message)) = annotation {
+                writeln!(
+                    lock,
+                    "::{level} title={}::{}",
+                    annotation_property(title),
+                    annotation_data(&message)
+                )
+                .unwrap();
+            }
+        }
+        for violation in &review.violations {
+            writeln!(
+                lock,
+                "::error title={}::{}",
+                annotation_property(violation.rule),
+                annotation_data(&format!("{}: {}", violation.location, violation.message))
+            )
+            .unwrap();
+        }
+        writeln!(
+            lock,
+            "::notice title=Change set::{} change(s) on stack {}",
+            review.change_set.changes.len(),
+            annotation_data(review.change_set.stack_name.as_deref().unwrap_or_default())
+        )
+        .unwrap();
+    }
+
+    pub fn print_violations(&self, violations: &[Violation]) {
+        if self.output == OutputFormat::Json {
+            let violations: Vec<_> = violations.iter().map(ViolationView::from).collect();
+            return self.print_json(&violations);
+        }
+        let mut lock = self.out();
+        for violation in violations {
+            pprintln!(
+                lock,
+                "{} [{}]",
+                0,
+                TextColor::Red,
+                violation.location,
+                violation.rule
+            );
+            pprintln!(lock, "{}", 2, TextColor::Red, violation.message);
+        }
+    }
+
+    fn write_differences(lock: &mut impl Write, differences: &[Difference], indentation: usize) {
+        let indentation = " ".repeat(indentation);
+        for difference in differences {
+            let (color, line) = match difference {
+                Difference::Added { path, value } => {
+                    (TextColor::Green, format!("+ {path}: {value}"))
+                }
+                Difference::Removed { path, value } => {
+                    (TextColor::Red, format!("- {path}: {value}"))
+                }
+                Difference::Changed { path, old, new } => {
+                    (TextColor::Yellow, format!("~ {path}: {old} => {new}"))
+                }
+            };
+            writeln!(lock, "{}", color.colorize(&format!("{indentation} {line}"))).unwrap();
+        }
+    }
+
+    pub fn print_template_diff(&self, differences: &[Difference]) {
+        let mut lock = self.out();
+        Self::write_differences(&mut lock, differences, 0);
+    }
+
+    pub fn print_stack_comparison(
+        &self,
+        old: &str,
+        new: &str,
+        sections: &[(&str, Vec<Difference>)],
+    ) {
+        let mut lock = self.out();
+        pprintln!(lock, "- {old}", 0, TextColor::Red);
+        pprintln!(lock, "+ {new}", 0, TextColor::Green);
+        for (section, differences) in sections {
+            if differences.is_empty() {
+                pprintln!(lock, "{section}: identical", 0, TextColor::Default);
+                continue;
+            }
+            pprintln!(
+                lock,
+                "{section}: {} divergence(s)",
+                0,
+                TextColor::Default,
+                differences.len()
+            );
+            Self::write_differences(&mut lock, differences, 2);
+        }
+    }
+
+    pub fn print_found_resources(&self, resources: &[StackResource]) {
+        let mut lock = self.out();
+        for resource in resources {
+            pprintln!(
+                lock,
+                "{} ({})",
+                0,
+                TextColor::Default,
+                resource
+                    .logical_resource_id()
+                    .unwrap_or(UNKNOWN_RESOURCE_LOGICAL_ID),
+                resource.resource_type().unwrap_or(UNKNOWN_RESOURCE_TYPE)
+            );
+            pprintln!(
+                lock,
+                "Stack: {}",
+                2,
+                TextColor::Default,
+                resource.stack_name().unwrap_or_default()
+            );
+            if let Some(physical_id) = resource.physical_resource_id() {
+                pprintln!(lock, "Physical ID: {physical_id}", 2, TextColor::Default);
+            }
+            if let Some(resource_status) = resource.resource_status() {
+                pprintln!(
+                    lock,
+                    "Status: {resource_status:?}",
+                    2,
+                    TextColor::from_resource_status(resource_status)
+                );
+            }
+            if let Some(console_url) = resource
+                .stack_id()
+                .zip(resource.logical_resource_id())
+                .and_then(|(stack_id, logical_id)| {
+                    partition::resource_console_url(stack_id, logical_id)
+                })
+            {
+                pprintln!(lock, "Console: {console_url}", 2, TextColor::Default);
+            }
+        }
+    }
+
+    pub fn print_destroy_impact(
+        &self,
+        resources: &[(StackResourceSummary, Option<String>)],
+        exports: &[(String, Vec<String>)],
+    ) {
+        let mut lock = self.out();
+        let is_retained = |deletion_policy: &Option<String>| {
+            matches!(
+                deletion_policy.as_deref(),
+                Some("Retain" | "RetainExceptOnCreate")
+            )
+        };
+
+        pprintln!(lock, "Resources to delete:", 0, TextColor::Default);
+        for (resource, deletion_policy) in resources.iter().filter(|(_, p)| !is_retained(p)) {
+            pprintln!(
+                lock,
+                "{} ({}) {}{}",
+                2,
+                TextColor::Red,
+                resource
+                    .logical_resource_id()
+                    .unwrap_or(UNKNOWN_RESOURCE_LOGICAL_ID),
+                resource.resource_type().unwrap_or(UNKNOWN_RESOURCE_TYPE),
+                resource.physical_resource_id().unwrap_or_default(),
+                match deletion_policy.as_deref() {
+                    Some("Snapshot") => " [snapshot before deletion]",
+                    _ => "",
+                }
+            );
+        }
+
+        pprintln!(
+            lock,
+            "Resources retained (orphaned):",
+            0,
+            TextColor::Default
+        );
+        for (resource, deletion_policy) in resources.iter().filter(|(_, p)| is_retained(p)) {
+            pprintln!(
+                lock,
+                "{} ({}) {} [DeletionPolicy: {}]",
+                2,
+                TextColor::Yellow,
+                resource
+                    .logical_resource_id()
+                    .unwrap_or(UNKNOWN_RESOURCE_LOGICAL_ID),
+                resource.resource_type().unwrap_or(UNKNOWN_RESOURCE_TYPE),
+                resource.physical_resource_id().unwrap_or_default(),
+                deletion_policy.as_deref().unwrap_or_default()
+            );
+        }
+
+        pprintln!(lock, "Exports to remove:", 0, TextColor::Default);
+        for (export_name, importers) in exports {
+            if importers.is_empty() {
+                pprintln!(lock, "{export_name} (not imported)", 2, TextColor::Default);
+            } else {
+                pprintln!(
+                    lock,
+                    "{export_name} imported by: {}",
+                    2,
+                    TextColor::Red,
+                    importers.join(", ")
+                );
+            }
+        }
+    }
+
+    pub fn print_stack_names(&self, title: &str, stacks: &[String]) {
+        let mut lock = self.out();
+        pprintln!(lock, "{title}", 0, TextColor::Default);
+        for stack in stacks {
+            pprintln!(lock, "{stack}", 2, TextColor::Default);
+        }
+    }
+
+    pub fn print_operation_results(
+        &self,
+        title: &str,
+        results: &[(String, anyhow::Result<StackStatus>)],
+    ) {
+        let mut lock = self.out();
+        pprintln!(lock, "{title}", 0, TextColor::Default);
+        for (target, result) in results {
+            match result {
+                Ok(status) => pprintln!(
+                    lock,
+                    "{target}: {status:?}",
+                    2,
+                    TextColor::from_stack_status(status)
+                ),
+                Err(err) => pprintln!(lock, "{target}: {err}", 2, TextColor::Red),
+            }
+        }
+    }
+
+    pub fn print_project_stacks(&self, stacks: &BTreeMap<String, StackConfig>) {
+        let mut lock = self.out();
+        for (name, stack) in stacks {
+            pprintln!(lock, "Stack name: {name}", 0, TextColor::Default);
+            if let Some(template) = stack.template.as_ref() {
+                pprintln!(
+                    lock,
+                    "Template: {}",
+                    2,
+                    TextColor::Default,
+                    template.display()
+                );
+            }
+            if let Some(region) = stack.region.as_ref() {
+                pprintln!(lock, "Region: {region}", 2, TextColor::Default);
+            }
+            if !stack.depends_on.is_empty() {
+                pprintln!(
+                    lock,
+                    "Depends on: {}",
+                    2,
+                    TextColor::Default,
+                    stack.depends_on.join(", ")
+                );
+            }
+            if !stack.parameters.is_empty() {
+                pprintln!(lock, "Parameters:", 2, TextColor::Default);
+                for (key, value) in &stack.parameters {
+                    pprintln!(lock, "{key}:{value}", 4, TextColor::Default);
+                }
+            }
+            if !stack.tags.is_empty() {
+                pprintln!(lock, "Tags:", 2, TextColor::Default);
+                for (key, value) in &stack.tags {
+                    pprintln!(lock, "{key}:{value}", 4, TextColor::Default);
+                }
+            }
+            if !stack.inputs.from_stack.is_empty() {
+                pprintln!(lock, "Inputs:", 2, TextColor::Default);
+                for (property, reference) in &stack.inputs.from_stack {
+                    pprintln!(lock, "{property} <- {reference}", 4, TextColor::Default);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use aws_sdk_cloudformation::{
+        primitives::DateTime,
+        types::{Stack, StackStatus},
+    };
+    use std::sync::{Arc, Mutex};
+
+    use super::{Display, OutputFormat, Sink};
+
+    #[test]
+    fn prints_go_to_the_sink() {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let sink: Sink = buffer.clone();
+        let stack = Stack::builder()
+            .stack_name("network")
+            .stack_status(StackStatus::CreateComplete)
+            .creation_time(DateTime::from_secs(0))
+            .build();
+        Display::new()
+            .output(OutputFormat::Json)
+            .sink(sink)
+            .print_stack(&stack);
+
+        let printed: serde_json::Value = serde_json::from_slice(&buffer.lock().unwrap()).unwrap();
+        assert_eq!(printed["name"], "network");
+    }
 }
@@ -0,0 +1,96 @@
+use aws_sdk_cloudformation::types::{StackDriftDetectionStatus, StackDriftStatus};
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::aws_client::AwsClient;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DriftedResource {
+    pub logical_id: String,
+    pub resource_type: String,
+    pub status: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DriftReport {
+    pub stack: String,
+    pub stack_id: Option<String>,
+    pub status: Option<String>,
+    pub drifted: Vec<DriftedResource>,
+    pub error: Option<String>,
+}
+
+impl DriftReport {
+    pub fn is_drifted(&self) -> bool {
+        self.status.as_deref() == Some(StackDriftStatus::Drifted.as_str())
+    }
+
+    // What the notifications compare, to only report drift when it appears or changes
+    pub fn fingerprint(&self) -> Vec<String> {
+        self.drifted
+            .iter()
+            .map(|resource| format!("{}:{}", resource.logical_id, resource.status))
+            .collect()
+    }
+}
+
+async fn try_detect(
+    client: &AwsClient,
+    stack: &str,
+    pool_interval: Duration,
+) -> anyhow::Result<DriftReport> {
+    let detection_id = client.detect_stack_drift(stack).await?;
+    let detection = client
+        .wait_for_drift_detection(&detection_id, pool_interval)
+        .await?;
+    // A failed detection still has the drift of the resources it could check
+    let error = match detection.detection_status() {
+        Some(StackDriftDetectionStatus::DetectionFailed) => Some(
+            detection
+                .detection_status_reason()
+                .unwrap_or("Drift detection failed")
+                .to_string(),
+        ),
+        _ => None,
+    };
+    let drifted = match detection.stack_drift_status() {
+        Some(StackDriftStatus::Drifted) => client
+            .drifted_resources(stack)
+            .await?
+            .iter()
+            .map(|drift| DriftedResource {
+                logical_id: drift.logical_resource_id().unwrap_or_default().to_string(),
+                resource_type: drift.resource_type().unwrap_or_default().to_string(),
+                status: drift
+                    .stack_resource_drift_status()
+                    .map(|status| status.as_str().to_string())
+                    .unwrap_or_default(),
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    Ok(DriftReport {
+        stack: stack.to_string(),
+        stack_id: detection.stack_id().map(str::to_string),
+        status: detection
+            .stack_drift_status()
+            .map(|status| status.as_str().to_string()),
+        drifted,
+        error,
+    })
+}
+
+// Errors are part of the report, one stack failing doesn't stop the others
+pub async fn detect(client: &AwsClient, stack: &str, pool_interval: Duration) -> DriftReport {
+    try_detect(client, stack, pool_interval)
+        .await
+        .unwrap_or_else(|err| DriftReport {
+            stack: stack.to_string(),
+            stack_id: None,
+            status: None,
+            drifted: Vec::new(),
+            error: Some(format!("{err}")),
+        })
+}
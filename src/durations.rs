@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::PathBuf, time::Duration};
+
+use crate::{timings::ResourceTiming, update};
+
+const SAMPLES: usize = 10;
+
+// Durations of the last deployments of each resource type, per stack
+#[derive(Default, Serialize, Deserialize)]
+pub struct DurationHistory {
+    stacks: BTreeMap<String, BTreeMap<String, Vec<u64>>>,
+}
+
+impl DurationHistory {
+    fn path() -> Option<PathBuf> {
+        update::cache_file("durations.json")
+    }
+
+    // A missing or unreadable history only means no estimate
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|history| serde_json::from_str(&history).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let Some(path) = Self::path() else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn record(&mut self, stack: &str, resource_timings: &[ResourceTiming]) {
+        let stack = self.stacks.entry(stack.to_string()).or_default();
+        for timing in resource_timings {
+            let status = timing.status.as_str();
+            if !status.ends_with("_COMPLETE") || status.contains("ROLLBACK") {
+                continue;
+            }
+            let samples = stack.entry(timing.resource_type.clone()).or_default();
+            samples.push(timing.duration.as_secs());
+            if samples.len() > SAMPLES {
+                samples.remove(0);
+            }
+        }
+    }
+
+    pub fn has_stack(&self, stack: &str) -> bool {
+        self.stacks.contains_key(stack)
+    }
+
+    // Minimum, median and maximum durations
+    fn range(&self, stack: &str, resource_type: &str) -> Option<(u64, u64, u64)> {
+        let mut samples = self.stacks.get(stack)?.get(resource_type)?.clone();
+        samples.sort_unstable();
+        Some((
+            *samples.first()?,
+            samples[samples.len() / 2],
+            *samples.last()?,
+        ))
+    }
+
+    // Estimated from the in progress resource expected to finish last
+    pub fn estimate(&self, stack: &str, in_progress: &[(String, Duration)]) -> Option<String> {
+        let (resource_type, remaining, (min, _, max)) = in_progress
+            .iter()
+            .filter_map(|(resource_type, elapsed)| {
+                let range = self.range(stack, resource_type)?;
+                let remaining = range.1.saturating_sub(elapsed.as_secs());
+                Some((resource_type, remaining, range))
+            })
+            .max_by_key(|(_, remaining, _)| *remaining)?;
+        Some(format!(
+            "~{} min left, {resource_type} historically take {}–{} min",
+            remaining.div_ceil(60),
+            min / 60,
+            max.div_ceil(60)
+        ))
+    }
+}
@@ -0,0 +1,71 @@
+use aws_sdk_cloudformation::types::StackStatus;
+
+use crate::aws_client::AccessDenied;
+
+const AWS_EXIT_CODE: u8 = 5;
+
+pub type Result<T, E = PklformationError> = std::result::Result<T, E>;
+
+// The errors callers may want to tell apart: the public APIs return them, the binary maps them to
+// exit codes. Inside the crate they travel in anyhow and are recovered at the public boundary.
+#[derive(Debug, thiserror::Error)]
+pub enum PklformationError {
+    #[error("{0}")]
+    Template(String),
+    #[error("{0}")]
+    Validation(String),
+    #[error("{action} failed: {message}")]
+    Aws {
+        action: &'static str,
+        code: Option<String>,
+        message: String,
+        // Only when the message explains the error in its own words, not to repeat the SDK text
+        #[source]
+        source: Option<Box<dyn std::error::Error + Send + Sync>>,
+    },
+    #[error(transparent)]
+    AccessDenied(#[from] AccessDenied),
+    #[error("{0}")]
+    Aborted(String),
+    // The operation ran to its end, but the stack didn't reach the status it was meant to
+    #[error("Stack {stack} ended in status {}{}", .status.as_str(), .reason.as_deref().map(|reason| format!(": {reason}")).unwrap_or_default())]
+    StackFailed {
+        stack: String,
+        status: StackStatus,
+        reason: Option<String>,
+    },
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+// The kinds raised inside the crate come back out of anyhow. Only a bare kind is unwrapped, the
+// context added on top of one would be lost, exit_code still finds it in the chain.
+impl From<anyhow::Error> for PklformationError {
+    fn from(err: anyhow::Error) -> Self {
+        let outermost: &(dyn std::error::Error + Send + Sync) = err.as_ref();
+        if !outermost.is::<PklformationError>() {
+            return PklformationError::Other(err);
+        }
+        err.downcast().unwrap_or_else(PklformationError::Other)
+    }
+}
+
+impl PklformationError {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            PklformationError::Template(_) => 3,
+            PklformationError::Validation(_) => 4,
+            PklformationError::Aws { .. } | PklformationError::AccessDenied(_) => AWS_EXIT_CODE,
+            PklformationError::Aborted(_) => 6,
+            PklformationError::StackFailed { .. } => 7,
+            PklformationError::Other(err) => exit_code(err),
+        }
+    }
+}
+
+// Context added on top of an error doesn't change its kind
+pub fn exit_code(err: &anyhow::Error) -> u8 {
+    err.chain()
+        .find_map(|cause| cause.downcast_ref::<PklformationError>())
+        .map_or(1, PklformationError::exit_code)
+}
@@ -0,0 +1,83 @@
+use aws_sdk_cloudformation::{primitives::DateTimeFormat, types::StackEvent};
+use serde_json::json;
+use std::{
+    collections::HashSet,
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// Keeps the events that happened since its creation and were not seen yet
+pub(crate) struct NewEvents {
+    since: f64,
+    seen: HashSet<String>,
+}
+
+impl NewEvents {
+    pub(crate) fn new() -> anyhow::Result<Self> {
+        Ok(Self {
+            since: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs_f64(),
+            seen: HashSet::new(),
+        })
+    }
+
+    pub(crate) fn filter<'a>(&mut self, events: &'a [StackEvent]) -> Vec<&'a StackEvent> {
+        // DescribeStackEvents lists the most recent events first
+        events
+            .iter()
+            .rev()
+            .filter(|event| {
+                let timestamp = event
+                    .timestamp()
+                    .map(|t| t.as_secs_f64())
+                    .unwrap_or_default();
+                timestamp >= self.since
+                    && self
+                        .seen
+                        .insert(event.event_id().unwrap_or_default().to_owned())
+            })
+            .collect()
+    }
+}
+
+// Events of nested stacks carry the logical ids leading to them from the root stack
+pub(crate) fn record(event: &StackEvent, nested_stack_path: Option<&str>) -> serde_json::Value {
+    json!({
+        "stackName": event.stack_name(),
+        "nestedStackPath": nested_stack_path,
+        "eventId": event.event_id(),
+        "timestamp": event.timestamp().and_then(|t| t.fmt(DateTimeFormat::DateTime).ok()),
+        "logicalResourceId": event.logical_resource_id(),
+        "physicalResourceId": event.physical_resource_id(),
+        "resourceType": event.resource_type(),
+        "resourceStatus": event.resource_status().map(|s| s.as_str()),
+        "resourceStatusReason": event.resource_status_reason(),
+    })
+}
+
+pub struct EventsLog {
+    file: File,
+    events: NewEvents,
+}
+
+impl EventsLog {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        Ok(Self {
+            file: OpenOptions::new().create(true).append(true).open(path)?,
+            events: NewEvents::new()?,
+        })
+    }
+
+    pub fn append(
+        &mut self,
+        events: &[StackEvent],
+        nested_stack_path: Option<&str>,
+    ) -> anyhow::Result<()> {
+        for event in self.events.filter(events) {
+            writeln!(self.file, "{}", record(event, nested_stack_path))?;
+        }
+        self.file.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,235 @@
+use anyhow::Context;
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::{debug, warn};
+
+use crate::{
+    cloudformation_api::CloudFormationApi,
+    state_key::{self, Sealed},
+    update,
+};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    pub operation: String,
+    pub stack: String,
+    pub started_at: String,
+    pub duration_seconds: u64,
+    pub template_hash: Option<String>,
+    // Only set once revealed, the values may be secrets and are never recorded in clear
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub parameters: BTreeMap<String, String>,
+    pub change_set_id: Option<String>,
+    pub outcome: String,
+    pub caller: Option<String>,
+    // The values and the outputs, encrypted when a state key is configured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sealed: Option<Sealed>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outputs: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SealedValues {
+    parameters: BTreeMap<String, String>,
+    outputs: BTreeMap<String, String>,
+}
+
+pub struct Run<'a> {
+    pub operation: &'a str,
+    pub stack: &'a str,
+    pub duration: Duration,
+    pub template: Option<&'a Path>,
+    pub parameters: &'a BTreeMap<String, String>,
+    pub change_set_id: Option<&'a str>,
+    pub outcome: String,
+}
+
+// The history outlives the cache, it goes with the user data
+fn history_file() -> Option<PathBuf> {
+    let data_dir = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share"))
+        })?;
+    Some(data_dir.join("pklformation").join("history.jsonl"))
+}
+
+// Readable by the user only, the file may land in shared CI caches
+fn open_private(path: &Path) -> std::io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.create(true).append(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        let file = options.open(path)?;
+        // Files created before are tightened too
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+        Ok(file)
+    }
+    #[cfg(not(unix))]
+    options.open(path)
+}
+
+fn append(entry: &HistoryEntry) -> anyhow::Result<()> {
+    let path = history_file().context("No home directory for the deployment history")?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = open_private(&path)?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+// A failed recording never fails the operation itself
+pub async fn record(client: &impl CloudFormationApi, run: Run<'_>) {
+    let started_at = Utc::now() - run.duration;
+    let mut entry = HistoryEntry {
+        operation: run.operation.to_string(),
+        stack: run.stack.to_string(),
+        started_at: started_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+        duration_seconds: run.duration.as_secs(),
+        template_hash: run
+            .template
+            .and_then(|template| std::fs::read(template).ok())
+            .map(|template| update::sha256_hex(&template)),
+        parameters: BTreeMap::new(),
+        change_set_id: run.change_set_id.map(str::to_string),
+        outcome: run.outcome.clone(),
+        caller: match client.caller_identity().await {
+            Ok(identity) => Some(identity.arn),
+            Err(err) => {
+                debug!("Unable to get the caller identity: {err}");
+                None
+            }
+        },
+        sealed: None,
+        outputs: None,
+    };
+    if state_key::configured() {
+        match seal(client, &run).await {
+            Ok(sealed) => entry.sealed = Some(sealed),
+            Err(err) => warn!(
+                "Unable to encrypt the values of the {}: {err}",
+                run.operation
+            ),
+        }
+    }
+    if let Err(err) = append(&entry) {
+        warn!(
+            "Unable to record the {} in the history: {err}",
+            run.operation
+        );
+    }
+}
+
+// The outputs are read back after the run, a deleted stack has none
+async fn seal(client: &impl CloudFormationApi, run: &Run<'_>) -> anyhow::Result<Sealed> {
+    let outputs = if run.operation == "destroy" {
+        BTreeMap::new()
+    } else {
+        client
+            .describe_stack(run.stack)
+            .await
+            .map(|stack| {
+                stack
+                    .outputs()
+                    .iter()
+                    .map(|output| {
+                        (
+                            output.output_key().unwrap_or_default().to_string(),
+                            output.output_value().unwrap_or_default().to_string(),
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let values = serde_json::to_vec(&SealedValues {
+        parameters: run.parameters.clone(),
+        outputs,
+    })?;
+    state_key::seal(client, &values, run.stack).await
+}
+
+// Sets the recorded values, which requires the key they were encrypted with
+pub async fn reveal(
+    client: &impl CloudFormationApi,
+    entry: &mut HistoryEntry,
+) -> anyhow::Result<()> {
+    let Some(sealed) = entry.sealed.take() else {
+        return Ok(());
+    };
+    let values = state_key::open(client, &sealed, &entry.stack).await?;
+    let values: SealedValues = serde_json::from_slice(&values)?;
+    entry.parameters = values.parameters;
+    entry.outputs = Some(values.outputs);
+    Ok(())
+}
+
+// Oldest first, as recorded
+pub fn load() -> anyhow::Result<Vec<HistoryEntry>> {
+    let Some(path) = history_file().filter(|path| path.exists()) else {
+        return Ok(Vec::new());
+    };
+    let file = File::open(&path)?;
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<HistoryEntry>(&line) {
+            // Entries recorded before held digests of the values, they are not shown
+            Ok(mut entry) => {
+                entry.parameters.clear();
+                entries.push(entry)
+            }
+            Err(err) => warn!(
+                "Skip an unreadable history entry in {}: {err}",
+                path.display()
+            ),
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cloudformation_api::mock::{isolate_user_data, MockCloudFormation};
+
+    #[tokio::test]
+    async fn values_are_not_recorded_without_a_state_key() {
+        isolate_user_data();
+        let parameters = BTreeMap::from([("Password".to_string(), "hunter2".to_string())]);
+
+        record(
+            &MockCloudFormation::new(),
+            Run {
+                operation: "preview",
+                stack: "history-mock",
+                duration: Duration::ZERO,
+                template: None,
+                parameters: &parameters,
+                change_set_id: None,
+                outcome: "CREATE_COMPLETE".to_string(),
+            },
+        )
+        .await;
+
+        let recorded = std::fs::read_to_string(history_file().unwrap()).unwrap();
+        assert!(recorded.contains("history-mock"));
+        assert!(!recorded.contains("Password"));
+        assert!(!recorded.contains("hunter2"));
+    }
+}
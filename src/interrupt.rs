@@ -0,0 +1,47 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    OnceLock,
+};
+
+use tokio::sync::Notify;
+use tracing::debug;
+
+// Exit status of a process terminated by SIGINT, as shells report it
+const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+static LISTENER: OnceLock<()> = OnceLock::new();
+static HANDLERS: AtomicUsize = AtomicUsize::new(0);
+static INTERRUPTS: Notify = Notify::const_new();
+
+// Ctrl-C handled by the command while it lives, for instance to offer a choice during a wait
+pub struct Handler(());
+
+impl Drop for Handler {
+    fn drop(&mut self) {
+        HANDLERS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// Once tokio listens to SIGINT the process is no longer terminated by it, so the listener, started
+// once for the whole command, exits itself when no handler is there
+pub fn handle() -> Handler {
+    LISTENER.get_or_init(|| {
+        tokio::spawn(async {
+            while tokio::signal::ctrl_c().await.is_ok() {
+                if HANDLERS.load(Ordering::SeqCst) == 0 {
+                    std::process::exit(INTERRUPTED_EXIT_CODE);
+                }
+                debug!("Interrupted");
+                INTERRUPTS.notify_waiters();
+            }
+        });
+    });
+    HANDLERS.fetch_add(1, Ordering::SeqCst);
+    Handler(())
+}
+
+impl Handler {
+    pub async fn interrupted(&self) {
+        INTERRUPTS.notified().await;
+    }
+}
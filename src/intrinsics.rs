@@ -0,0 +1,292 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use regex::Regex;
+use serde_json::Value;
+use tracing::debug;
+
+use crate::{aws_client::AwsClient, validation::Violation};
+
+struct AttributeReference {
+    location: String,
+    logical_id: String,
+    attribute: String,
+}
+
+struct References<'a> {
+    parameters: BTreeSet<&'a str>,
+    resources: BTreeMap<&'a str, &'a str>,
+}
+
+impl<'a> References<'a> {
+    fn from_template(template: &'a Value) -> Self {
+        let parameters = template
+            .get("Parameters")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flat_map(|parameters| parameters.keys().map(String::as_str))
+            .collect();
+        let resources = template
+            .get("Resources")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flat_map(|resources| resources.iter())
+            .map(|(logical_id, resource)| {
+                let resource_type = resource.get("Type").and_then(Value::as_str);
+                (logical_id.as_str(), resource_type.unwrap_or_default())
+            })
+            .collect();
+        Self {
+            parameters,
+            resources,
+        }
+    }
+
+    fn can_ref(&self, name: &str) -> bool {
+        name.starts_with("AWS::")
+            || self.parameters.contains(name)
+            || self.resources.contains_key(name)
+    }
+}
+
+fn child_location(location: &str, key: &str) -> String {
+    if location.is_empty() {
+        key.to_string()
+    } else {
+        format!("{location}.{key}")
+    }
+}
+
+// Calls visit with every intrinsic function found under value, with its location
+fn walk(value: &Value, location: String, visit: &mut impl FnMut(&str, &Value, &str)) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                let location = child_location(&location, key);
+                if key == "Ref" || key.starts_with("Fn::") {
+                    visit(key, value, &location);
+                }
+                walk(value, location, visit);
+            }
+        }
+        Value::Array(array) => {
+            for (index, value) in array.iter().enumerate() {
+                walk(value, format!("{location}[{index}]"), visit);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn sub_variables(sub: &Value) -> Option<(&str, BTreeSet<&str>)> {
+    match sub {
+        Value::String(string) => Some((string, BTreeSet::new())),
+        Value::Array(array) => {
+            let string = array.first()?.as_str()?;
+            let variables = array
+                .get(1)
+                .and_then(Value::as_object)
+                .into_iter()
+                .flat_map(|variables| variables.keys().map(String::as_str))
+                .collect();
+            Some((string, variables))
+        }
+        _ => None,
+    }
+}
+
+// ${!Literal} is written as is by Fn::Sub, so it is not a reference
+fn sub_references(string: &str) -> Vec<&str> {
+    let sub_reference = Regex::new(r"\$\{([^!}][^}]*)\}").unwrap();
+    sub_reference
+        .captures_iter(string)
+        .filter_map(|captures| captures.get(1))
+        .map(|name| name.as_str().trim())
+        .collect()
+}
+
+fn get_att_target(get_att: &Value) -> Option<(&str, &str)> {
+    match get_att {
+        Value::String(string) => string.split_once('.'),
+        Value::Array(array) => Some((array.first()?.as_str()?, array.get(1)?.as_str()?)),
+        _ => None,
+    }
+}
+
+fn attribute_references(template: &Value) -> Vec<AttributeReference> {
+    let mut references = Vec::new();
+    walk(
+        template,
+        String::new(),
+        &mut |function, value, location| match function {
+            "Fn::GetAtt" => {
+                if let Some((logical_id, attribute)) = get_att_target(value) {
+                    references.push(AttributeReference {
+                        location: location.to_string(),
+                        logical_id: logical_id.to_string(),
+                        attribute: attribute.to_string(),
+                    });
+                }
+            }
+            "Fn::Sub" => {
+                let Some((string, variables)) = sub_variables(value) else {
+                    return;
+                };
+                for name in sub_references(string) {
+                    if variables.contains(name) {
+                        continue;
+                    }
+                    if let Some((logical_id, attribute)) = name.split_once('.') {
+                        references.push(AttributeReference {
+                            location: location.to_string(),
+                            logical_id: logical_id.to_string(),
+                            attribute: attribute.to_string(),
+                        });
+                    }
+                }
+            }
+            _ => {}
+        },
+    );
+    references
+}
+
+pub fn check_references(template: &Value, violations: &mut Vec<Violation>) {
+    // Transforms like AWS::Serverless generate resources that can be referenced
+    if template.get("Transform").is_some() {
+        debug!("Template with a transform, skipping the intrinsic function checks");
+        return;
+    }
+
+    let references = References::from_template(template);
+    walk(
+        template,
+        String::new(),
+        &mut |function, value, location| match function {
+            "Ref" => {
+                if let Some(name) = value.as_str().filter(|name| !references.can_ref(name)) {
+                    violations.push(Violation {
+                        rule: "intrinsic/ref",
+                        location: location.to_string(),
+                        message: format!(
+                            "{name:?} is not a parameter, a resource or a pseudo parameter"
+                        ),
+                    });
+                }
+            }
+            "Fn::Sub" => {
+                let Some((string, variables)) = sub_variables(value) else {
+                    return;
+                };
+                for name in sub_references(string) {
+                    let known = match name.split_once('.') {
+                        _ if variables.contains(name) => true,
+                        Some((logical_id, _)) => references.resources.contains_key(logical_id),
+                        None => references.can_ref(name),
+                    };
+                    if !known {
+                        violations.push(Violation {
+                            rule: "intrinsic/sub",
+                            location: location.to_string(),
+                            message: format!("${{{name}}} does not match a variable, a parameter, a resource or a pseudo parameter"),
+                        });
+                    }
+                }
+            }
+            "Fn::GetAtt" => match get_att_target(value) {
+                Some((logical_id, _)) if !references.resources.contains_key(logical_id) => {
+                    violations.push(Violation {
+                        rule: "intrinsic/get-att",
+                        location: location.to_string(),
+                        message: format!("{logical_id:?} is not a resource"),
+                    })
+                }
+                _ => {}
+            },
+            _ => {}
+        },
+    );
+
+    let Some(resources) = template.get("Resources").and_then(Value::as_object) else {
+        return;
+    };
+    for (logical_id, resource) in resources {
+        let depends_on = match resource.get("DependsOn") {
+            Some(Value::String(target)) => vec![target.as_str()],
+            Some(Value::Array(targets)) => targets.iter().filter_map(Value::as_str).collect(),
+            _ => continue,
+        };
+        for target in depends_on {
+            if target == logical_id || !references.resources.contains_key(target) {
+                violations.push(Violation {
+                    rule: "intrinsic/depends-on",
+                    location: format!("Resources.{logical_id}.DependsOn"),
+                    message: format!("{target:?} is not another resource of the template"),
+                });
+            }
+        }
+    }
+}
+
+// Resource types whose attributes are free-form or not described by a registry schema
+fn has_dynamic_attributes(resource_type: &str) -> bool {
+    resource_type.starts_with("Custom::")
+        || resource_type == "AWS::CloudFormation::CustomResource"
+        || resource_type == "AWS::CloudFormation::Stack"
+}
+
+fn schema_attributes(schema: &Value) -> BTreeSet<String> {
+    let read_only = schema
+        .get("readOnlyProperties")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(|pointer| pointer.trim_start_matches("/properties/").replace('/', "."));
+    let properties = schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flat_map(|properties| properties.keys().cloned());
+    read_only.chain(properties).collect()
+}
+
+pub async fn check_attributes(
+    client: &AwsClient,
+    template: &Value,
+) -> anyhow::Result<Vec<Violation>> {
+    let references = References::from_template(template);
+    let mut schemas: BTreeMap<&str, Option<BTreeSet<String>>> = BTreeMap::new();
+    let mut violations = Vec::new();
+    for reference in attribute_references(template) {
+        let Some(resource_type) = references
+            .resources
+            .get(reference.logical_id.as_str())
+            .copied()
+            .filter(|resource_type| !has_dynamic_attributes(resource_type))
+        else {
+            continue;
+        };
+        if !schemas.contains_key(resource_type) {
+            let attributes = match client.resource_schema(resource_type).await? {
+                Some(schema) => Some(schema_attributes(&serde_json::from_str(&schema)?)),
+                None => None,
+            };
+            schemas.insert(resource_type, attributes);
+        }
+        let Some(attributes) = schemas[resource_type].as_ref() else {
+            debug!("No schema for {resource_type}, skipping its attributes");
+            continue;
+        };
+        if !attributes.contains(&reference.attribute) {
+            violations.push(Violation {
+                rule: "intrinsic/get-att-attribute",
+                location: reference.location,
+                message: format!(
+                    "{resource_type} has no attribute {:?} (resource {})",
+                    reference.attribute, reference.logical_id
+                ),
+            });
+        }
+    }
+    Ok(violations)
+}
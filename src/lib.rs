@@ -0,0 +1,42 @@
+pub(crate) mod approval;
+pub(crate) mod audit_log;
+pub mod aws_client;
+pub(crate) mod browser;
+// Entry point of the pklformation binary, not part of the library API
+#[doc(hidden)]
+pub mod cli;
+pub(crate) mod cloudformation_api;
+pub(crate) mod commands;
+pub(crate) mod costs;
+pub(crate) mod deployment;
+pub(crate) mod diff;
+pub(crate) mod display;
+pub(crate) mod drift;
+pub(crate) mod durations;
+pub mod error;
+pub(crate) mod events_log;
+pub(crate) mod history;
+pub(crate) mod interrupt;
+pub(crate) mod intrinsics;
+pub(crate) mod nested_stacks;
+pub(crate) mod notifications;
+pub(crate) mod pager;
+pub(crate) mod partition;
+pub(crate) mod pkl;
+pub(crate) mod progress;
+pub(crate) mod project;
+pub(crate) mod proxy;
+pub(crate) mod quotas;
+pub(crate) mod reports;
+pub(crate) mod sso;
+pub(crate) mod stack_graph;
+pub(crate) mod stack_outputs;
+pub(crate) mod state_key;
+pub(crate) mod template_format;
+pub(crate) mod timings;
+pub(crate) mod update;
+pub(crate) mod validation;
+pub(crate) mod views;
+
+pub use deployment::{Approval, Deployment, DeploymentBuilder, DeploymentResult};
+pub use validation::{NamingRules, Validator, Violation};
@@ -0,0 +1,123 @@
+use aws_sdk_cloudformation::types::{ResourceStatus, StackEvent};
+use std::collections::{HashSet, VecDeque};
+use tracing::debug;
+
+use crate::{aws_client::AwsClient, cloudformation_api::CloudFormationApi};
+
+pub(crate) const NESTED_STACK_TYPE: &str = "AWS::CloudFormation::Stack";
+
+pub struct NestedFailure {
+    // Logical ids of the nested stacks, from the root stack down
+    pub path: Vec<String>,
+    pub event: StackEvent,
+}
+
+fn is_failure(event: &StackEvent) -> bool {
+    matches!(
+        event.resource_status(),
+        Some(
+            ResourceStatus::CreateFailed
+                | ResourceStatus::UpdateFailed
+                | ResourceStatus::DeleteFailed
+        )
+    )
+}
+
+fn is_nested_stack(event: &StackEvent) -> bool {
+    event.resource_type() == Some(NESTED_STACK_TYPE)
+}
+
+// The failure of a nested stack only says "Embedded stack ... failed", the cause is in its own
+// events, possibly several levels down
+pub async fn root_causes(
+    client: &impl CloudFormationApi,
+    events: &[StackEvent],
+    start_time: f64,
+) -> anyhow::Result<Vec<NestedFailure>> {
+    let mut failures = Vec::new();
+    let mut visited = HashSet::new();
+    let mut nested_stacks: VecDeque<(Vec<String>, String)> = VecDeque::new();
+    let enqueue = |path: &[String], event: &StackEvent, queue: &mut VecDeque<_>| {
+        if let Some(stack_id) = event.physical_resource_id().filter(|id| !id.is_empty()) {
+            let mut path = path.to_vec();
+            path.push(event.logical_resource_id().unwrap_or_default().to_string());
+            queue.push_back((path, stack_id.to_string()));
+        }
+    };
+    for event in events
+        .iter()
+        .filter(|event| is_failure(event) && is_nested_stack(event))
+    {
+        enqueue(&[], event, &mut nested_stacks);
+    }
+
+    while let Some((path, stack_id)) = nested_stacks.pop_front() {
+        if !visited.insert(stack_id.clone()) {
+            continue;
+        }
+        let nested_events = client.describe_stack_events(&stack_id).await?;
+        for event in nested_events.into_iter().filter(|event| {
+            is_failure(event)
+                && event
+                    .timestamp()
+                    .map(|t| t.as_secs_f64())
+                    .unwrap_or_default()
+                    > start_time
+        }) {
+            // The stack itself appears in its own events
+            if event.physical_resource_id() == Some(stack_id.as_str()) {
+                continue;
+            }
+            if is_nested_stack(&event)
+                && event
+                    .physical_resource_id()
+                    .is_some_and(|id| !id.is_empty())
+            {
+                enqueue(&path, &event, &mut nested_stacks);
+            } else {
+                failures.push(NestedFailure {
+                    path: path.clone(),
+                    event,
+                });
+            }
+        }
+    }
+    Ok(failures)
+}
+
+// The nested stacks being created, updated or deleted, with their logical path like
+// Network/Subnets, found level by level from the root stack
+pub async fn in_progress(client: &AwsClient, stack: &str) -> Vec<(String, String)> {
+    let mut found = Vec::new();
+    let mut parents = VecDeque::from([(String::new(), stack.to_string())]);
+    while let Some((parent_path, parent)) = parents.pop_front() {
+        let resources = match client.list_all_stack_resources(&parent).await {
+            Ok(resources) => resources,
+            Err(err) => {
+                debug!("Unable to list the nested stacks of {parent}: {err}");
+                continue;
+            }
+        };
+        for resource in resources.iter().filter(|resource| {
+            resource.resource_type() == Some(NESTED_STACK_TYPE)
+                && resource
+                    .resource_status()
+                    .is_some_and(|status| status.as_str().ends_with("_IN_PROGRESS"))
+        }) {
+            let (Some(logical_id), Some(stack_id)) = (
+                resource.logical_resource_id(),
+                resource.physical_resource_id().filter(|id| !id.is_empty()),
+            ) else {
+                continue;
+            };
+            let path = if parent_path.is_empty() {
+                logical_id.to_string()
+            } else {
+                format!("{parent_path}/{logical_id}")
+            };
+            parents.push_back((path.clone(), stack_id.to_string()));
+            found.push((path, stack_id.to_string()));
+        }
+    }
+    found
+}
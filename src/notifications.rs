@@ -0,0 +1,234 @@
+use aws_sdk_cloudformation::types::StackStatus;
+use serde::Deserialize;
+use serde_json::json;
+
+use std::{fmt, time::Duration};
+use tracing::{debug, warn};
+
+use crate::{
+    cloudformation_api::CloudFormationApi, drift::DriftReport, error::PklformationError, partition,
+    proxy, timings,
+};
+
+const NOTIFICATION_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum NotificationTarget {
+    Slack { webhook_url: String },
+    Webhook { url: String },
+    Sns { topic_arn: String },
+}
+
+// Webhook URLs carry their secret in the path, only their host is logged
+pub(crate) fn redact_url(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(url) => format!(
+            "{}://{}/...",
+            url.scheme(),
+            url.host_str().unwrap_or_default()
+        ),
+        Err(_) => "<invalid URL>".to_string(),
+    }
+}
+
+impl fmt::Display for NotificationTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotificationTarget::Slack { webhook_url } => {
+                write!(f, "Slack webhook {}", redact_url(webhook_url))
+            }
+            NotificationTarget::Webhook { url } => write!(f, "webhook {}", redact_url(url)),
+            NotificationTarget::Sns { topic_arn } => write!(f, "SNS topic {topic_arn}"),
+        }
+    }
+}
+
+// The project is logged when debugging, it must not leak the URLs either
+impl fmt::Debug for NotificationTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+pub struct Completion<'a> {
+    pub operation: &'a str,
+    pub stack: &'a str,
+    pub stack_id: Option<&'a str>,
+    pub duration: Duration,
+    pub result: &'a anyhow::Result<StackStatus>,
+}
+
+impl Completion<'_> {
+    fn succeeded(&self) -> bool {
+        matches!(
+            self.result,
+            Ok(StackStatus::CreateComplete
+                | StackStatus::UpdateComplete
+                | StackStatus::DeleteComplete)
+        )
+    }
+
+    // Aborted and detached runs are not failures
+    pub(crate) fn failed(&self) -> bool {
+        match self.result {
+            Ok(status) => {
+                status.as_str().contains("FAILED") || status.as_str().contains("ROLLBACK")
+            }
+            Err(err) => !matches!(
+                err.downcast_ref::<PklformationError>(),
+                Some(PklformationError::Aborted(_))
+            ),
+        }
+    }
+
+    pub(crate) fn status(&self) -> String {
+        match self.result {
+            Ok(status) => status.as_str().to_string(),
+            Err(err) => format!("FAILED ({err})"),
+        }
+    }
+
+    fn duration(&self) -> String {
+        timings::format_duration(self.duration)
+    }
+
+    pub(crate) fn console_url(&self) -> Option<String> {
+        self.stack_id.and_then(partition::stack_console_url)
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "pklformation {} of stack {}: {} in {}",
+            self.operation,
+            self.stack,
+            self.status(),
+            self.duration()
+        )
+    }
+
+    fn text(&self) -> String {
+        match self.console_url() {
+            Some(console_url) => format!("{}\n{console_url}", self.summary()),
+            None => self.summary(),
+        }
+    }
+
+    fn payload(&self) -> serde_json::Value {
+        json!({
+            "operation": self.operation,
+            "stack": self.stack,
+            "status": self.status(),
+            "succeeded": self.succeeded(),
+            "durationSeconds": self.duration.as_secs(),
+            "consoleUrl": self.console_url(),
+        })
+    }
+}
+
+async fn post(url: &str, body: &serde_json::Value) -> anyhow::Result<()> {
+    proxy::reqwest_client_builder()
+        .timeout(NOTIFICATION_TIMEOUT)
+        .build()?
+        .post(url)
+        .json(body)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(reqwest::Error::without_url)?;
+    Ok(())
+}
+
+// Every target gets the same message, as text for chats and emails or as JSON for webhooks
+struct Message {
+    summary: String,
+    text: String,
+    payload: serde_json::Value,
+}
+
+impl From<&Completion<'_>> for Message {
+    fn from(completion: &Completion<'_>) -> Self {
+        Self {
+            summary: completion.summary(),
+            text: completion.text(),
+            payload: completion.payload(),
+        }
+    }
+}
+
+impl From<&DriftReport> for Message {
+    fn from(report: &DriftReport) -> Self {
+        let summary = format!(
+            "pklformation drift of stack {}: {} resource(s) drifted",
+            report.stack,
+            report.drifted.len()
+        );
+        let mut text = summary.clone();
+        for resource in &report.drifted {
+            text.push_str(&format!(
+                "\n{} ({}): {}",
+                resource.logical_id, resource.resource_type, resource.status
+            ));
+        }
+        let console_url = report
+            .stack_id
+            .as_deref()
+            .and_then(partition::stack_console_url);
+        if let Some(console_url) = &console_url {
+            text.push_str(&format!("\n{console_url}"));
+        }
+        Self {
+            summary,
+            text,
+            payload: json!({
+                "operation": "drift",
+                "stack": report.stack,
+                "status": report.status,
+                "driftedResources": report.drifted,
+                "consoleUrl": console_url,
+            }),
+        }
+    }
+}
+
+async fn send(
+    client: &impl CloudFormationApi,
+    target: &NotificationTarget,
+    message: &Message,
+) -> anyhow::Result<()> {
+    match target {
+        NotificationTarget::Slack { webhook_url } => {
+            post(webhook_url, &json!({ "text": message.text })).await
+        }
+        NotificationTarget::Webhook { url } => post(url, &message.payload).await,
+        NotificationTarget::Sns { topic_arn } => {
+            // SNS subjects are limited to 100 characters
+            let subject: String = message.summary.chars().take(100).collect();
+            client
+                .publish_notification(topic_arn, &subject, &message.text)
+                .await
+        }
+    }
+}
+
+async fn send_all(client: &impl CloudFormationApi, message: Message) {
+    for target in client.notification_targets() {
+        debug!("Send notification to {target}");
+        if let Err(err) = send(client, target, &message).await {
+            warn!("Unable to send the notification to {target}: {err}");
+        }
+    }
+}
+
+// A failed notification never fails the operation it reports
+pub async fn notify(client: &impl CloudFormationApi, completion: Completion<'_>) {
+    send_all(client, Message::from(&completion)).await;
+}
+
+pub async fn notify_drift(client: &impl CloudFormationApi, report: &DriftReport) {
+    send_all(client, Message::from(report)).await;
+}
@@ -0,0 +1,101 @@
+use std::{
+    io::{ErrorKind, IsTerminal, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{Arc, Mutex},
+};
+
+use tracing::debug;
+
+use crate::display::{self, Sink};
+
+const DEFAULT_PAGER: &str = "less";
+// Quit when the output fits the screen, keep the colors and leave the output on the screen
+const DEFAULT_LESS: &str = "FRX";
+
+struct PagerWriter {
+    command: Vec<String>,
+    child: Option<(Child, ChildStdin)>,
+    spawned: bool,
+}
+
+impl PagerWriter {
+    // Started on the first write, the spinners of the command are done by then
+    fn stdin(&mut self) -> Option<&mut ChildStdin> {
+        if !self.spawned {
+            self.spawned = true;
+            let mut command = Command::new(&self.command[0]);
+            command.args(&self.command[1..]).stdin(Stdio::piped());
+            if std::env::var_os("LESS").is_none() {
+                command.env("LESS", DEFAULT_LESS);
+            }
+            match command.spawn() {
+                Ok(mut child) => {
+                    let stdin = child.stdin.take().expect("stdin is piped");
+                    self.child = Some((child, stdin));
+                }
+                Err(err) => debug!("Unable to start the pager {:?}: {err}", self.command),
+            }
+        }
+        self.child.as_mut().map(|(_, stdin)| stdin)
+    }
+}
+
+impl Write for PagerWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self.stdin() {
+            // Quitting the pager early drops the rest of the output
+            Some(stdin) => match stdin.write(buf) {
+                Err(err) if err.kind() == ErrorKind::BrokenPipe => Ok(buf.len()),
+                result => result,
+            },
+            None => std::io::stdout().write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self.stdin() {
+            Some(stdin) => match stdin.flush() {
+                Err(err) if err.kind() == ErrorKind::BrokenPipe => Ok(()),
+                result => result,
+            },
+            None => std::io::stdout().flush(),
+        }
+    }
+}
+
+// Pipes the output of the displays through $PAGER, less by default, like git does. Dropping it
+// waits for the pager to be closed.
+pub struct Pager(Arc<Mutex<PagerWriter>>);
+
+impl Pager {
+    // Only for terminals, and not when $PAGER is empty or cat
+    pub fn start() -> Option<Self> {
+        if !std::io::stdout().is_terminal() {
+            return None;
+        }
+        let pager = std::env::var("PAGER").unwrap_or_else(|_| DEFAULT_PAGER.to_string());
+        let command: Vec<_> = pager.split_whitespace().map(str::to_string).collect();
+        if command.is_empty() || command[0] == "cat" {
+            return None;
+        }
+        let writer = Arc::new(Mutex::new(PagerWriter {
+            command,
+            child: None,
+            spawned: false,
+        }));
+        let sink: Sink = writer.clone();
+        display::set_default_sink(Some(sink));
+        Some(Self(writer))
+    }
+}
+
+impl Drop for Pager {
+    fn drop(&mut self) {
+        display::set_default_sink(None);
+        if let Some((mut child, stdin)) = self.0.lock().unwrap().child.take() {
+            // Closing stdin ends the output, the pager exits when the user quits
+            drop(stdin);
+            let _ = child.wait();
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::BTreeSet;
+
+use anyhow::bail;
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::{debug, warn};
+
+use crate::cloudformation_api::CloudFormationApi;
+
+const STANDARD_REGION_PREFIXES: &[&str] = &[
+    "af-", "ap-", "ca-", "eu-", "il-", "me-", "mx-", "sa-", "us-east-", "us-west-",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Partition {
+    #[serde(rename = "aws")]
+    Aws,
+    #[serde(rename = "aws-cn")]
+    AwsCn,
+    #[serde(rename = "aws-us-gov")]
+    AwsUsGov,
+}
+
+impl Partition {
+    pub fn from_region(region: &str) -> Option<Self> {
+        if region.starts_with("cn-") {
+            Some(Partition::AwsCn)
+        } else if region.starts_with("us-gov-") {
+            Some(Partition::AwsUsGov)
+        } else if STANDARD_REGION_PREFIXES
+            .iter()
+            .any(|prefix| region.starts_with(prefix))
+        {
+            Some(Partition::Aws)
+        } else {
+            None
+        }
+    }
+
+    pub fn from_arn(arn: &str) -> Option<Self> {
+        match arn.split(':').nth(1)? {
+            "aws" => Some(Partition::Aws),
+            "aws-cn" => Some(Partition::AwsCn),
+            "aws-us-gov" => Some(Partition::AwsUsGov),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Partition::Aws => "aws",
+            Partition::AwsCn => "aws-cn",
+            Partition::AwsUsGov => "aws-us-gov",
+        }
+    }
+
+    pub fn console_domain(&self) -> &'static str {
+        match self {
+            Partition::Aws => "console.aws.amazon.com",
+            Partition::AwsCn => "console.amazonaws.cn",
+            Partition::AwsUsGov => "console.amazonaws-us-gov.com",
+        }
+    }
+
+    pub fn dns_suffix(&self) -> &'static str {
+        match self {
+            Partition::Aws | Partition::AwsUsGov => "amazonaws.com",
+            Partition::AwsCn => "amazonaws.com.cn",
+        }
+    }
+}
+
+fn url_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+// Stack ids are ARNs, so they carry both the partition and the region of the stack
+fn stack_tab_console_url(stack_id: &str, tab: &str, filter: Option<&str>) -> Option<String> {
+    let partition = Partition::from_arn(stack_id)?;
+    let region = stack_id.split(':').nth(3)?;
+    let mut url = format!(
+        "https://{region}.{}/cloudformation/home?region={region}#/stacks/{tab}?stackId={}",
+        partition.console_domain(),
+        url_encode(stack_id)
+    );
+    if let Some(filter) = filter {
+        url.push_str(&format!("&filteringText={}", url_encode(filter)));
+    }
+    Some(url)
+}
+
+pub fn stack_console_url(stack_id: &str) -> Option<String> {
+    stack_tab_console_url(stack_id, "stackinfo", None)
+}
+
+pub fn resource_console_url(stack_id: &str, logical_id: &str) -> Option<String> {
+    stack_tab_console_url(stack_id, "resources", Some(logical_id))
+}
+
+pub fn events_console_url(stack_id: &str, logical_id: &str) -> Option<String> {
+    stack_tab_console_url(stack_id, "events", Some(logical_id))
+}
+
+pub fn validate_region(region: Option<&str>, partition: Option<Partition>) -> anyhow::Result<()> {
+    // Without a region the SDK reports the missing configuration on the first call
+    let Some(region) = region else {
+        return Ok(());
+    };
+    // New regions and local endpoints have prefixes of their own, the SDK has the last word
+    let Some(region_partition) = Partition::from_region(region) else {
+        warn!("Unknown AWS region {region}, its partition is not checked");
+        return Ok(());
+    };
+    match partition {
+        Some(partition) if partition != region_partition => bail!(
+            "Region {region} belongs to the {} partition, not to the configured {} partition",
+            region_partition.name(),
+            partition.name()
+        ),
+        _ => Ok(()),
+    }
+}
+
+// Services launch in the aws partition first, so types are only checked against the registry
+// of the target region in the other partitions
+pub async fn check_resource_types(
+    client: &impl CloudFormationApi,
+    template: &str,
+) -> anyhow::Result<()> {
+    let Some(region) = client.region() else {
+        return Ok(());
+    };
+    if Partition::from_region(region).is_none_or(|partition| partition == Partition::Aws) {
+        return Ok(());
+    }
+
+    let template: Value = serde_json::from_str(template)?;
+    let resource_types: BTreeSet<_> = template
+        .get("Resources")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flat_map(|resources| resources.values())
+        .filter_map(|resource| resource.get("Type").and_then(Value::as_str))
+        .filter(|resource_type| resource_type.starts_with("AWS::"))
+        .collect();
+    let mut unavailable = Vec::new();
+    for resource_type in resource_types {
+        debug!("Check availability of {resource_type} in {region}");
+        if client.resource_schema(resource_type).await?.is_none() {
+            unavailable.push(resource_type);
+        }
+    }
+    if !unavailable.is_empty() {
+        bail!(
+            "Resource types not available in {region}: {}",
+            unavailable.join(", ")
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,175 @@
+use anyhow::{bail, Context};
+use clap::Args;
+use serde::Deserialize;
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+};
+use tracing::debug;
+
+use crate::error::PklformationError;
+
+const PKL_EXEC_ENV: &str = "PKL_EXEC";
+const DEFAULT_PKL_EXEC: &str = "pkl";
+// --project-dir was added in Pkl 0.25
+const MIN_PKL_VERSION: [u64; 3] = [0, 25, 0];
+
+static CHECKED_EXECUTABLES: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct PklOptions {
+    /// Property forwarded to pkl eval, readable in templates with read("prop:NAME")
+    #[arg(long = "pkl-property", value_name = "NAME=VALUE", value_parser = parse_key_value)]
+    pub properties: Vec<(String, String)>,
+    /// Environment variable set for pkl eval, readable in templates with read("env:NAME")
+    #[arg(long = "pkl-env", value_name = "NAME=VALUE", value_parser = parse_key_value)]
+    pub env_vars: Vec<(String, String)>,
+    /// Environment variable inherited by pkl eval from the caller, once any is set pkl sees only
+    /// the variables set with --pkl-env or listed here
+    #[arg(long = "pkl-inherit-env", value_name = "NAME")]
+    pub inherit_env: Vec<String>,
+    #[arg(skip)]
+    pub executable: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PklConfig {
+    pub env: BTreeMap<String, String>,
+    pub inherit_env: Vec<String>,
+    pub executable: Option<PathBuf>,
+}
+
+impl PklOptions {
+    // Command line values take precedence over the project file ones
+    pub fn with_config(&self, config: &PklConfig) -> Self {
+        let mut env_vars = config.env.clone();
+        env_vars.extend(self.env_vars.iter().cloned());
+        let mut inherit_env = config.inherit_env.clone();
+        for name in &self.inherit_env {
+            if !inherit_env.contains(name) {
+                inherit_env.push(name.clone());
+            }
+        }
+        Self {
+            properties: self.properties.clone(),
+            env_vars: env_vars.into_iter().collect(),
+            inherit_env,
+            executable: self.executable.clone().or(config.executable.clone()),
+        }
+    }
+
+    pub fn executable(&self) -> PathBuf {
+        self.executable
+            .clone()
+            .or_else(|| std::env::var_os(PKL_EXEC_ENV).map(PathBuf::from))
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_PKL_EXEC))
+    }
+
+    fn env_var_args(&self) -> Vec<String> {
+        let mut env_vars: BTreeMap<_, _> = self
+            .inherit_env
+            .iter()
+            .filter_map(|name| match std::env::var(name) {
+                Ok(value) => Some((name.clone(), value)),
+                Err(_) => {
+                    debug!("Environment variable {name} is not set, not passed to pkl");
+                    None
+                }
+            })
+            .collect();
+        env_vars.extend(self.env_vars.iter().cloned());
+        env_vars
+            .into_iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect()
+    }
+}
+
+pub fn parse_key_value(arg: &str) -> Result<(String, String), String> {
+    arg.split_once('=')
+        .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        .ok_or_else(|| format!("expected NAME=VALUE, got {arg}"))
+}
+
+fn parse_version(version: &str) -> Vec<u64> {
+    version
+        .split(['.', '-', '+'])
+        .take(3)
+        .map(|part| part.parse().unwrap_or_default())
+        .collect()
+}
+
+pub fn check_executable(executable: &Path) -> anyhow::Result<()> {
+    let mut checked = CHECKED_EXECUTABLES.lock().unwrap();
+    if checked.contains(executable) {
+        return Ok(());
+    }
+
+    let version_output = match Command::new(executable).arg("--version").output() {
+        Ok(version_output) => version_output,
+        Err(err) if err.kind() == ErrorKind::NotFound => bail!(
+            "pkl executable {} not found: install Pkl (https://pkl-lang.org) or set its path with --pkl-bin, {PKL_EXEC_ENV} or pkl.executable in the project file",
+            executable.display()
+        ),
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("Unable to run pkl executable {}", executable.display()))
+        }
+    };
+    let version_output = String::from_utf8_lossy(&version_output.stdout);
+    debug!("pkl version: {version_output}");
+    // The output looks like "Pkl 0.25.3 (macOS 14.3, native)"
+    let version = version_output
+        .split_whitespace()
+        .nth(1)
+        .with_context(|| format!("Unexpected pkl version output: {version_output}"))?;
+    if parse_version(version) < MIN_PKL_VERSION.to_vec() {
+        bail!(
+            "pkl {version} at {} is too old, pklformation requires pkl {} or later",
+            executable.display(),
+            MIN_PKL_VERSION.map(|part| part.to_string()).join(".")
+        );
+    }
+
+    checked.insert(executable.to_path_buf());
+    Ok(())
+}
+
+pub fn eval(path: &Path, options: &PklOptions) -> anyhow::Result<String> {
+    let executable = options.executable();
+    check_executable(&executable)?;
+    let project_dir = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let mut command = Command::new(executable);
+    command.args([
+        "eval",
+        path.to_str().unwrap(),
+        "--project-dir",
+        project_dir.to_str().unwrap(),
+        "--format",
+        "json",
+    ]);
+    for (name, value) in &options.properties {
+        command.args(["--property", &format!("{name}={value}")]);
+    }
+    for env_var in options.env_var_args() {
+        command.args(["--env-var", &env_var]);
+    }
+    let eval_result = command.output()?;
+
+    debug!("pkl eval result: {eval_result:?}");
+    if !eval_result.status.success() {
+        bail!(PklformationError::Template(String::from_utf8(
+            eval_result.stderr
+        )?));
+    }
+
+    Ok(String::from_utf8(eval_result.stdout)?)
+}
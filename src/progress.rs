@@ -0,0 +1,419 @@
+use aws_sdk_cloudformation::types::{ChangeSetStatus, StackEvent, StackStatus};
+use chrono::Utc;
+use clap::ValueEnum;
+use serde_json::{json, Value};
+use spinners::{Spinner, Spinners, Stream};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::OpenOptions,
+    io::{IsTerminal, Write},
+    path::Path,
+    sync::{
+        mpsc::{self, RecvTimeoutError, Sender},
+        Arc, Mutex, OnceLock,
+    },
+    thread,
+    time::Duration,
+};
+use tracing::{info, warn};
+
+use crate::{
+    events_log::{record, NewEvents},
+    timings::format_duration,
+};
+
+// Statuses of the stack's own event starting an operation, the events before are not counted
+const OPERATION_STARTS: [&str; 4] = [
+    "CREATE_IN_PROGRESS",
+    "UPDATE_IN_PROGRESS",
+    "DELETE_IN_PROGRESS",
+    "IMPORT_IN_PROGRESS",
+];
+
+const SPINNER_ENV: &str = "PKLFORMATION_SPINNER";
+const LOG_INTERVAL: Duration = Duration::from_secs(30);
+
+static SPINNER_STYLE: OnceLock<SpinnerStyle> = OnceLock::new();
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SpinnerStyle {
+    #[default]
+    Dots,
+    /// Spinner made of ASCII characters only
+    Ascii,
+    /// No spinner, the message is logged every 30 seconds instead
+    Log,
+}
+
+impl SpinnerStyle {
+    // The flag takes precedence over the environment
+    pub fn apply(style: Option<SpinnerStyle>) -> anyhow::Result<()> {
+        let style = match (style, std::env::var(SPINNER_ENV)) {
+            (Some(style), _) => style,
+            (None, Ok(value)) => SpinnerStyle::from_str(&value, true).map_err(|_| {
+                anyhow::anyhow!("Invalid {SPINNER_ENV} {value}, expected dots, ascii or log")
+            })?,
+            (None, Err(_)) => SpinnerStyle::default(),
+        };
+        let _ = SPINNER_STYLE.set(style);
+        Ok(())
+    }
+
+    fn spinner(self) -> Spinners {
+        match self {
+            SpinnerStyle::Ascii => Spinners::Line,
+            _ => Spinners::Dots9,
+        }
+    }
+}
+
+enum Indicator {
+    Spinner(Spinner),
+    // Sends the new messages to the logging thread, dropping it stops the thread
+    Log(Option<Sender<String>>),
+}
+
+impl Indicator {
+    fn start(style: SpinnerStyle, message: String) -> Option<Self> {
+        if style == SpinnerStyle::Log {
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || {
+                let mut message = message;
+                info!("{message}");
+                loop {
+                    match receiver.recv_timeout(LOG_INTERVAL) {
+                        Ok(new_message) => message = new_message,
+                        Err(RecvTimeoutError::Timeout) => info!("{message}"),
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+            });
+            return Some(Indicator::Log(Some(sender)));
+        }
+        // Spinners only draw on terminals, their control characters corrupt piped output
+        let enabled = std::io::stdout().is_terminal() && std::io::stderr().is_terminal();
+        enabled.then(|| {
+            Indicator::Spinner(Spinner::with_stream(
+                style.spinner(),
+                message,
+                Stream::Stderr,
+            ))
+        })
+    }
+
+    fn stop(&mut self) {
+        match self {
+            Indicator::Spinner(spinner) => spinner.stop(),
+            Indicator::Log(sender) => {
+                sender.take();
+            }
+        }
+    }
+}
+
+pub struct Progress(Option<Indicator>);
+
+impl Progress {
+    pub fn start(message: String) -> Self {
+        Self(Indicator::start(Self::style(), message))
+    }
+
+    fn style() -> SpinnerStyle {
+        SPINNER_STYLE.get().copied().unwrap_or_default()
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(indicator) = self.0.as_mut() {
+            indicator.stop();
+        }
+    }
+
+    // The spinner doesn't clear the line, a shorter message would leave the end of the previous one.
+    // The logged message is only replaced, to keep logging at the same pace.
+    pub fn restart(&mut self, message: String) {
+        match self.0.as_mut() {
+            Some(Indicator::Spinner(spinner)) => {
+                spinner.stop();
+                eprint!("\r\x1B[2K");
+                *spinner = Spinner::with_stream(Self::style().spinner(), message, Stream::Stderr);
+            }
+            Some(Indicator::Log(Some(sender))) => {
+                let _ = sender.send(message);
+            }
+            Some(Indicator::Log(None)) | None => {}
+        }
+    }
+}
+
+// The stack's own event starting the operation, by the name or the id of the stack
+pub fn starts_operation(stack: &str, event: &StackEvent) -> bool {
+    (event.logical_resource_id() == Some(stack) || event.physical_resource_id() == Some(stack))
+        && event
+            .resource_status()
+            .is_some_and(|status| OPERATION_STARTS.contains(&status.as_str()))
+}
+
+// The last event of a resource, with its type and when it happened for the estimates
+struct ResourceState {
+    status: String,
+    resource_type: Option<String>,
+    since: Option<f64>,
+}
+
+// Latest status of each resource touched by the operation in progress, from the stack events
+#[derive(Default)]
+pub struct ResourceCounters {
+    statuses: HashMap<String, ResourceState>,
+    last_event_id: Option<String>,
+    new_events: Vec<(String, ResourceState)>,
+    newest_event_id: Option<String>,
+}
+
+impl ResourceCounters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Pages come most recent first, returns false once the events already counted or the start
+    // of the operation are reached
+    pub fn add_page(&mut self, stack_name: &str, events: &[StackEvent]) -> bool {
+        for event in events {
+            if self.newest_event_id.is_none() {
+                self.newest_event_id = event.event_id().map(str::to_string);
+            }
+            if event.event_id().is_some() && event.event_id() == self.last_event_id.as_deref() {
+                return false;
+            }
+            let status = event.resource_status().map(|status| status.as_str());
+            if event.logical_resource_id() == Some(stack_name)
+                || event.physical_resource_id() == Some(stack_name)
+            {
+                if starts_operation(stack_name, event) {
+                    return false;
+                }
+                continue;
+            }
+            if let (Some(logical_id), Some(status)) = (event.logical_resource_id(), status) {
+                self.new_events.push((
+                    logical_id.to_string(),
+                    ResourceState {
+                        status: status.to_string(),
+                        resource_type: event.resource_type().map(str::to_string),
+                        since: event.timestamp().map(|timestamp| timestamp.as_secs_f64()),
+                    },
+                ));
+            }
+        }
+        true
+    }
+
+    // Applies the events of the pages added since the last refresh, the oldest first
+    pub fn refresh(&mut self) {
+        for (logical_id, state) in self.new_events.drain(..).rev() {
+            self.statuses.insert(logical_id, state);
+        }
+        if let Some(event_id) = self.newest_event_id.take() {
+            self.last_event_id = Some(event_id);
+        }
+    }
+
+    // A refresh interrupted by an error is done again from the last complete one
+    pub fn discard(&mut self) {
+        self.new_events.clear();
+        self.newest_event_id = None;
+    }
+
+    // Every resource touched by the operation so far is done
+    pub fn all_complete(&self) -> bool {
+        !self.statuses.is_empty()
+            && !self
+                .statuses
+                .values()
+                .any(|state| state.status.ends_with("_IN_PROGRESS"))
+    }
+
+    pub fn type_in_progress(&self, resource_type: &str) -> bool {
+        self.statuses.values().any(|state| {
+            state.resource_type.as_deref() == Some(resource_type)
+                && state.status.ends_with("_IN_PROGRESS")
+        })
+    }
+
+    // The type of the resources in progress and for how long they have been, at `now`
+    pub fn in_progress(&self, now: f64) -> Vec<(String, Duration)> {
+        self.statuses
+            .values()
+            .filter(|state| state.status.ends_with("_IN_PROGRESS"))
+            .filter_map(|state| {
+                Some((
+                    state.resource_type.clone()?,
+                    Duration::from_secs_f64((now - state.since?).max(0.0)),
+                ))
+            })
+            .collect()
+    }
+
+    // Like `CREATE_IN_PROGRESS: 12 | CREATE_COMPLETE: 34 | FAILED: 0 (3m12s)`
+    pub fn summary(&self, elapsed: Duration) -> Option<String> {
+        if self.statuses.is_empty() {
+            return None;
+        }
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        let mut failed = 0;
+        for ResourceState { status, .. } in self.statuses.values() {
+            if status.ends_with("_FAILED") {
+                failed += 1;
+            } else {
+                *counts.entry(status).or_default() += 1;
+            }
+        }
+        let mut counts: Vec<_> = counts
+            .into_iter()
+            .map(|(status, count)| format!("{status}: {count}"))
+            .collect();
+        counts.push(format!("FAILED: {failed}"));
+        Some(format!(
+            "{} ({})",
+            counts.join(" | "),
+            format_duration(elapsed)
+        ))
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ProgressFormat {
+    #[default]
+    Spinner,
+    Ndjson,
+}
+
+struct ProgressWriter {
+    out: Box<dyn Write + Send>,
+    events: NewEvents,
+}
+
+// One JSON object per line and per state transition, for the wrappers following a deployment
+#[derive(Clone)]
+pub struct ProgressStream(Arc<Mutex<ProgressWriter>>);
+
+impl ProgressStream {
+    pub fn open(path: Option<&Path>) -> anyhow::Result<Self> {
+        let out: Box<dyn Write + Send> = match path {
+            // Appending also works with named pipes
+            Some(path) => Box::new(OpenOptions::new().create(true).append(true).open(path)?),
+            None => Box::new(std::io::stdout()),
+        };
+        Ok(Self(Arc::new(Mutex::new(ProgressWriter {
+            out,
+            events: NewEvents::new()?,
+        }))))
+    }
+
+    fn emit(&self, kind: &str, mut record: Value) {
+        record["type"] = json!(kind);
+        record["emittedAt"] = json!(Utc::now().to_rfc3339());
+        let mut writer = self.0.lock().unwrap();
+        if let Err(err) = writeln!(writer.out, "{record}").and_then(|_| writer.out.flush()) {
+            warn!("Unable to write the progress event: {err}");
+        }
+    }
+
+    pub fn change_set_created(&self, stack: &str, change_set_id: Option<&str>) {
+        self.emit(
+            "changeSetCreated",
+            json!({ "stackName": stack, "changeSetId": change_set_id }),
+        );
+    }
+
+    pub fn change_set_status(&self, change_set_id: &str, status: &ChangeSetStatus, reason: &str) {
+        self.emit(
+            "changeSetStatus",
+            json!({ "changeSetId": change_set_id, "status": status.as_str(), "reason": reason }),
+        );
+    }
+
+    pub fn stack_events(&self, events: &[StackEvent], nested_stack_path: Option<&str>) {
+        let records: Vec<_> = {
+            let mut writer = self.0.lock().unwrap();
+            writer
+                .events
+                .filter(events)
+                .into_iter()
+                .map(|event| record(event, nested_stack_path))
+                .collect()
+        };
+        for record in records {
+            self.emit("resourceEvent", record);
+        }
+    }
+
+    pub fn stack_status(&self, stack: &str, status: &StackStatus, reason: &str) {
+        self.emit(
+            "stackStatus",
+            json!({ "stackName": stack, "status": status.as_str(), "reason": reason }),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use aws_sdk_cloudformation::{primitives::DateTime, types::ResourceStatus};
+
+    use super::*;
+
+    fn event(id: &str, logical_id: &str, resource_type: &str, status: &str) -> StackEvent {
+        StackEvent::builder()
+            .event_id(id)
+            .logical_resource_id(logical_id)
+            .resource_type(resource_type)
+            .resource_status(ResourceStatus::from(status))
+            .timestamp(DateTime::from_secs(100))
+            .build()
+    }
+
+    #[test]
+    fn counters_follow_the_events_since_the_operation_started() {
+        let mut counters = ResourceCounters::new();
+        // Most recent first, the events before the start of the operation are ignored
+        counters.add_page(
+            "stack",
+            &[
+                event("4", "Queue", "AWS::SQS::Queue", "UPDATE_IN_PROGRESS"),
+                event("3", "Topic", "AWS::SNS::Topic", "UPDATE_COMPLETE"),
+                event(
+                    "2",
+                    "stack",
+                    "AWS::CloudFormation::Stack",
+                    "UPDATE_IN_PROGRESS",
+                ),
+                event("1", "Old", "AWS::SNS::Topic", "CREATE_IN_PROGRESS"),
+            ],
+        );
+        counters.refresh();
+
+        assert!(!counters.all_complete());
+        assert!(counters.type_in_progress("AWS::SQS::Queue"));
+        let in_progress = counters.in_progress(160.0);
+        assert_eq!(
+            in_progress,
+            [("AWS::SQS::Queue".to_string(), Duration::from_secs(60))]
+        );
+
+        // The next poll only reads the events up to the last one counted
+        let more = counters.add_page(
+            "stack",
+            &[
+                event("5", "Queue", "AWS::SQS::Queue", "UPDATE_COMPLETE"),
+                event("4", "Queue", "AWS::SQS::Queue", "UPDATE_IN_PROGRESS"),
+            ],
+        );
+        counters.refresh();
+
+        assert!(!more);
+        assert!(counters.all_complete());
+        assert_eq!(
+            counters.summary(Duration::ZERO).unwrap(),
+            "UPDATE_COMPLETE: 2 | FAILED: 0 (0m00s)"
+        );
+    }
+}
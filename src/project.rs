@@ -0,0 +1,249 @@
+use anyhow::{bail, Context};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+use tracing::{debug, warn};
+
+use crate::{
+    approval::ApprovalConfig,
+    aws_client::{ChangeSetOptions, SdkConfig, SdkOptions},
+    notifications::NotificationTarget,
+    partition::Partition,
+    pkl::{self, PklConfig, PklOptions},
+    validation::NamingRules,
+};
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StackConfig {
+    pub template: Option<PathBuf>,
+    pub parameters: BTreeMap<String, String>,
+    pub tags: BTreeMap<String, String>,
+    pub region: Option<String>,
+    pub partition: Option<Partition>,
+    pub depends_on: Vec<String>,
+    pub immutable_resources: Vec<String>,
+    pub protected: bool,
+    pub inputs: StackInputs,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StackInputs {
+    // Pkl property name to "stack.OutputKey"
+    pub from_stack: BTreeMap<String, String>,
+}
+
+// Tags with an empty value are as good as missing
+pub fn missing_tags(required_tags: &[String], tags: &BTreeMap<String, String>) -> Vec<String> {
+    required_tags
+        .iter()
+        .filter(|tag| tags.get(*tag).is_none_or(|value| value.is_empty()))
+        .cloned()
+        .collect()
+}
+
+pub struct StackInput {
+    pub property: String,
+    pub stack: String,
+    pub output: String,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StackOverlay {
+    pub parameters: BTreeMap<String, String>,
+    pub tags: BTreeMap<String, String>,
+    pub region: Option<String>,
+    pub partition: Option<Partition>,
+}
+
+impl StackOverlay {
+    fn apply(&self, stack: &mut StackConfig) {
+        stack.parameters.extend(self.parameters.clone());
+        stack.tags.extend(self.tags.clone());
+        if self.region.is_some() {
+            stack.region.clone_from(&self.region);
+        }
+        if self.partition.is_some() {
+            stack.partition = self.partition;
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EnvironmentConfig {
+    #[serde(flatten)]
+    pub overlay: StackOverlay,
+    pub stack_suffix: String,
+    pub stacks: BTreeMap<String, StackOverlay>,
+    pub approval: Option<ApprovalConfig>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Project {
+    pub naming: NamingRules,
+    pub protected_resource_types: Vec<String>,
+    pub required_tags: Vec<String>,
+    pub notifications: Vec<NotificationTarget>,
+    pub approval: Option<ApprovalConfig>,
+    pub stacks: BTreeMap<String, StackConfig>,
+    pub environments: BTreeMap<String, EnvironmentConfig>,
+    pub pkl: PklConfig,
+    pub sdk: SdkConfig,
+    #[serde(skip)]
+    root: PathBuf,
+    #[serde(skip)]
+    environment: EnvironmentConfig,
+}
+
+impl Project {
+    // The project file itself is always evaluated with the given pkl executable, or the default one
+    pub fn load(path: &Path, pkl_executable: Option<PathBuf>) -> anyhow::Result<Self> {
+        if !path.exists() {
+            debug!("No project file found at {}", path.display());
+            let mut project = Self::default();
+            project.pkl.executable = pkl_executable;
+            return Ok(project);
+        }
+
+        let pkl_options = PklOptions {
+            executable: pkl_executable.clone(),
+            ..PklOptions::default()
+        };
+        let project = pkl::eval(path, &pkl_options)?;
+        let mut project: Self = serde_json::from_str(&project)
+            .with_context(|| format!("Invalid project file {}", path.display()))?;
+        project.root = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        if pkl_executable.is_some() {
+            project.pkl.executable = pkl_executable;
+        }
+        Ok(project)
+    }
+
+    // Environment values override the stack ones, and stack specific environment values override both
+    pub fn with_environment(mut self, environment: &str) -> anyhow::Result<Self> {
+        let Some(config) = self.environments.get(environment) else {
+            bail!("Environment {environment} is not declared in the project file");
+        };
+        for (name, stack) in self.stacks.iter_mut() {
+            config.overlay.apply(stack);
+            if let Some(overlay) = config.stacks.get(name) {
+                overlay.apply(stack);
+            }
+        }
+        for name in config.stacks.keys() {
+            if !self.stacks.contains_key(name) {
+                warn!("Environment {environment} overrides {name}, which is not declared in the project");
+            }
+        }
+        self.environment = config.clone();
+        Ok(self)
+    }
+
+    pub fn stack_name(&self, stack: &str) -> String {
+        format!("{stack}{}", self.environment.stack_suffix)
+    }
+
+    pub fn stack(&self, name: &str) -> Option<&StackConfig> {
+        self.stacks.get(name)
+    }
+
+    pub fn region(&self, stack: &str) -> Option<&str> {
+        self.stack(stack)
+            .and_then(|config| config.region.as_deref())
+            .or(self.default_region())
+    }
+
+    // For the commands working on the whole account rather than on a stack of the project
+    pub fn default_region(&self) -> Option<&str> {
+        self.environment.overlay.region.as_deref()
+    }
+
+    pub fn partition(&self, stack: &str) -> Option<Partition> {
+        self.stack(stack)
+            .and_then(|config| config.partition)
+            .or(self.environment.overlay.partition)
+    }
+
+    pub fn template(&self, stack: &str, template: Option<&Path>) -> anyhow::Result<PathBuf> {
+        if let Some(template) = template {
+            return Ok(template.to_path_buf());
+        }
+        let template = self
+            .stack(stack)
+            .and_then(|config| config.template.as_ref())
+            .with_context(|| {
+                format!("No template for stack {stack}: pass --template or declare it in the project file")
+            })?;
+        Ok(self.root.join(template))
+    }
+
+    pub fn pkl_options(&self, options: &PklOptions) -> PklOptions {
+        options.with_config(&self.pkl)
+    }
+
+    pub fn sdk_options(&self, options: &SdkOptions) -> anyhow::Result<SdkOptions> {
+        Ok(options.with_config(&self.sdk)?)
+    }
+
+    pub fn stack_inputs(&self, stack: &str) -> anyhow::Result<Vec<StackInput>> {
+        let Some(config) = self.stack(stack) else {
+            return Ok(Vec::new());
+        };
+        config
+            .inputs
+            .from_stack
+            .iter()
+            .map(|(property, reference)| {
+                let Some((source, output)) = reference.split_once('.') else {
+                    bail!("Input {property} of stack {stack} must reference an output as stack.OutputKey, got {reference}");
+                };
+                if !self.stacks.contains_key(source) {
+                    bail!("Input {property} of stack {stack} references {source}, which is not declared in the project");
+                }
+                Ok(StackInput {
+                    property: property.clone(),
+                    stack: source.to_string(),
+                    output: output.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    pub fn immutable_resources(&self, stack: &str) -> Vec<String> {
+        self.stack(stack)
+            .map(|config| config.immutable_resources.clone())
+            .unwrap_or_default()
+    }
+
+    // An environment, like production, can require its own approval
+    pub fn approval(&self) -> Option<ApprovalConfig> {
+        self.environment
+            .approval
+            .clone()
+            .or_else(|| self.approval.clone())
+    }
+
+    pub fn is_protected(&self, stack: &str) -> bool {
+        self.stack(stack).is_some_and(|config| config.protected)
+    }
+
+    pub fn change_set_options(&self, stack: &str) -> ChangeSetOptions {
+        self.stack(stack)
+            .map(|config| ChangeSetOptions {
+                parameters: config.parameters.clone(),
+                tags: config.tags.clone(),
+                ..Default::default()
+            })
+            .unwrap_or_else(|| ChangeSetOptions {
+                parameters: self.environment.overlay.parameters.clone(),
+                tags: self.environment.overlay.tags.clone(),
+                ..Default::default()
+            })
+    }
+}
@@ -0,0 +1,61 @@
+use anyhow::bail;
+use aws_sdk_cloudformation::types::ChangeSetType;
+use tracing::{debug, warn};
+
+use crate::{cloudformation_api::CloudFormationApi, error::PklformationError};
+
+const CLOUDFORMATION_SERVICE_CODE: &str = "cloudformation";
+const STACK_COUNT_QUOTA: &str = "Stack count";
+const DEFAULT_STACK_COUNT: f64 = 2000.0;
+// Not exposed by Service Quotas, this is the documented CloudFormation limit
+const CHANGE_SETS_PER_STACK: usize = 1000;
+const WARNING_RATIO: f64 = 0.9;
+
+async fn stack_count_quota(client: &impl CloudFormationApi) -> anyhow::Result<f64> {
+    match client
+        .service_quota(CLOUDFORMATION_SERVICE_CODE, STACK_COUNT_QUOTA)
+        .await
+    {
+        Ok(quota) => Ok(quota.unwrap_or(DEFAULT_STACK_COUNT)),
+        Err(err)
+            if matches!(
+                err.downcast_ref::<PklformationError>(),
+                Some(PklformationError::AccessDenied(_))
+            ) =>
+        {
+            warn!("{err}, assuming the default stack count quota of {DEFAULT_STACK_COUNT}");
+            Ok(DEFAULT_STACK_COUNT)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+fn check_usage(usage: usize, quota: f64, what: &str) -> anyhow::Result<()> {
+    debug!("{what}: {usage} of {quota}");
+    if usage as f64 >= quota {
+        bail!("The quota of {quota} {what} is reached ({usage} in use), raise it or clean up before deploying");
+    }
+    if usage as f64 >= quota * WARNING_RATIO {
+        warn!("{usage} of {quota} {what} are in use, the quota will soon be reached");
+    }
+    Ok(())
+}
+
+pub async fn preflight(
+    client: &impl CloudFormationApi,
+    stack: &str,
+    change_set_type: &ChangeSetType,
+) -> anyhow::Result<()> {
+    if *change_set_type == ChangeSetType::Create {
+        let quota = stack_count_quota(client).await?;
+        let stacks = client.count_active_stacks().await?;
+        return check_usage(stacks, quota, "stacks in the account");
+    }
+
+    let change_sets = client.list_change_sets(stack).await?.len();
+    check_usage(
+        change_sets,
+        CHANGE_SETS_PER_STACK as f64,
+        &format!("change sets of stack {stack}"),
+    )
+}
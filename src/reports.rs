@@ -0,0 +1,97 @@
+use clap::ValueEnum;
+use serde_json::json;
+use std::{collections::BTreeSet, path::Path};
+
+use crate::validation::Violation;
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    /// SARIF 2.1.0, for code scanning
+    Sarif,
+    /// JUnit XML, for the test reports of CI systems
+    Junit,
+}
+
+impl ReportFormat {
+    pub fn render(&self, template: &Path, violations: &[Violation]) -> String {
+        match self {
+            ReportFormat::Sarif => sarif(template, violations),
+            ReportFormat::Junit => junit(template, violations),
+        }
+    }
+}
+
+fn sarif(template: &Path, violations: &[Violation]) -> String {
+    let rules: BTreeSet<_> = violations.iter().map(|violation| violation.rule).collect();
+    let results: Vec<_> = violations
+        .iter()
+        .map(|violation| {
+            json!({
+                "ruleId": violation.rule,
+                "level": "error",
+                "message": { "text": violation.message },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": template.to_string_lossy() },
+                    },
+                    "logicalLocations": [{ "fullyQualifiedName": violation.location }],
+                }],
+            })
+        })
+        .collect();
+    let report = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "pklformation",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules.iter().map(|rule| json!({ "id": rule })).collect::<Vec<_>>(),
+                },
+            },
+            "results": results,
+        }],
+    });
+    serde_json::to_string_pretty(&report).expect("SARIF report serializes to JSON")
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn junit(template: &Path, violations: &[Violation]) -> String {
+    let suite = xml_escape(&template.to_string_lossy());
+    let mut testcases = Vec::new();
+    if violations.is_empty() {
+        testcases.push(format!(
+            r#"    <testcase classname="{suite}" name="template is valid"/>"#
+        ));
+    }
+    for violation in violations {
+        testcases.push(format!(
+            r#"    <testcase classname="{suite}" name="{} {}">
+      <failure message="{}" type="{}"/>
+    </testcase>"#,
+            xml_escape(violation.rule),
+            xml_escape(&violation.location),
+            xml_escape(&violation.message),
+            xml_escape(violation.rule),
+        ));
+    }
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="pklformation validate" tests="{tests}" failures="{failures}">
+  <testsuite name="{suite}" tests="{tests}" failures="{failures}">
+{testcases}
+  </testsuite>
+</testsuites>"#,
+        tests = testcases.len(),
+        failures = violations.len(),
+        testcases = testcases.join("\n"),
+    )
+}
@@ -0,0 +1,79 @@
+use std::{io::IsTerminal, path::PathBuf, process::Command};
+
+use tracing::{info, warn};
+
+use crate::display::Display;
+
+const DEFAULT_PROFILE: &str = "default";
+// Keys of the profiles getting their credentials from IAM Identity Center
+const SSO_KEYS: [&str; 2] = ["sso_session", "sso_start_url"];
+
+// The profile the SDK resolves the credentials with
+fn profile() -> String {
+    std::env::var("AWS_PROFILE")
+        .ok()
+        .filter(|profile| !profile.is_empty())
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+fn config_path() -> Option<PathBuf> {
+    std::env::var_os("AWS_CONFIG_FILE")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".aws").join("config"))
+        })
+}
+
+// The default profile is `[default]` in the config file, the others `[profile name]`
+fn uses_sso(profile: &str) -> bool {
+    let Some(config) = config_path().and_then(|path| std::fs::read_to_string(path).ok()) else {
+        return false;
+    };
+    let mut in_profile = false;
+    for line in config.lines().map(str::trim) {
+        if let Some(section) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            let section = section.trim();
+            in_profile = section == format!("profile {profile}")
+                || (profile == DEFAULT_PROFILE && section == DEFAULT_PROFILE);
+        } else if in_profile {
+            let key = line.split('=').next().unwrap_or_default().trim();
+            if SSO_KEYS.contains(&key) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+// Offers to run `aws sso login` when the profile uses SSO and a user is there to answer, returns
+// whether the login succeeded
+pub fn offer_login() -> bool {
+    let profile = profile();
+    if !uses_sso(&profile) || !std::io::stdin().is_terminal() {
+        return false;
+    }
+    let prompt = format!("The SSO session of profile {profile} may have expired, run `aws sso login --profile {profile}`?");
+    if !Display::new().ask_confirm(&prompt) {
+        return false;
+    }
+    match Command::new("aws")
+        .args(["sso", "login", "--profile", &profile])
+        .status()
+    {
+        Ok(status) if status.success() => {
+            info!("Logged in with profile {profile}");
+            true
+        }
+        Ok(status) => {
+            warn!("aws sso login failed with {status}");
+            false
+        }
+        Err(err) => {
+            warn!("Unable to run aws sso login: {err}");
+            false
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use anyhow::{bail, Context};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use tracing::{debug, warn};
+
+use crate::{pkl::PklOptions, project::Project, template_format};
+
+pub struct StackGraph {
+    dependencies: BTreeMap<String, BTreeSet<String>>,
+    imported_exports: BTreeMap<(String, String), BTreeSet<String>>,
+}
+
+fn collect_imports(value: &Value, imports: &mut Vec<String>) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                match (key.as_str(), value) {
+                    ("Fn::ImportValue", Value::String(name)) => imports.push(name.clone()),
+                    _ => collect_imports(value, imports),
+                }
+            }
+        }
+        Value::Array(array) => array
+            .iter()
+            .for_each(|value| collect_imports(value, imports)),
+        _ => {}
+    }
+}
+
+pub fn template_exports(template: &Value) -> Vec<String> {
+    template
+        .get("Outputs")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flat_map(|outputs| outputs.values())
+        .filter_map(|output| output.pointer("/Export/Name").and_then(Value::as_str))
+        .map(str::to_owned)
+        .collect()
+}
+
+pub fn template_imports(template: &Value) -> Vec<String> {
+    let mut imports = Vec::new();
+    collect_imports(template, &mut imports);
+    imports
+}
+
+impl StackGraph {
+    pub fn from_project(project: &Project) -> anyhow::Result<Self> {
+        let mut dependencies: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        let mut exporters = BTreeMap::new();
+        let mut imports = BTreeMap::new();
+        let mut imported_exports: BTreeMap<(String, String), BTreeSet<String>> = BTreeMap::new();
+
+        for (name, stack) in &project.stacks {
+            let stack_dependencies = dependencies.entry(name.clone()).or_default();
+            for dependency in &stack.depends_on {
+                if project.stacks.contains_key(dependency) {
+                    stack_dependencies.insert(dependency.clone());
+                } else {
+                    warn!("Stack {name} depends on {dependency}, which is not declared in the project");
+                }
+            }
+
+            // The graph only needs the exports and imports, inputs are not fetched
+            let mut pkl_options = project.pkl_options(&PklOptions::default());
+            for input in project.stack_inputs(name)? {
+                stack_dependencies.insert(input.stack.clone());
+                pkl_options
+                    .properties
+                    .push((input.property, format!("{}.{}", input.stack, input.output)));
+            }
+            let template: Value = serde_json::from_str(&template_format::load(
+                &project.template(name, None)?,
+                None,
+                &pkl_options,
+            )?)
+            .with_context(|| format!("Invalid template JSON for stack {name}"))?;
+            for export in template_exports(&template) {
+                exporters.insert(export, name.clone());
+            }
+            imports.insert(name.clone(), template_imports(&template));
+        }
+
+        for (name, stack_imports) in imports {
+            for import in stack_imports {
+                match exporters.get(&import) {
+                    Some(exporter) if *exporter != name => {
+                        debug!("Stack {name} imports {import} from {exporter}");
+                        dependencies
+                            .entry(name.clone())
+                            .or_default()
+                            .insert(exporter.clone());
+                        imported_exports
+                            .entry((name.clone(), exporter.clone()))
+                            .or_default()
+                            .insert(import);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(Self {
+            dependencies,
+            imported_exports,
+        })
+    }
+
+    // Edges go from a stack to each stack it depends on, labeled with the imported exports
+    fn edges(&self) -> impl Iterator<Item = (&str, &str, Option<String>)> {
+        self.dependencies
+            .iter()
+            .flat_map(move |(name, dependencies)| {
+                dependencies.iter().map(move |dependency| {
+                    let label = self
+                        .imported_exports
+                        .get(&(name.clone(), dependency.clone()))
+                        .map(|exports| exports.iter().cloned().collect::<Vec<_>>().join(", "));
+                    (name.as_str(), dependency.as_str(), label)
+                })
+            })
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph stacks {\n    rankdir=LR;\n");
+        for name in self.dependencies.keys() {
+            dot.push_str(&format!("    \"{name}\";\n"));
+        }
+        for (name, dependency, label) in self.edges() {
+            match label {
+                Some(label) => dot.push_str(&format!(
+                    "    \"{name}\" -> \"{dependency}\" [label=\"{label}\"];\n"
+                )),
+                None => dot.push_str(&format!("    \"{name}\" -> \"{dependency}\";\n")),
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn to_mermaid(&self) -> String {
+        // Mermaid node ids cannot contain every character allowed in stack names
+        let ids: BTreeMap<&str, String> = self
+            .dependencies
+            .keys()
+            .enumerate()
+            .map(|(index, name)| (name.as_str(), format!("stack{index}")))
+            .collect();
+        let mut mermaid = String::from("graph LR\n");
+        for (name, id) in &ids {
+            mermaid.push_str(&format!("    {id}[\"{name}\"]\n"));
+        }
+        for (name, dependency, label) in self.edges() {
+            match label {
+                Some(label) => mermaid.push_str(&format!(
+                    "    {} -->|\"{label}\"| {}\n",
+                    ids[name], ids[dependency]
+                )),
+                None => mermaid.push_str(&format!("    {} --> {}\n", ids[name], ids[dependency])),
+            }
+        }
+        mermaid
+    }
+
+    pub fn deployment_order(&self) -> anyhow::Result<Vec<String>> {
+        let mut order = Vec::new();
+        let mut pending = self.dependencies.clone();
+        while !pending.is_empty() {
+            let ready: Vec<_> = pending
+                .iter()
+                .filter(|(_, dependencies)| dependencies.iter().all(|d| order.contains(d)))
+                .map(|(name, _)| name.clone())
+                .collect();
+            if ready.is_empty() {
+                bail!(
+                    "Circular dependency between stacks: {}",
+                    pending.keys().cloned().collect::<Vec<_>>().join(", ")
+                );
+            }
+            for name in ready {
+                pending.remove(&name);
+                order.push(name);
+            }
+        }
+        Ok(order)
+    }
+}
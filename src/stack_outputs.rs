@@ -0,0 +1,156 @@
+use anyhow::Context;
+use aws_sdk_cloudformation::types::Stack;
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+use std::path::Path;
+use tracing::{debug, info, warn};
+
+use crate::{
+    aws_client::AwsClient, cloudformation_api::CloudFormationApi, pkl::PklOptions, project::Project,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputsFormat {
+    #[default]
+    Dotenv,
+    Json,
+    Pkl,
+}
+
+impl OutputsFormat {
+    pub fn from_extension(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => OutputsFormat::Json,
+            Some("pkl") => OutputsFormat::Pkl,
+            _ => OutputsFormat::Dotenv,
+        }
+    }
+
+    pub fn render(&self, stack: &Stack) -> String {
+        let outputs = stack.outputs().iter().filter_map(|output| {
+            Some((
+                output.output_key()?,
+                output.output_value().unwrap_or_default(),
+            ))
+        });
+        match self {
+            OutputsFormat::Dotenv => outputs
+                .map(|(key, value)| format!("{key}={}\n", quote(value)))
+                .collect(),
+            OutputsFormat::Json => {
+                let outputs: Map<_, _> = outputs
+                    .map(|(key, value)| (key.to_string(), Value::from(value)))
+                    .collect();
+                format!("{:#}\n", Value::Object(outputs))
+            }
+            OutputsFormat::Pkl => outputs
+                .map(|(key, value)| format!("{key} = {}\n", quote(value)))
+                .collect(),
+        }
+    }
+}
+
+// Dotenv and Pkl share the double quoted string escapes
+fn quote(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{escaped}\"")
+}
+
+// The outputs of the stacks referenced by inputs.fromStack become pkl properties, the command
+// line ones still take precedence
+pub async fn pkl_options(
+    project: &Project,
+    stack: &str,
+    options: &PklOptions,
+) -> anyhow::Result<PklOptions> {
+    let mut pkl_options = project.pkl_options(options);
+    let inputs = project.stack_inputs(stack)?;
+    if inputs.is_empty() {
+        return Ok(pkl_options);
+    }
+    let mut properties = Vec::new();
+    for input in inputs {
+        let source = project.stack_name(&input.stack);
+        let client = AwsClient::new(project.region(&input.stack)).await;
+        let source_stack = client
+            .describe_stack(&source)
+            .await
+            .with_context(|| format!("Unable to read the outputs of stack {source}"))?;
+        let value = source_stack
+            .outputs()
+            .iter()
+            .find(|output| output.output_key() == Some(input.output.as_str()))
+            .and_then(|output| output.output_value())
+            .with_context(|| format!("Stack {source} has no output {}", input.output))?;
+        debug!(
+            "Input {} of stack {stack} set from {source}.{}",
+            input.property, input.output
+        );
+        properties.push((input.property, value.to_string()));
+    }
+    properties.append(&mut pkl_options.properties);
+    pkl_options.properties = properties;
+    Ok(pkl_options)
+}
+
+pub fn parse_ssm_prefix(arg: &str) -> Result<String, String> {
+    match arg.strip_prefix("ssm:") {
+        Some(prefix) if prefix.starts_with('/') => Ok(format!("{}/", prefix.trim_end_matches('/'))),
+        _ => Err("Outputs are published under an SSM path, like ssm:/myapp/".to_string()),
+    }
+}
+
+// Each output becomes the parameter {prefix}{OutputKey}, tagged with the stack it comes from
+pub async fn publish(
+    client: &impl CloudFormationApi,
+    stack: &Stack,
+    prefix: &str,
+) -> anyhow::Result<()> {
+    let stack_name = stack.stack_name().unwrap_or_default();
+    let tags = [
+        ("pklformation:stack", stack_name),
+        (
+            "pklformation:stack-id",
+            stack.stack_id().unwrap_or_default(),
+        ),
+    ];
+    for output in stack.outputs() {
+        let Some(key) = output.output_key() else {
+            continue;
+        };
+        let name = format!("{prefix}{key}");
+        // SSM parameters can't be empty
+        match output.output_value().filter(|value| !value.is_empty()) {
+            Some(value) => client
+                .put_ssm_parameter(&name, value, &tags)
+                .await
+                .with_context(|| format!("Unable to publish the output {key} to {name}"))?,
+            None => warn!("Output {key} of stack {stack_name} is empty, not published"),
+        }
+    }
+    info!(
+        "{} output(s) of stack {stack_name} published under {prefix}",
+        stack.outputs().len()
+    );
+    Ok(())
+}
+
+pub fn write(stack: &Stack, format: OutputsFormat, out: Option<&Path>) -> anyhow::Result<()> {
+    let rendered = format.render(stack);
+    match out {
+        Some(out) => {
+            std::fs::write(out, rendered)
+                .with_context(|| format!("Unable to write the outputs to {}", out.display()))?;
+            info!(
+                "Outputs of stack {} written to {}",
+                stack.stack_name().unwrap_or_default(),
+                out.display()
+            );
+        }
+        None => print!("{rendered}"),
+    }
+    Ok(())
+}
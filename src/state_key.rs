@@ -0,0 +1,187 @@
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, bail, Context};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+    rand::{SecureRandom, SystemRandom},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::cloudformation_api::CloudFormationApi;
+
+const STATE_KEY_ENV: &str = "PKLFORMATION_STATE_KEY";
+const KMS_PREFIX: &str = "kms:";
+const KEY_LEN: usize = 32;
+
+static STATE_KEY: OnceLock<Option<StateKey>> = OnceLock::new();
+
+enum StateKey {
+    // Envelope encryption, KMS generates a data key for each record
+    Kms(String),
+    Local(Vec<u8>),
+}
+
+// What gets written instead of the values, the data key is there when KMS encrypted it
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Sealed {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data_key: Option<String>,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct KmsResponse {
+    plaintext: Option<String>,
+    ciphertext_blob: Option<String>,
+}
+
+impl StateKey {
+    fn parse(value: &str) -> anyhow::Result<Self> {
+        if let Some(key_id) = value.strip_prefix(KMS_PREFIX) {
+            return Ok(StateKey::Kms(key_id.to_string()));
+        }
+        let encoded = std::fs::read_to_string(value)
+            .with_context(|| format!("Unable to read the state key {value}"))?;
+        let key = STANDARD
+            .decode(encoded.trim())
+            .with_context(|| format!("The state key {value} is not base64"))?;
+        if key.len() != KEY_LEN {
+            bail!(
+                "The state key {value} must hold {KEY_LEN} bytes, not {}",
+                key.len()
+            );
+        }
+        Ok(StateKey::Local(key))
+    }
+}
+
+// The flag takes precedence over the environment. Read once, before the first record.
+pub fn configure(key: Option<&str>) -> anyhow::Result<()> {
+    let key = key.map(str::to_string).or_else(|| {
+        std::env::var(STATE_KEY_ENV)
+            .ok()
+            .filter(|key| !key.is_empty())
+    });
+    let _ = STATE_KEY.set(key.as_deref().map(StateKey::parse).transpose()?);
+    Ok(())
+}
+
+fn state_key() -> Option<&'static StateKey> {
+    STATE_KEY.get().and_then(Option::as_ref)
+}
+
+pub fn configured() -> bool {
+    state_key().is_some()
+}
+
+async fn kms(
+    client: &impl CloudFormationApi,
+    key_id: &str,
+    target: &str,
+    request: serde_json::Value,
+) -> anyhow::Result<KmsResponse> {
+    let response = client.kms(key_id, target, request).await?;
+    serde_json::from_value(response).context("Invalid KMS response")
+}
+
+fn decode(value: Option<String>) -> anyhow::Result<Vec<u8>> {
+    Ok(STANDARD.decode(value.context("Incomplete KMS response")?)?)
+}
+
+fn aead_key(key: &[u8]) -> anyhow::Result<LessSafeKey> {
+    let key = UnboundKey::new(&AES_256_GCM, key).map_err(|_| anyhow!("Invalid key"))?;
+    Ok(LessSafeKey::new(key))
+}
+
+// The associated data ties the ciphertext to its record, it can't be moved to another one
+pub async fn seal(
+    client: &impl CloudFormationApi,
+    plaintext: &[u8],
+    associated: &str,
+) -> anyhow::Result<Sealed> {
+    let Some(state_key) = state_key() else {
+        bail!("No state key configured");
+    };
+    let (key, data_key) = match state_key {
+        StateKey::Local(key) => (key.clone(), None),
+        StateKey::Kms(key_id) => {
+            let response = kms(
+                client,
+                key_id,
+                "GenerateDataKey",
+                json!({ "KeyId": key_id, "KeySpec": "AES_256" }),
+            )
+            .await?;
+            (
+                decode(response.plaintext)?,
+                Some(
+                    response
+                        .ciphertext_blob
+                        .context("Incomplete KMS response")?,
+                ),
+            )
+        }
+    };
+    let mut nonce = [0; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| anyhow!("No random source for the nonce"))?;
+    let mut ciphertext = plaintext.to_vec();
+    aead_key(&key)?
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(associated.as_bytes()),
+            &mut ciphertext,
+        )
+        .map_err(|_| anyhow!("Unable to encrypt"))?;
+    Ok(Sealed {
+        data_key,
+        nonce: STANDARD.encode(nonce),
+        ciphertext: STANDARD.encode(ciphertext),
+    })
+}
+
+pub async fn open(
+    client: &impl CloudFormationApi,
+    sealed: &Sealed,
+    associated: &str,
+) -> anyhow::Result<Vec<u8>> {
+    let key = match (state_key(), &sealed.data_key) {
+        (None, _) => bail!("The values are encrypted, --state-key or ${STATE_KEY_ENV} is required"),
+        (Some(StateKey::Local(key)), None) => key.clone(),
+        (Some(StateKey::Kms(key_id)), Some(data_key)) => {
+            let response = kms(
+                client,
+                key_id,
+                "Decrypt",
+                json!({ "KeyId": key_id, "CiphertextBlob": data_key }),
+            )
+            .await?;
+            decode(response.plaintext)?
+        }
+        (Some(StateKey::Local(_)), Some(_)) => {
+            bail!("The values were encrypted with KMS, the state key must be kms:<key id>")
+        }
+        (Some(StateKey::Kms(_)), None) => {
+            bail!("The values were encrypted with a key file, not with KMS")
+        }
+    };
+    let nonce: [u8; NONCE_LEN] = STANDARD
+        .decode(&sealed.nonce)?
+        .try_into()
+        .map_err(|_| anyhow!("Invalid nonce"))?;
+    let mut ciphertext = STANDARD.decode(&sealed.ciphertext)?;
+    let plaintext = aead_key(&key)?
+        .open_in_place(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(associated.as_bytes()),
+            &mut ciphertext,
+        )
+        .map_err(|_| anyhow!("Unable to decrypt the values, wrong state key or altered entry"))?;
+    Ok(plaintext.to_vec())
+}
@@ -0,0 +1,98 @@
+use anyhow::Context;
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+
+use std::path::Path;
+
+use crate::{
+    error::PklformationError,
+    pkl::{self, PklOptions},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum TemplateFormat {
+    Pkl,
+    Json,
+    Yaml,
+}
+
+impl TemplateFormat {
+    pub fn from_extension(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => TemplateFormat::Json,
+            Some("yaml" | "yml") => TemplateFormat::Yaml,
+            _ => TemplateFormat::Pkl,
+        }
+    }
+}
+
+// Short form intrinsic functions like !Ref or !Sub are expanded to their JSON form
+fn yaml_to_json(value: serde_yaml::Value) -> anyhow::Result<Value> {
+    Ok(match value {
+        serde_yaml::Value::Null => Value::Null,
+        serde_yaml::Value::Bool(bool) => Value::Bool(bool),
+        serde_yaml::Value::Number(number) => serde_json::to_value(number)?,
+        serde_yaml::Value::String(string) => Value::String(string),
+        serde_yaml::Value::Sequence(sequence) => Value::Array(
+            sequence
+                .into_iter()
+                .map(yaml_to_json)
+                .collect::<anyhow::Result<_>>()?,
+        ),
+        serde_yaml::Value::Mapping(mapping) => {
+            let mut object = Map::new();
+            for (key, value) in mapping {
+                let key = match key {
+                    serde_yaml::Value::String(key) => key,
+                    key => serde_yaml::to_string(&key)?.trim().to_string(),
+                };
+                object.insert(key, yaml_to_json(value)?);
+            }
+            Value::Object(object)
+        }
+        serde_yaml::Value::Tagged(tagged) => {
+            let tag = tagged.tag.to_string();
+            let function = match tag.trim_start_matches('!') {
+                name @ ("Ref" | "Condition") => name.to_string(),
+                name => format!("Fn::{name}"),
+            };
+            let value = match (function.as_str(), yaml_to_json(tagged.value)?) {
+                // Only the YAML short form accepts "Resource.Attribute"
+                ("Fn::GetAtt", Value::String(target)) => match target.split_once('.') {
+                    Some((logical_id, attribute)) => {
+                        Value::Array(vec![logical_id.into(), attribute.into()])
+                    }
+                    None => Value::String(target),
+                },
+                (_, value) => value,
+            };
+            let mut object = Map::new();
+            object.insert(function, value);
+            Value::Object(object)
+        }
+    })
+}
+
+// Templates are always returned as JSON, whatever their source format
+pub fn load(
+    path: &Path,
+    format: Option<TemplateFormat>,
+    pkl_options: &PklOptions,
+) -> anyhow::Result<String> {
+    match format.unwrap_or_else(|| TemplateFormat::from_extension(path)) {
+        TemplateFormat::Pkl => pkl::eval(path, pkl_options),
+        TemplateFormat::Json => std::fs::read_to_string(path)
+            .with_context(|| format!("Unable to read template {}", path.display())),
+        TemplateFormat::Yaml => {
+            let template = std::fs::read_to_string(path)
+                .with_context(|| format!("Unable to read template {}", path.display()))?;
+            let template: serde_yaml::Value = serde_yaml::from_str(&template).map_err(|err| {
+                PklformationError::Template(format!(
+                    "Invalid YAML template {}: {err}",
+                    path.display()
+                ))
+            })?;
+            Ok(serde_json::to_string(&yaml_to_json(template)?)?)
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use aws_sdk_cloudformation::types::{ResourceStatus, StackEvent};
+use std::{cmp::Reverse, collections::HashMap, time::Duration};
+
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}m{:02}s", seconds / 60, seconds % 60)
+}
+
+pub struct ResourceTiming {
+    pub logical_id: String,
+    pub resource_type: String,
+    pub status: ResourceStatus,
+    pub duration: Duration,
+}
+
+fn in_progress(status: &ResourceStatus) -> bool {
+    status.as_str().ends_with("_IN_PROGRESS")
+}
+
+// Pairs the first IN_PROGRESS event of each resource with the COMPLETE or FAILED event ending
+// it, the slowest resources first. The events of the stack itself are left out.
+pub fn resource_timings(events: &[StackEvent]) -> Vec<ResourceTiming> {
+    let mut started: HashMap<&str, f64> = HashMap::new();
+    let mut timings: HashMap<&str, ResourceTiming> = HashMap::new();
+    // DescribeStackEvents lists the most recent events first
+    for event in events.iter().rev() {
+        let (Some(logical_id), Some(status), Some(timestamp)) = (
+            event.logical_resource_id(),
+            event.resource_status(),
+            event.timestamp(),
+        ) else {
+            continue;
+        };
+        if event.physical_resource_id() == event.stack_id() {
+            continue;
+        }
+        let timestamp = timestamp.as_secs_f64();
+        if in_progress(status) {
+            started.entry(logical_id).or_insert(timestamp);
+        } else if let Some(start) = started.get(logical_id) {
+            timings.insert(
+                logical_id,
+                ResourceTiming {
+                    logical_id: logical_id.to_string(),
+                    resource_type: event.resource_type().unwrap_or_default().to_string(),
+                    status: status.clone(),
+                    duration: Duration::from_secs_f64((timestamp - start).max(0.0)),
+                },
+            );
+        }
+    }
+    let mut timings: Vec<_> = timings.into_values().collect();
+    timings.sort_by_key(|timing| Reverse(timing.duration));
+    timings
+}
@@ -0,0 +1,185 @@
+use anyhow::{anyhow, Context};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use colored::Colorize;
+use ring::signature::{UnparsedPublicKey, ED25519};
+use semver::Version;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::debug;
+
+use crate::proxy;
+
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/techterraformers/pklformation/releases/latest";
+const NO_UPDATE_CHECK_ENV: &str = "PKLFORMATION_NO_UPDATE_CHECK";
+const UPDATE_CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+const NOTICE_TIMEOUT: Duration = Duration::from_secs(2);
+// Ed25519 key the release binaries are signed with, see `openssl pkeyutl -sign -rawin`
+const RELEASE_PUBLIC_KEY: &str = "YP1r1SSAAbd366GYNiBsnSo30GFBNej3HX1mTbwwE2Y=";
+
+#[derive(Debug, Deserialize)]
+pub struct Asset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    pub assets: Vec<Asset>,
+}
+
+fn parse_version(version: &str) -> Option<Version> {
+    Version::parse(version.trim_start_matches('v')).ok()
+}
+
+impl Release {
+    pub fn version(&self) -> &str {
+        self.tag_name.trim_start_matches('v')
+    }
+
+    pub fn is_newer(&self) -> bool {
+        match (
+            parse_version(self.version()),
+            parse_version(CURRENT_VERSION),
+        ) {
+            (Some(latest), Some(current)) => latest > current,
+            _ => {
+                debug!(
+                    "Unable to compare {} with {CURRENT_VERSION}",
+                    self.version()
+                );
+                false
+            }
+        }
+    }
+
+    pub fn asset(&self, name: &str) -> Option<&Asset> {
+        self.assets.iter().find(|asset| asset.name == name)
+    }
+}
+
+pub fn platform_asset_name() -> String {
+    format!(
+        "pklformation-{}-{}{}",
+        std::env::consts::ARCH,
+        std::env::consts::OS,
+        std::env::consts::EXE_SUFFIX
+    )
+}
+
+fn http_client(timeout: Option<Duration>) -> anyhow::Result<reqwest::Client> {
+    let mut builder =
+        proxy::reqwest_client_builder().user_agent(format!("pklformation/{CURRENT_VERSION}"));
+    if let Some(timeout) = timeout {
+        builder = builder.timeout(timeout);
+    }
+    Ok(builder.build()?)
+}
+
+async fn fetch_latest_release(timeout: Option<Duration>) -> anyhow::Result<Release> {
+    let release = http_client(timeout)?
+        .get(LATEST_RELEASE_URL)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Release>()
+        .await
+        .context("Invalid release description")?;
+    debug!("Latest release: {release:?}");
+    Ok(release)
+}
+
+pub async fn latest_release() -> anyhow::Result<Release> {
+    fetch_latest_release(None).await
+}
+
+pub async fn download(url: &str) -> anyhow::Result<Vec<u8>> {
+    let bytes = http_client(None)?
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    Ok(bytes.to_vec())
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+pub fn verify_signature(binary: &[u8], signature: &str) -> anyhow::Result<()> {
+    let public_key = STANDARD
+        .decode(RELEASE_PUBLIC_KEY)
+        .context("Invalid release public key")?;
+    let signature = STANDARD
+        .decode(signature.trim())
+        .context("The release signature is not base64")?;
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(binary, &signature)
+        .map_err(|_| anyhow!("Invalid release signature"))
+}
+
+pub(crate) fn cache_file(name: &str) -> Option<PathBuf> {
+    let cache_dir = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(cache_dir.join("pklformation").join(name))
+}
+
+fn last_check_file() -> Option<PathBuf> {
+    cache_file("last-update-check")
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn update_check_due(last_check_file: &Path) -> bool {
+    std::fs::read_to_string(last_check_file)
+        .ok()
+        .and_then(|last_check| last_check.trim().parse::<u64>().ok())
+        .is_none_or(|last_check| {
+            now_secs().saturating_sub(last_check) >= UPDATE_CHECK_INTERVAL.as_secs()
+        })
+}
+
+pub async fn notify_new_version() {
+    if std::env::var_os(NO_UPDATE_CHECK_ENV).is_some() {
+        return;
+    }
+    let Some(last_check_file) = last_check_file() else {
+        return;
+    };
+    if !update_check_due(&last_check_file) {
+        return;
+    }
+
+    let _ = std::fs::create_dir_all(last_check_file.parent().unwrap());
+    let _ = std::fs::write(&last_check_file, now_secs().to_string());
+    match fetch_latest_release(Some(NOTICE_TIMEOUT)).await {
+        Ok(release) if release.is_newer() => eprintln!(
+            "{}",
+            format!(
+                "pklformation {} is available (current {CURRENT_VERSION}), run `pklformation self-update` to upgrade",
+                release.version()
+            )
+            .yellow()
+        ),
+        Ok(_) => {}
+        Err(err) => debug!("Update check failed: {err}"),
+    }
+}
@@ -0,0 +1,233 @@
+use anyhow::Context;
+use aws_sdk_cloudformation::{
+    operation::describe_change_set::DescribeChangeSetOutput,
+    types::{ChangeAction, Replacement},
+};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::intrinsics;
+
+pub(crate) const MAX_TEMPLATE_BODY_BYTES: usize = 51_200;
+pub(crate) const MAX_TEMPLATE_S3_BYTES: usize = 1_048_576;
+pub(crate) const MAX_RESOURCES: usize = 500;
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NamingRules {
+    pub outputs: Option<String>,
+    pub exports: Option<String>,
+    pub logical_ids: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Violation {
+    pub rule: &'static str,
+    pub location: String,
+    pub message: String,
+}
+
+impl Violation {
+    pub fn is_replacement(&self) -> bool {
+        self.rule == "guardrail/replacement"
+    }
+}
+
+#[derive(Clone)]
+pub struct Validator {
+    outputs: Option<Regex>,
+    exports: Option<Regex>,
+    logical_ids: Option<Regex>,
+    immutable_resources: Vec<String>,
+    protected_resource_types: Vec<String>,
+}
+
+// A rule matches the whole name, `[A-Z][A-Za-z]+` doesn't accept `MyBucket-1`
+fn compile(pattern: Option<&str>, rule: &str) -> anyhow::Result<Option<Regex>> {
+    pattern
+        .map(|p| {
+            Regex::new(&format!("^(?:{p})$"))
+                .with_context(|| format!("Invalid {rule} naming rule: {p}"))
+        })
+        .transpose()
+}
+
+fn check_name(
+    violations: &mut Vec<Violation>,
+    regex: Option<&Regex>,
+    rule: &'static str,
+    location: String,
+    name: &str,
+) {
+    if let Some(regex) = regex {
+        if !regex.is_match(name) {
+            violations.push(Violation {
+                rule,
+                location,
+                message: format!("{name:?} does not match {:?}", regex.as_str()),
+            });
+        }
+    }
+}
+
+fn check_structure(template: &Value, template_size: usize, violations: &mut Vec<Violation>) {
+    // Templates are sent inline, the S3 limit only matters to explain how far off they are
+    if template_size > MAX_TEMPLATE_BODY_BYTES {
+        let message = if template_size > MAX_TEMPLATE_S3_BYTES {
+            format!("{template_size} bytes, over the {MAX_TEMPLATE_BODY_BYTES} bytes inline limit and the {MAX_TEMPLATE_S3_BYTES} bytes S3 limit")
+        } else {
+            format!("{template_size} bytes, over the {MAX_TEMPLATE_BODY_BYTES} bytes inline limit")
+        };
+        violations.push(Violation {
+            rule: "structure/size",
+            location: "Template".to_string(),
+            message,
+        });
+    }
+
+    match template.get("Resources") {
+        Some(Value::Object(resources)) if resources.is_empty() => violations.push(Violation {
+            rule: "structure/resources",
+            location: "Resources".to_string(),
+            message: "At least one resource is required".to_string(),
+        }),
+        Some(Value::Object(resources)) if resources.len() > MAX_RESOURCES => {
+            violations.push(Violation {
+                rule: "structure/resources",
+                location: "Resources".to_string(),
+                message: format!(
+                    "{} resources, over the limit of {MAX_RESOURCES}",
+                    resources.len()
+                ),
+            })
+        }
+        Some(Value::Object(_)) => {}
+        Some(_) => violations.push(Violation {
+            rule: "structure/resources",
+            location: "Resources".to_string(),
+            message: "Resources must be an object".to_string(),
+        }),
+        None => violations.push(Violation {
+            rule: "structure/resources",
+            location: "Template".to_string(),
+            message: "Missing Resources section".to_string(),
+        }),
+    }
+}
+
+impl Validator {
+    pub fn new(naming: &NamingRules) -> anyhow::Result<Self> {
+        Ok(Self {
+            outputs: compile(naming.outputs.as_deref(), "outputs")?,
+            exports: compile(naming.exports.as_deref(), "exports")?,
+            logical_ids: compile(naming.logical_ids.as_deref(), "logical ids")?,
+            immutable_resources: Vec::new(),
+            protected_resource_types: Vec::new(),
+        })
+    }
+
+    pub fn with_immutable_resources(mut self, immutable_resources: Vec<String>) -> Self {
+        self.immutable_resources = immutable_resources;
+        self
+    }
+
+    pub fn with_protected_resource_types(mut self, protected_resource_types: Vec<String>) -> Self {
+        self.protected_resource_types = protected_resource_types;
+        self
+    }
+
+    pub fn validate_change_set(&self, change_set: &DescribeChangeSetOutput) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        for rc in change_set
+            .changes()
+            .iter()
+            .filter_map(|c| c.resource_change.as_ref())
+        {
+            let (Some(logical_id), Some(action)) = (rc.logical_resource_id(), rc.action()) else {
+                continue;
+            };
+            let replacement = rc.replacement().unwrap_or(&Replacement::False);
+            if matches!(action, ChangeAction::Add | ChangeAction::Import) {
+                continue;
+            }
+            if self.immutable_resources.iter().any(|id| id == logical_id) {
+                violations.push(Violation {
+                    rule: "guardrail/immutable",
+                    location: format!("Resources.{logical_id}"),
+                    message: format!(
+                        "{action:?} of an immutable resource (replacement: {replacement:?})"
+                    ),
+                });
+            }
+
+            let resource_type = rc.resource_type().unwrap_or_default();
+            let replaced = *action == ChangeAction::Remove || *replacement == Replacement::True;
+            if replaced
+                && self
+                    .protected_resource_types
+                    .iter()
+                    .any(|protected| protected == resource_type)
+            {
+                violations.push(Violation {
+                    rule: "guardrail/replacement",
+                    location: format!("Resources.{logical_id}"),
+                    message: format!(
+                        "{action:?} of a {resource_type} (replacement: {replacement:?})"
+                    ),
+                });
+            }
+        }
+        violations
+    }
+
+    pub fn validate(&self, template: &str) -> anyhow::Result<Vec<Violation>> {
+        let template_size = template.len();
+        let template: Value = serde_json::from_str(template).context("Invalid template JSON")?;
+        let mut violations = Vec::new();
+        check_structure(&template, template_size, &mut violations);
+        self.check_naming(&template, &mut violations);
+        intrinsics::check_references(&template, &mut violations);
+        Ok(violations)
+    }
+
+    fn check_naming(&self, template: &Value, violations: &mut Vec<Violation>) {
+        for section in ["Parameters", "Resources"] {
+            let Some(entries) = template.get(section).and_then(Value::as_object) else {
+                continue;
+            };
+            for logical_id in entries.keys() {
+                check_name(
+                    violations,
+                    self.logical_ids.as_ref(),
+                    "naming/logical-id",
+                    format!("{section}.{logical_id}"),
+                    logical_id,
+                );
+            }
+        }
+
+        let Some(outputs) = template.get("Outputs").and_then(Value::as_object) else {
+            return;
+        };
+        for (name, output) in outputs {
+            check_name(
+                violations,
+                self.outputs.as_ref(),
+                "naming/output",
+                format!("Outputs.{name}"),
+                name,
+            );
+            // Export names built with intrinsic functions can't be checked before deploy
+            if let Some(export_name) = output.pointer("/Export/Name").and_then(Value::as_str) {
+                check_name(
+                    violations,
+                    self.exports.as_ref(),
+                    "naming/export",
+                    format!("Outputs.{name}.Export.Name"),
+                    export_name,
+                );
+            }
+        }
+    }
+}
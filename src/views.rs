@@ -0,0 +1,561 @@
+use aws_sdk_cloudformation::{
+    operation::describe_change_set::DescribeChangeSetOutput,
+    primitives::DateTime,
+    types::{
+        ChangeAction, ChangeSetStatus, ExecutionStatus, Export, Replacement, RequiresRecreation,
+        ResourceChange, ResourceStatus, Stack, StackDriftStatus, StackEvent, StackResourceDetail,
+        StackResourceDriftStatus, StackResourceSummary, StackStatus, StackSummary,
+    },
+};
+use serde::{Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::{nested_stacks::NESTED_STACK_TYPE, partition, validation::Violation};
+
+// The SDK types are not serializable, these views are what both the text and the JSON
+// renderers print, with the SDK enums serialized as their CloudFormation names
+fn as_str<S: Serializer, T: AsRef<str>>(
+    value: &Option<T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(value) => serializer.serialize_str(value.as_ref()),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn timestamp(date_time: Option<&DateTime>) -> Option<String> {
+    date_time.map(DateTime::to_string)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StackSummaryView {
+    pub name: String,
+    #[serde(serialize_with = "as_str")]
+    pub status: Option<StackStatus>,
+    pub status_reason: Option<String>,
+    pub creation_time: Option<String>,
+    pub last_updated_time: Option<String>,
+    #[serde(serialize_with = "as_str")]
+    pub drift_status: Option<StackDriftStatus>,
+    pub description: Option<String>,
+}
+
+impl From<&StackSummary> for StackSummaryView {
+    fn from(stack: &StackSummary) -> Self {
+        Self {
+            name: stack.stack_name().unwrap_or_default().to_string(),
+            status: stack.stack_status().cloned(),
+            status_reason: stack.stack_status_reason().map(str::to_string),
+            creation_time: timestamp(stack.creation_time()),
+            last_updated_time: timestamp(stack.last_updated_time()),
+            drift_status: stack
+                .drift_information()
+                .and_then(|drift| drift.stack_drift_status())
+                .cloned(),
+            description: stack.template_description().map(str::to_string),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StackTreeView {
+    #[serde(flatten)]
+    pub stack: StackSummaryView,
+    pub nested: Vec<StackTreeView>,
+}
+
+impl StackTreeView {
+    // Nested stacks go under their parent, the stacks whose parent is not listed are roots
+    pub fn build(stacks: &[StackSummary]) -> Vec<Self> {
+        let ids: HashSet<_> = stacks.iter().filter_map(|stack| stack.stack_id()).collect();
+        let mut roots = Vec::new();
+        let mut children: HashMap<&str, Vec<&StackSummary>> = HashMap::new();
+        for stack in stacks {
+            match stack
+                .parent_id()
+                .filter(|parent_id| ids.contains(parent_id))
+            {
+                Some(parent_id) => children.entry(parent_id).or_default().push(stack),
+                None => roots.push(stack),
+            }
+        }
+        roots
+            .into_iter()
+            .map(|root| Self::node(root, &children))
+            .collect()
+    }
+
+    fn node(stack: &StackSummary, children: &HashMap<&str, Vec<&StackSummary>>) -> Self {
+        Self {
+            stack: StackSummaryView::from(stack),
+            nested: children
+                .get(stack.stack_id().unwrap_or_default())
+                .map(|nested| {
+                    nested
+                        .iter()
+                        .map(|child| Self::node(child, children))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StackView {
+    pub name: String,
+    pub id: Option<String>,
+    pub parent_id: Option<String>,
+    pub console_url: Option<String>,
+    pub description: Option<String>,
+    pub creation_time: Option<String>,
+    pub last_updated_time: Option<String>,
+    pub termination_protection: Option<bool>,
+    #[serde(serialize_with = "as_str")]
+    pub status: Option<StackStatus>,
+    pub status_reason: Option<String>,
+    #[serde(serialize_with = "as_str")]
+    pub drift_status: Option<StackDriftStatus>,
+    pub drift_last_check_time: Option<String>,
+    pub parameters: BTreeMap<String, String>,
+    pub outputs: Vec<OutputView>,
+    pub tags: BTreeMap<String, String>,
+    pub capabilities: Vec<String>,
+    pub rollback_configuration: Option<RollbackConfigurationView>,
+    pub notification_arns: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputView {
+    pub key: String,
+    pub value: Option<String>,
+    pub description: Option<String>,
+    pub export_name: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RollbackConfigurationView {
+    pub monitoring_time_in_minutes: Option<i32>,
+    pub triggers: Vec<RollbackTriggerView>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RollbackTriggerView {
+    pub arn: String,
+    pub r#type: String,
+}
+
+impl From<&Stack> for StackView {
+    fn from(stack: &Stack) -> Self {
+        Self {
+            name: stack.stack_name().unwrap_or_default().to_string(),
+            id: stack.stack_id().map(str::to_string),
+            parent_id: stack.parent_id().map(str::to_string),
+            console_url: stack.stack_id().and_then(partition::stack_console_url),
+            description: stack.description().map(str::to_string),
+            creation_time: timestamp(stack.creation_time()),
+            last_updated_time: timestamp(stack.last_updated_time()),
+            termination_protection: stack.enable_termination_protection(),
+            status: stack.stack_status().cloned(),
+            status_reason: stack.stack_status_reason().map(str::to_string),
+            drift_status: stack
+                .drift_information()
+                .and_then(|drift| drift.stack_drift_status())
+                .cloned(),
+            drift_last_check_time: timestamp(
+                stack
+                    .drift_information()
+                    .and_then(|drift| drift.last_check_timestamp()),
+            ),
+            parameters: stack
+                .parameters()
+                .iter()
+                .map(|parameter| {
+                    (
+                        parameter.parameter_key().unwrap_or_default().to_string(),
+                        parameter.parameter_value().unwrap_or_default().to_string(),
+                    )
+                })
+                .collect(),
+            outputs: stack
+                .outputs()
+                .iter()
+                .map(|output| OutputView {
+                    key: output.output_key().unwrap_or_default().to_string(),
+                    value: output.output_value().map(str::to_string),
+                    description: output.description().map(str::to_string),
+                    export_name: output.export_name().map(str::to_string),
+                })
+                .collect(),
+            tags: stack
+                .tags()
+                .iter()
+                .map(|tag| {
+                    (
+                        tag.key().unwrap_or_default().to_string(),
+                        tag.value().unwrap_or_default().to_string(),
+                    )
+                })
+                .collect(),
+            capabilities: stack
+                .capabilities()
+                .iter()
+                .map(|capability| capability.as_str().to_string())
+                .collect(),
+            // An empty configuration is what CloudFormation returns when none was set
+            rollback_configuration: stack
+                .rollback_configuration()
+                .filter(|rollback| {
+                    rollback.monitoring_time_in_minutes().is_some()
+                        || !rollback.rollback_triggers().is_empty()
+                })
+                .map(|rollback| RollbackConfigurationView {
+                    monitoring_time_in_minutes: rollback.monitoring_time_in_minutes(),
+                    triggers: rollback
+                        .rollback_triggers()
+                        .iter()
+                        .map(|trigger| RollbackTriggerView {
+                            arn: trigger.arn().unwrap_or_default().to_string(),
+                            r#type: trigger.r#type().unwrap_or_default().to_string(),
+                        })
+                        .collect(),
+                }),
+            notification_arns: stack.notification_arns().to_vec(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportView {
+    pub name: String,
+    pub value: Option<String>,
+    pub exporting_stack: Option<String>,
+    pub exporting_stack_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub importers: Option<Vec<String>>,
+}
+
+impl ExportView {
+    pub fn new(export: &Export, importers: Option<Vec<String>>) -> Self {
+        Self {
+            name: export.name().unwrap_or_default().to_string(),
+            value: export.value().map(str::to_string),
+            exporting_stack: export.exporting_stack_id().and_then(stack_name_from_id),
+            exporting_stack_id: export.exporting_stack_id().map(str::to_string),
+            importers,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateStatsView {
+    pub resources: usize,
+    pub resources_by_type: BTreeMap<String, usize>,
+    pub nested_stacks: usize,
+    pub parameters: usize,
+    pub conditions: usize,
+    pub mappings: usize,
+    pub outputs: usize,
+    pub size_bytes: usize,
+    pub minified_size_bytes: usize,
+}
+
+impl TemplateStatsView {
+    pub fn new(template: &serde_json::Value, size_bytes: usize) -> Self {
+        let section_len = |section: &str| {
+            template
+                .get(section)
+                .and_then(serde_json::Value::as_object)
+                .map_or(0, |section| section.len())
+        };
+        let mut resources_by_type = BTreeMap::new();
+        if let Some(resources) = template
+            .get("Resources")
+            .and_then(serde_json::Value::as_object)
+        {
+            for resource in resources.values() {
+                let resource_type = resource
+                    .get("Type")
+                    .and_then(serde_json::Value::as_str)
+                    .unwrap_or("UNKNOWN");
+                *resources_by_type
+                    .entry(resource_type.to_string())
+                    .or_default() += 1;
+            }
+        }
+        Self {
+            resources: section_len("Resources"),
+            nested_stacks: resources_by_type
+                .get(NESTED_STACK_TYPE)
+                .copied()
+                .unwrap_or_default(),
+            resources_by_type,
+            parameters: section_len("Parameters"),
+            conditions: section_len("Conditions"),
+            mappings: section_len("Mappings"),
+            outputs: section_len("Outputs"),
+            size_bytes,
+            minified_size_bytes: template.to_string().len(),
+        }
+    }
+}
+
+// Stack ids are ARNs ending with stack/NAME/UUID
+pub fn stack_name_from_id(stack_id: &str) -> Option<String> {
+    stack_id
+        .split(':')
+        .nth(5)?
+        .split('/')
+        .nth(1)
+        .map(str::to_string)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StackResourceView {
+    pub logical_id: Option<String>,
+    pub physical_id: Option<String>,
+    pub resource_type: Option<String>,
+    #[serde(serialize_with = "as_str")]
+    pub status: Option<ResourceStatus>,
+    pub status_reason: Option<String>,
+    pub last_updated_time: Option<String>,
+    pub console_url: Option<String>,
+}
+
+impl StackResourceView {
+    // Resource summaries don't carry the id of their stack
+    pub fn new(resource: &StackResourceSummary, stack_id: Option<&str>) -> Self {
+        Self {
+            logical_id: resource.logical_resource_id().map(str::to_string),
+            physical_id: resource.physical_resource_id().map(str::to_string),
+            resource_type: resource.resource_type().map(str::to_string),
+            status: resource.resource_status().cloned(),
+            status_reason: resource.resource_status_reason().map(str::to_string),
+            last_updated_time: timestamp(resource.last_updated_timestamp()),
+            console_url: stack_id.zip(resource.logical_resource_id()).and_then(
+                |(stack_id, logical_id)| partition::resource_console_url(stack_id, logical_id),
+            ),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StackEventView {
+    pub timestamp: Option<String>,
+    pub logical_id: Option<String>,
+    pub physical_id: Option<String>,
+    pub resource_type: Option<String>,
+    #[serde(serialize_with = "as_str")]
+    pub status: Option<ResourceStatus>,
+    pub status_reason: Option<String>,
+}
+
+impl From<&StackEvent> for StackEventView {
+    fn from(event: &StackEvent) -> Self {
+        Self {
+            timestamp: timestamp(event.timestamp()),
+            logical_id: event.logical_resource_id().map(str::to_string),
+            physical_id: event.physical_resource_id().map(str::to_string),
+            resource_type: event.resource_type().map(str::to_string),
+            status: event.resource_status().cloned(),
+            status_reason: event.resource_status_reason().map(str::to_string),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StackResourceDetailView {
+    pub stack_name: Option<String>,
+    pub stack_id: Option<String>,
+    pub logical_id: Option<String>,
+    pub physical_id: Option<String>,
+    pub resource_type: Option<String>,
+    #[serde(serialize_with = "as_str")]
+    pub status: Option<ResourceStatus>,
+    pub status_reason: Option<String>,
+    pub description: Option<String>,
+    pub last_updated_time: Option<String>,
+    #[serde(serialize_with = "as_str")]
+    pub drift_status: Option<StackResourceDriftStatus>,
+    pub drift_last_check_time: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+    pub console_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub properties: Option<serde_json::Value>,
+}
+
+impl StackResourceDetailView {
+    pub fn new(resource: &StackResourceDetail, properties: Option<serde_json::Value>) -> Self {
+        Self {
+            stack_name: resource.stack_name().map(str::to_string),
+            stack_id: resource.stack_id().map(str::to_string),
+            logical_id: resource.logical_resource_id().map(str::to_string),
+            physical_id: resource.physical_resource_id().map(str::to_string),
+            resource_type: resource.resource_type().map(str::to_string),
+            status: resource.resource_status().cloned(),
+            status_reason: resource.resource_status_reason().map(str::to_string),
+            description: resource.description().map(str::to_string),
+            last_updated_time: timestamp(resource.last_updated_timestamp()),
+            drift_status: resource
+                .drift_information()
+                .and_then(|drift| drift.stack_resource_drift_status())
+                .cloned(),
+            drift_last_check_time: timestamp(
+                resource
+                    .drift_information()
+                    .and_then(|drift| drift.last_check_timestamp()),
+            ),
+            // The metadata is a JSON document, kept as a string when it can't be parsed
+            metadata: resource.metadata().map(|metadata| {
+                serde_json::from_str(metadata)
+                    .unwrap_or_else(|_| serde_json::Value::String(metadata.to_string()))
+            }),
+            console_url: resource
+                .stack_id()
+                .zip(resource.logical_resource_id())
+                .and_then(|(stack_id, logical_id)| {
+                    partition::resource_console_url(stack_id, logical_id)
+                }),
+            properties,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StackDescriptionView {
+    pub stack: StackView,
+    pub resources: Vec<StackResourceView>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeDetailView {
+    pub attribute: Option<String>,
+    pub name: Option<String>,
+    #[serde(serialize_with = "as_str")]
+    pub requires_recreation: Option<RequiresRecreation>,
+    pub causing_entity: Option<String>,
+    pub change_source: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceChangeView {
+    #[serde(serialize_with = "as_str")]
+    pub action: Option<ChangeAction>,
+    pub logical_id: Option<String>,
+    pub physical_id: Option<String>,
+    pub resource_type: Option<String>,
+    #[serde(serialize_with = "as_str")]
+    pub replacement: Option<Replacement>,
+    pub scope: Vec<String>,
+    pub details: Vec<ChangeDetailView>,
+}
+
+impl From<&ResourceChange> for ResourceChangeView {
+    fn from(rc: &ResourceChange) -> Self {
+        Self {
+            action: rc.action().cloned(),
+            logical_id: rc.logical_resource_id().map(str::to_string),
+            physical_id: rc.physical_resource_id().map(str::to_string),
+            resource_type: rc.resource_type().map(str::to_string),
+            replacement: rc.replacement().cloned(),
+            scope: rc.scope().iter().map(|s| s.as_str().to_string()).collect(),
+            details: rc
+                .details()
+                .iter()
+                .map(|detail| ChangeDetailView {
+                    attribute: detail
+                        .target()
+                        .and_then(|target| target.attribute())
+                        .map(|attribute| attribute.as_str().to_string()),
+                    name: detail
+                        .target()
+                        .and_then(|target| target.name())
+                        .map(str::to_string),
+                    requires_recreation: detail
+                        .target()
+                        .and_then(|target| target.requires_recreation())
+                        .cloned(),
+                    causing_entity: detail.causing_entity().map(str::to_string),
+                    change_source: detail
+                        .change_source()
+                        .map(|change_source| change_source.as_str().to_string()),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeSetView {
+    pub name: Option<String>,
+    pub id: Option<String>,
+    pub stack_name: Option<String>,
+    pub description: Option<String>,
+    #[serde(serialize_with = "as_str")]
+    pub status: Option<ChangeSetStatus>,
+    pub status_reason: Option<String>,
+    #[serde(serialize_with = "as_str")]
+    pub execution_status: Option<ExecutionStatus>,
+    pub changes: Vec<ResourceChangeView>,
+}
+
+impl From<&DescribeChangeSetOutput> for ChangeSetView {
+    fn from(change_set: &DescribeChangeSetOutput) -> Self {
+        Self {
+            name: change_set.change_set_name().map(str::to_string),
+            id: change_set.change_set_id().map(str::to_string),
+            stack_name: change_set.stack_name().map(str::to_string),
+            description: change_set.description().map(str::to_string),
+            status: change_set.status().cloned(),
+            status_reason: change_set.status_reason().map(str::to_string),
+            execution_status: change_set.execution_status().cloned(),
+            changes: change_set
+                .changes()
+                .iter()
+                .filter_map(|change| change.resource_change())
+                .map(ResourceChangeView::from)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViolationView {
+    pub rule: &'static str,
+    pub location: String,
+    pub message: String,
+}
+
+impl From<&Violation> for ViolationView {
+    fn from(violation: &Violation) -> Self {
+        Self {
+            rule: violation.rule,
+            location: violation.location.clone(),
+            message: violation.message.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeSetReviewView {
+    pub change_set: ChangeSetView,
+    pub violations: Vec<ViolationView>,
+}
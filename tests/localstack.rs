@@ -0,0 +1,217 @@
+#![cfg(feature = "integration-tests")]
+
+// End-to-end runs of up and destroy against LocalStack, started in docker for each test:
+// cargo test --features integration-tests --test localstack
+
+use std::{
+    path::PathBuf,
+    process::Command,
+    time::{Duration, Instant},
+};
+
+use aws_sdk_cloudformation::types::StackStatus;
+use pklformation::{
+    aws_client::AwsClient,
+    cli::{
+        ApprovalConfig, ApprovalHook, ChangeSetOptions, DestroyCommand, DestroyOptions, PklOptions,
+        UpCommand, UpOptions,
+    },
+    error::PklformationError,
+    NamingRules, Validator,
+};
+
+const LOCALSTACK_IMAGE: &str = "localstack/localstack:3";
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(120);
+const POOL_INTERVAL: Duration = Duration::from_secs(1);
+
+// Each test has its own container, its clients are pointed at it so the tests can run together
+struct LocalStack {
+    container_id: String,
+    endpoint: String,
+}
+
+impl LocalStack {
+    async fn start() -> Self {
+        let output = Command::new("docker")
+            .args(["run", "--detach", "--rm", "--publish", "127.0.0.1::4566"])
+            .arg(LOCALSTACK_IMAGE)
+            .output()
+            .expect("docker is required for the integration tests");
+        assert!(
+            output.status.success(),
+            "Unable to start LocalStack: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let container_id = String::from_utf8(output.stdout).unwrap().trim().to_string();
+
+        let output = Command::new("docker")
+            .args(["port", &container_id, "4566/tcp"])
+            .output()
+            .unwrap();
+        let address = String::from_utf8(output.stdout).unwrap();
+        let localstack = Self {
+            container_id,
+            endpoint: format!("http://{}", address.lines().next().unwrap().trim()),
+        };
+        localstack.wait_until_ready().await;
+        localstack
+    }
+
+    async fn wait_until_ready(&self) {
+        let started = Instant::now();
+        while started.elapsed() < STARTUP_TIMEOUT {
+            let health = reqwest::get(format!("{}/_localstack/health", self.endpoint)).await;
+            if let Ok(health) = health {
+                if let Ok(health) = health.json::<serde_json::Value>().await {
+                    let cloudformation = health["services"]["cloudformation"].as_str();
+                    if matches!(cloudformation, Some("available" | "running")) {
+                        return;
+                    }
+                }
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+        panic!("LocalStack not ready after {STARTUP_TIMEOUT:?}");
+    }
+
+    async fn client(&self) -> AwsClient {
+        AwsClient::builder()
+            .endpoint_url(&self.endpoint)
+            .region("us-east-1")
+            .credentials("test", "test")
+            .build()
+            .await
+    }
+
+    // The approval hook stands in for the interactive confirmation, `true` approves every change set
+    async fn up(
+        &self,
+        stack: &str,
+        template: &str,
+        properties: &[(&str, &str)],
+        yes: bool,
+    ) -> Result<StackStatus, PklformationError> {
+        let options = UpOptions {
+            pkl: PklOptions {
+                properties: properties
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect(),
+                ..Default::default()
+            },
+            yes,
+            approval: Some(ApprovalConfig {
+                hook: ApprovalHook::Command {
+                    command: "true".to_string(),
+                    args: Vec::new(),
+                },
+                replace_confirm: true,
+            }),
+            ..Default::default()
+        };
+        UpCommand::new(
+            self.client().await,
+            stack.to_string(),
+            fixture(template),
+            POOL_INTERVAL,
+            Validator::new(&NamingRules::default()).unwrap(),
+            ChangeSetOptions::default(),
+            options,
+        )
+        .run()
+        .await
+    }
+
+    async fn destroy(&self, stack: &str) -> Result<(), PklformationError> {
+        let options = DestroyOptions {
+            yes: true,
+            ..Default::default()
+        };
+        DestroyCommand::new(
+            self.client().await,
+            stack.to_string(),
+            POOL_INTERVAL,
+            options,
+        )
+        .run()
+        .await
+    }
+
+    async fn stack_status(&self, stack: &str) -> Option<StackStatus> {
+        self.client()
+            .await
+            .stack_status(stack)
+            .await
+            .ok()
+            .map(|(status, _)| status)
+    }
+}
+
+impl Drop for LocalStack {
+    fn drop(&mut self) {
+        let _ = Command::new("docker")
+            .args(["stop", &self.container_id])
+            .output();
+    }
+}
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+#[tokio::test]
+async fn up_creates_updates_and_destroy_deletes() {
+    let localstack = LocalStack::start().await;
+
+    let status = localstack.up("queue", "queue.pkl", &[], false).await;
+    assert_eq!(status.unwrap(), StackStatus::CreateComplete);
+
+    let timeout = [("visibilityTimeout", "60")];
+    let status = localstack.up("queue", "queue.pkl", &timeout, false).await;
+    assert_eq!(status.unwrap(), StackStatus::UpdateComplete);
+
+    // Nothing changed, the change set is deleted and the stack left as is
+    let status = localstack.up("queue", "queue.pkl", &timeout, false).await;
+    assert_eq!(status.unwrap(), StackStatus::UpdateComplete);
+
+    localstack.destroy("queue").await.unwrap();
+    assert!(matches!(
+        localstack.stack_status("queue").await,
+        None | Some(StackStatus::DeleteComplete)
+    ));
+}
+
+#[tokio::test]
+async fn failed_creation_must_be_recovered() {
+    let localstack = LocalStack::start().await;
+
+    let err = localstack
+        .up("failing", "failing.pkl", &[], false)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(
+            err,
+            PklformationError::StackFailed {
+                status: StackStatus::CreateFailed | StackStatus::RollbackComplete,
+                ..
+            }
+        ),
+        "unexpected error {err:?}"
+    );
+    assert_eq!(err.exit_code(), 7);
+
+    // The stack can't be updated anymore, up refuses to recreate it without --yes
+    let err = localstack
+        .up("failing", "queue.pkl", &[], false)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, PklformationError::Aborted(_)), "{err:?}");
+
+    let status = localstack.up("failing", "queue.pkl", &[], true).await;
+    assert_eq!(status.unwrap(), StackStatus::CreateComplete);
+
+    localstack.destroy("failing").await.unwrap();
+}